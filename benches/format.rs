@@ -0,0 +1,89 @@
+//! Measures allocations made while formatting a large batch of paths.
+//!
+//! Run with `cargo bench --bench format`. The naive formatter reproduces the
+//! previous `push_str(&format!(..))` approach for comparison.
+
+mod common;
+
+use std::time::Instant;
+use structpath::{Segment, SegmentKey, Structpath};
+
+const PATH_COUNT: usize = 100_000;
+
+fn build_paths() -> Vec<Structpath> {
+    (0..PATH_COUNT)
+        .map(|i| {
+            let mut path = Structpath::new();
+            path.push_string_key("users");
+            path.push_index(i);
+            path.push_string_key("addresses");
+            path.push_index(i % 7);
+            path.push_int_key(i as i64);
+            path.push_index_variable("idx").unwrap();
+            path
+        })
+        .collect()
+}
+
+fn naive_to_string(path: &Structpath) -> String {
+    let mut result = String::from("$");
+    let mut first = true;
+    for segment in path.segments() {
+        match segment {
+            Segment::Key(key) => {
+                if !first {
+                    result.push('.');
+                }
+                match key {
+                    SegmentKey::String(s) => result.push_str(s),
+                    SegmentKey::Int(i) => result.push_str(&i.to_string()),
+                }
+            }
+            Segment::Index(idx) => result.push_str(&format!("[{}]", idx)),
+            Segment::KeyVariable(name) => {
+                if !first {
+                    result.push('.');
+                }
+                result.push_str(&format!("#{}", name));
+            }
+            Segment::IndexVariable(name) => {
+                result.push_str(&format!("[#{}]", name))
+            }
+            _ => unreachable!("not produced by build_paths"),
+        }
+        first = false;
+    }
+    result
+}
+
+fn measure(
+    label: &str,
+    paths: &[Structpath],
+    f: impl Fn(&Structpath) -> String,
+) {
+    let allocations_before = common::allocations();
+    let start = Instant::now();
+
+    let mut total_len = 0;
+    for path in paths {
+        total_len += f(path).len();
+    }
+
+    let elapsed = start.elapsed();
+    let allocations = common::allocations() - allocations_before;
+    println!(
+        "{:<8} {:>10} allocations ({:.2} per path), {:?}, {} bytes",
+        label,
+        allocations,
+        allocations as f64 / paths.len() as f64,
+        elapsed,
+        total_len
+    );
+}
+
+fn main() {
+    let paths = build_paths();
+
+    measure("naive", &paths, naive_to_string);
+    measure("format", &paths, |path| path.to_string());
+}
@@ -0,0 +1,53 @@
+//! Measures allocations made while resolving paths with `get`.
+//!
+//! Run with `cargo bench --bench get`. Successful lookups through string and
+//! integer keys and indices are expected to make no allocations at all.
+
+mod common;
+
+use serde_json::{json, Value};
+use std::time::Instant;
+use structpath::Structpath;
+
+const LOOKUP_COUNT: usize = 100_000;
+
+fn measure(label: &str, path: &Structpath, data: &Value) -> usize {
+    let allocations_before = common::allocations();
+    let start = Instant::now();
+
+    let mut found = 0;
+    for _ in 0..LOOKUP_COUNT {
+        if path.get(data, None).is_ok() {
+            found += 1;
+        }
+    }
+
+    let elapsed = start.elapsed();
+    let allocations = common::allocations() - allocations_before;
+    println!(
+        "{:<8} {:>10} allocations ({:.2} per lookup), {:?}, {} found",
+        label,
+        allocations,
+        allocations as f64 / LOOKUP_COUNT as f64,
+        elapsed,
+        found
+    );
+    allocations
+}
+
+fn main() {
+    let data = json!({
+        "users": [
+            {"name": "Alice", "scores": {"2024": 91}},
+            {"name": "Bob", "scores": {"2024": 78}}
+        ]
+    });
+    let string_path = Structpath::parse("$users[1].name").unwrap();
+    let int_path = Structpath::parse("$users[0].scores.2024").unwrap();
+
+    let string_allocations = measure("string", &string_path, &data);
+    let int_allocations = measure("int", &int_path, &data);
+
+    assert_eq!(string_allocations, 0, "string key lookups allocated");
+    assert_eq!(int_allocations, 0, "int key lookups allocated");
+}
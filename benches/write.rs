@@ -0,0 +1,78 @@
+//! Measures allocations made while writing one leaf into documents of
+//! growing size.
+//!
+//! Run with `cargo bench --bench write`. `write_in_place` only stages the
+//! part of the document that changes, and `write_with` returns no copy of
+//! given data, so their allocations must not grow with the document; the
+//! copying `write` is shown for comparison.
+
+mod common;
+
+use serde_json::{json, Map, Value};
+use std::time::Instant;
+use structpath::{Structpath, WriteOptions};
+
+const WRITE_COUNT: usize = 100;
+
+/// A document with `size` sibling records next to the one being written.
+fn build_document(size: usize) -> Value {
+    let records: Map<String, Value> = (0..size)
+        .map(|i| (format!("r{}", i), json!({"id": i, "tags": ["a", "b"]})))
+        .collect();
+    json!({"db": {"records": records, "target": {"leaf": 0}}})
+}
+
+fn measure(label: &str, size: usize, write: impl Fn(&mut Value)) -> usize {
+    let mut data = build_document(size);
+    let allocations_before = common::allocations();
+    let start = Instant::now();
+
+    for _ in 0..WRITE_COUNT {
+        write(&mut data);
+    }
+
+    let elapsed = start.elapsed();
+    let allocations = common::allocations() - allocations_before;
+    println!(
+        "{:<8} {:>7} records {:>10} allocations ({:.2} per write), {:?}",
+        label,
+        size,
+        allocations,
+        allocations as f64 / WRITE_COUNT as f64,
+        elapsed
+    );
+    allocations
+}
+
+fn main() {
+    let existing = Structpath::parse("$db.target.leaf").unwrap();
+    let new = Structpath::parse("$db.target.new.deep").unwrap();
+
+    let options = WriteOptions::default();
+
+    let mut in_place = Vec::new();
+    let mut with_options = Vec::new();
+    for size in [100, 10_000] {
+        measure("write", size, |data| {
+            existing.write(Some(data), json!(1), None).unwrap();
+        });
+        in_place.push(measure("in_place", size, |data| {
+            existing.write_in_place(data, json!(1), None).unwrap();
+            new.write_in_place(data, json!(1), None).unwrap();
+        }));
+        with_options.push(measure("with", size, |data| {
+            existing
+                .write_with(Some(data), json!(1), None, &options)
+                .unwrap();
+        }));
+    }
+
+    assert_eq!(
+        in_place[0], in_place[1],
+        "in-place writes allocated more for a bigger document"
+    );
+    assert_eq!(
+        with_options[0], with_options[1],
+        "write_with allocated more for a bigger document"
+    );
+}
@@ -0,0 +1,42 @@
+"""
+Times writing a large value at a single key of a large dict.
+
+Such writes take a fast path that converts only the value and returns a
+shallow copy of the dict, instead of converting the whole document.
+`skip_none=True` does not take it, and gives an equal result for data
+without None values, so it stands in for the general path.
+
+Run with the extension built and installed:
+
+    python python/benchmarks/bench_write.py
+"""
+
+import time
+
+from structpath import Structpath
+
+
+def best_of(runs, func):
+    best = float("inf")
+    for _ in range(runs):
+        start = time.perf_counter()
+        func()
+        best = min(best, time.perf_counter() - start)
+    return best
+
+
+def main():
+    data = {f"key{i}": {"nested": [i, str(i)]} for i in range(10_000)}
+    blob = {"items": [{"id": i} for i in range(1_000)]}
+    path = Structpath.parse("$blob")
+
+    fast = best_of(20, lambda: path.write(data, blob))
+    general = best_of(20, lambda: path.write(data, blob, skip_none=True))
+
+    print(f"single key:   {fast * 1000:8.2f} ms")
+    print(f"general path: {general * 1000:8.2f} ms")
+    print(f"speedup:      {general / fast:8.2f}x")
+
+
+if __name__ == "__main__":
+    main()
@@ -1,3 +1,13 @@
+from ._structpath import LazyValue
 from ._structpath import Structpath
+from ._structpath import Walker
+from ._structpath import apply_merge_patch
+from ._structpath import serialization_diagnostics
 
-__all__ = ["Structpath"]
+__all__ = [
+    "LazyValue",
+    "Structpath",
+    "Walker",
+    "apply_merge_patch",
+    "serialization_diagnostics",
+]
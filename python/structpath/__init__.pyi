@@ -6,10 +6,16 @@ like those found in JSON, and extract values from these structures using
 the defined paths.
 """
 
-from typing import Any, Iterator, TypeVar, overload
+import os
+from typing import Any, Iterable, Iterator, Literal, TypeVar, Union, overload
 
 T = TypeVar("T")
 V = TypeVar("V")
+D = TypeVar("D")
+
+# How keys that collide once converted to strings, like 1 and "1", are
+# resolved when serializing a dictionary
+DuplicateKeys = Literal["error", "last_wins", "first_wins"]
 
 class Structpath:
     """
@@ -17,6 +23,13 @@ class Structpath:
 
     Structpath provides a way to define paths into nested data structures,
     and extract or write values using these paths.
+
+    Errors are raised as built-in exceptions (KeyError, IndexError,
+    TypeError, ValueError) carrying the details as attributes: `variant`
+    names the kind of error, such as "InvalidPath" or "MissingVariable".
+    InvalidPath errors add `expected` and `found`, missing and duplicate
+    variables add `variable`, and parse errors add the `position` in the
+    path string, or None if it is not known.
     """
 
     def __init__(self) -> None:
@@ -36,17 +49,50 @@ class Structpath:
         Parse a structpath string into a Structpath object.
 
         Args:
-            path_str: A string representation of a path
+            path_str: A string representation of a path. The leading `$` is
+                optional and may also be written as `.`, so `"$a.b"`,
+                `"a.b"` and `".a.b"` all give the same path.
 
         Returns:
             A new Structpath object
 
         Raises:
-            ValueError: If the path string cannot be parsed
+            ValueError: If the path string cannot be parsed. Its `position`
+                attribute is the offset where the failing segment starts.
 
         Examples:
             >>> path = Structpath.parse("$users[0].name")
             >>> path.get(data)  # Returns "Alice"
+            >>> path = Structpath.parse('$a."".b')  # "" is the empty key
+        """
+        pass
+
+    @staticmethod
+    def parse_many(input: str) -> list["Structpath"]:
+        """
+        Parse a list of paths, one per line, as found in config files.
+
+        Lines are stripped, and blank lines and lines starting with `//`
+        are skipped (`#` starts a variable, so it cannot mark comments).
+
+        Args:
+            input: The lines to parse
+
+        Returns:
+            The paths in the order of their lines
+
+        Raises:
+            ValueError: If a line cannot be parsed. The message starts with
+                the line number, which is also the `line` attribute.
+
+        Examples:
+            >>> paths = Structpath.parse_many("""
+            ... // Exported fields
+            ... $users[0].name
+            ... $users[0].email
+            ... """)
+            >>> [str(path) for path in paths]
+            ['$users[0].name', '$users[0].email']
         """
         pass
 
@@ -121,6 +167,79 @@ class Structpath:
         """
         pass
 
+    def append_segments(
+        self,
+        segments: Iterable[
+            tuple[
+                Literal["key", "index", "key_variable", "index_variable"],
+                str | int,
+            ]
+        ],
+    ) -> None:
+        """
+        Extend the path with segments given as (kind, value) tuples.
+
+        The kinds match the push methods: "key" takes a string or integer
+        key, "index" an integer and "key_variable" and "index_variable" a
+        variable name. The path is left unchanged if any segment is invalid.
+
+        Args:
+            segments: The (kind, value) tuples to append, in order
+
+        Raises:
+            ValueError: If a kind is unknown or a variable name is already
+                used in this path
+            TypeError: If a value has the wrong type for its kind
+
+        Examples:
+            >>> path = Structpath.parse("$a")
+            >>> path.append_segments([("index", 0), ("key", "b")])
+            >>> str(path)
+            '$a[0].b'
+        """
+        pass
+
+    def append(self, path_str: str) -> None:
+        """
+        Extend the path with a relative path string, parsed like `parse`
+        but without needing the leading `$`.
+
+        The path is left unchanged if the string is invalid.
+
+        Args:
+            path_str: The relative path, e.g. "b.c[0]"
+
+        Raises:
+            ValueError: If the string is not a valid path or uses a variable
+                name that is already used in this path
+
+        Examples:
+            >>> path = Structpath.parse("$a")
+            >>> path.append("b.c[0]")
+            >>> str(path)
+            '$a.b.c[0]'
+        """
+        pass
+
+    def contains_variable(self, name: str) -> bool:
+        """
+        Check whether the path uses a variable with the given name.
+
+        Args:
+            name: The variable name, without the leading `#`
+
+        Returns:
+            True if the path contains the variable
+
+        Examples:
+            >>> path = Structpath.parse("$a.#x.b")
+            >>> path.contains_variable("x")
+            True
+            >>> path.contains_variable("y")
+            False
+        """
+        pass
+
     @overload
     def get(
         self, data: dict[str, V], vars: dict[str, Any] | None = None
@@ -129,22 +248,87 @@ class Structpath:
     def get(self, data: list[V], vars: dict[str, Any] | None = None) -> V: ...
     @overload
     def get(self, data: Any) -> Any: ...
-    def get(self, data: Any, vars: dict[str, Any] | None = None) -> Any:
+    @overload
+    def get(
+        self,
+        data: Any,
+        vars: dict[str, Any] | None = None,
+        *,
+        default: D,
+    ) -> Any | D: ...
+    @overload
+    def get(
+        self,
+        data: Any,
+        vars: dict[str, Any] | None = None,
+        *,
+        lazy: Literal[True],
+    ) -> "LazyValue": ...
+    @overload
+    def get(
+        self,
+        data: Any,
+        vars: dict[str, Any] | None = None,
+        *,
+        trace: Literal[True],
+    ) -> tuple[Any, list[tuple[str, bool, str]]]: ...
+    @overload
+    def get(
+        self,
+        data: Any,
+        vars: dict[str, Any] | None = None,
+        *,
+        with_type: Literal[True],
+        default: Any = ...,
+    ) -> tuple[Any, str | None]: ...
+    def get(
+        self,
+        data: Any,
+        vars: dict[str, Any] | None = None,
+        **kwargs: Any,
+    ) -> Any:
         """
         Get a value from data using this path.
 
         Args:
             data: The data structure to navigate
             vars: Optional dictionary mapping variable names to values
+            default: Keyword-only value to return if the path doesn't exist
+                in the data. It is returned as the same object, without
+                being converted, and is not looked at when the path exists.
+            lazy: Keyword-only flag to return a LazyValue instead of the
+                value itself, deferring building the Python objects.
+            trace: Keyword-only flag to return `(value_or_none, steps)`
+                instead of raising on a miss. Each step is a
+                `(segment, resolved, found_type)` tuple, where found_type
+                is the JSON type the segment was applied to, named as for
+                `with_type`. The last step of a miss is the segment that
+                failed. Cannot be combined with `default` or `lazy`.
+            sort_keys: Keyword-only flag to build the dictionaries of the
+                value with their keys in sorted order, at any depth, e.g.
+                for canonical JSON. Without it, keys keep the order of the
+                underlying JSON map. Cannot be combined with `lazy`.
+            with_type: Keyword-only flag to return `(value, type_name)`,
+                where the type name is the JSON type of the value:
+                "object", "array", "string", "number", "boolean" or "null".
+                A miss with a `default` gives `(default, None)`. Cannot be
+                combined with `lazy` or `trace`.
+            duplicate_keys: Keyword-only policy for dictionary keys that
+                collide once integer keys are converted to strings, as for
+                `write`.
 
         Returns:
-            The value at the path
+            The value at the path, or `default` if given and the path is
+            missing
 
         Raises:
-            KeyError: If the path doesn't exist in the data
-            IndexError: If an index doesn't exist in the data
+            KeyError: If the path doesn't exist in the data and no default
+                is given
+            IndexError: If an index doesn't exist in the data and no default
+                is given
             TypeError: If the path is invalid for the data structure
-            ValueError: If a variable in the path is missing from vars
+            ValueError: If a variable in the path is missing from vars, or
+                keys collide and `duplicate_keys` is "error"
 
         Examples:
             >>> data = {"users": [{"name": "Alice"}, {"name": "Bob"}]}
@@ -152,10 +336,194 @@ class Structpath:
             >>> path.get(data)  # Returns "Alice"
             >>> path = Structpath.parse("$users[#idx].name")
             >>> path.get(data, {"idx": "1"})  # Returns "Bob"
+            >>> Structpath.parse("$users[0].age").get(data, trace=True)
+            (None, [('.users', True, 'object'), ('[0]', True, 'array'), ('.age', False, 'object')])
+            >>> Structpath.parse("$users[0]").get(data, sort_keys=True)
+            {'name': 'Alice'}
+            >>> Structpath.parse("$users").get(data, with_type=True)
+            ([{'name': 'Alice'}, {'name': 'Bob'}], 'array')
+        """
+        pass
+
+    @staticmethod
+    def get_first(
+        data: Any,
+        paths: Iterable[Union[str, "Structpath"]],
+        vars: dict[str, Any] | None = None,
+    ) -> Any:
+        """
+        Get the value at the first of several candidate paths that exists.
+
+        Paths that are missing from the data, or don't fit its structure,
+        are skipped. Errors about variables are raised straight away.
+
+        Args:
+            data: The data structure to navigate
+            paths: Candidate paths (strings or Structpaths), tried in order
+            vars: Optional dictionary mapping variable names to values
+
+        Returns:
+            The value at the first path that resolves
+
+        Raises:
+            KeyError: If none of the paths resolve
+            ValueError: If a variable in a path is missing from vars
+
+        Examples:
+            >>> data = {"old": {"field": 1}}
+            >>> Structpath.get_first(data, ["$new.field", "$old.field"])
+            1
+        """
+        pass
+
+    @staticmethod
+    def exists_many(
+        data: Any,
+        paths: Iterable[Union[str, "Structpath"]],
+        vars: dict[str, Any] | None = None,
+    ) -> list[bool]:
+        """
+        Check which of several paths exist in the data, converting the data
+        only once.
+
+        Paths that are missing from the data, or don't fit its structure,
+        give False. Errors about variables are raised straight away.
+
+        Args:
+            data: The data structure to navigate
+            paths: Paths to check (strings or Structpaths)
+            vars: Optional dictionary mapping variable names to values
+
+        Returns:
+            A list with one bool per path, in the order of `paths`
+
+        Raises:
+            ValueError: If a variable in a path is missing from vars
+
+        Examples:
+            >>> data = {"name": "Alice", "tags": []}
+            >>> Structpath.exists_many(data, ["$name", "$email", "$tags[0]"])
+            [True, False, False]
+        """
+        pass
+
+    def get_sibling(
+        self, data: Any, key: str | int, vars: dict[str, Any] | None = None
+    ) -> Any:
+        """
+        Get the value of a sibling of the node at this path.
+
+        The sibling path is this path with its last segment replaced by key,
+        which is handy for looking at neighbouring fields while walking.
+
+        Args:
+            data: The data structure to navigate
+            key: The key of the sibling
+            vars: Optional dictionary of variable values
+
+        Returns:
+            The value of the sibling
+
+        Examples:
+            >>> data = {"user": {"name": "Alice", "age": 30}}
+            >>> Structpath.parse("$user.name").get_sibling(data, "age")
+            30
+
+        Raises:
+            ValueError: If this is the root path, which has no siblings
+            KeyError: If the sibling is not found
+        """
+        pass
+
+    def child_count(
+        self, data: Any, vars: dict[str, Any] | None = None
+    ) -> int:
+        """
+        Count the immediate children of the node at this path.
+
+        This is the number of keys of a dict or items of a list, found
+        without building the node as a Python object, which makes it cheaper
+        than `len(path.get(data))` for large subtrees.
+
+        Args:
+            data: The data structure to navigate
+            vars: Optional dictionary of variable values
+
+        Returns:
+            The number of keys or items at the path
+
+        Examples:
+            >>> data = {"users": [{"name": "Alice"}, {"name": "Bob"}]}
+            >>> Structpath.parse("$users").child_count(data)
+            2
+            >>> Structpath.parse("$users[0]").child_count(data)
+            1
+
+        Raises:
+            KeyError: If the path is not found
+            TypeError: If the value at the path is not a dict or list
+        """
+        pass
+
+    def unset(self, data: Any, vars: dict[str, Any] | None = None) -> bool:
+        """
+        Remove the value at this path from data, in place.
+
+        A key can be in one of three states, and each has its own operation:
+
+        - absent: `unset` removes the key, so it is no longer in its parent
+        - present with None: `write(data, None)` keeps the key with value None
+        - present with a value: `write(data, value)`
+
+        Removing a list element moves the elements after it down by one.
+
+        Args:
+            data: The dictionary or list to modify
+            vars: Optional dictionary mapping variable names to values
+
+        Returns:
+            True if a value was removed, False if there was nothing at the
+            path
+
+        Raises:
+            TypeError: If the parent of the path is not a container of the
+                kind the last segment needs
+            ValueError: If a variable in the path is missing from vars
+
+        Examples:
+            >>> data = {"a": 1, "b": 2}
+            >>> Structpath.parse("$a").unset(data)
+            True
+            >>> data
+            {'b': 2}
+            >>> Structpath.parse("$a").unset(data)
+            False
         """
         pass
 
-    def iter(self, data: Any) -> Iterator[tuple[dict[str, str], Any]]:
+    @overload
+    def iter(
+        self,
+        data: Any,
+        fixed: dict[str, str] | None = None,
+        *,
+        include_path: Literal[False] = False,
+    ) -> Iterator[tuple[dict[str, str], Any]]: ...
+    @overload
+    def iter(
+        self,
+        data: Any,
+        fixed: dict[str, str] | None = None,
+        *,
+        include_path: Literal[True],
+    ) -> Iterator[tuple["Structpath", dict[str, str], Any]]: ...
+    def iter(
+        self,
+        data: Any,
+        fixed: dict[str, str] | None = None,
+        *,
+        include_path: bool = False,
+    ) -> Iterator[tuple[Any, ...]]:
         """
         Iterate over all possible variable resolutions in the data.
 
@@ -165,6 +533,14 @@ class Structpath:
 
         Args:
             data: The data structure to navigate
+            fixed: Optional dictionary of variables to keep at the given
+                value. Only the other variables are iterated, and the fixed
+                ones still appear in each variable_values.
+            include_path: Keyword-only flag to also yield the resolved path
+                of each value, with every variable replaced by its key or
+                index, as (path, variable_values, value). Paths with
+                wildcards or key patterns cannot be resolved and raise
+                ValueError; use `iter_matches` for those.
 
         Returns:
             An iterator yielding (variable_values, value) tuples
@@ -176,6 +552,38 @@ class Structpath:
             ...     print(f"{vars['userId']}: {value}")
             user1: Alice
             user2: Bob
+            >>> list(path.iter(data, fixed={"userId": "user2"}))
+            [({'userId': 'user2'}, 'Bob')]
+            >>> for path, vars, value in path.iter(data, include_path=True):
+            ...     print(path)
+            $users.user1.name
+            $users.user2.name
+        """
+        pass
+
+    def iter_matches(self, data: Any) -> Iterator[tuple["Structpath", Any]]:
+        """
+        Iterate over every value this path matches, with its resolved path.
+
+        Variables, wildcards and key patterns are all expanded, and each
+        resolved path has them replaced by the key or index they matched.
+        Matches are found one at a time as the iterator advances, in the
+        same order as `iter`, so stopping early skips the rest of the
+        search.
+
+        Args:
+            data: The data structure to navigate
+
+        Returns:
+            An iterator yielding (path, value) tuples
+
+        Examples:
+            >>> data = {"teams": {"a": ["Ann", "Bo"], "b": ["Cy"]}}
+            >>> for path, value in Structpath.parse("$teams.*[*]").iter_matches(data):
+            ...     print(f"{path}: {value}")
+            $teams.a[0]: Ann
+            $teams.a[1]: Bo
+            $teams.b[0]: Cy
         """
         pass
 
@@ -185,6 +593,10 @@ class Structpath:
         data: dict[str, Any],
         value: Any | None = None,
         vars: dict[str, Any] | None = None,
+        *,
+        strict_root: bool = False,
+        skip_none: bool = False,
+        duplicate_keys: DuplicateKeys = "error",
     ) -> dict[str, Any]: ...
     @overload
     def write(
@@ -192,12 +604,20 @@ class Structpath:
         data: list[Any],
         value: Any | None = None,
         vars: dict[str, Any] | None = None,
+        *,
+        strict_root: bool = False,
+        skip_none: bool = False,
+        duplicate_keys: DuplicateKeys = "error",
     ) -> list[Any]: ...
     def write(
         self,
         data: Any | None = None,
         value: Any | None = None,
         vars: dict[str, Any] | None = None,
+        *,
+        strict_root: bool = False,
+        skip_none: bool = False,
+        duplicate_keys: DuplicateKeys = "error",
     ) -> Any:
         """
         Write a value to a path in the data structure.
@@ -205,17 +625,48 @@ class Structpath:
         This method creates or updates a value at the specified path in the data.
         If parts of the path don't exist, they will be created.
 
+        A created container follows the segment that steps into it: a key
+        creates a dictionary and an index a list. This includes the root, so
+        writing `$0` into None gives `{"0": value}`, while `$[0]` gives
+        `[value]`. No root type hint is needed for numeric keys.
+
+        A root that is a scalar other than None, like `5`, is replaced by
+        the container too, unless `strict_root` is set.
+
+        A dictionary or list `data` is also updated in place. Only the
+        value that changed is replaced, so the other nested dictionaries
+        and lists in `data` stay the same objects.
+
+        Writing a single key like `$name` into a dictionary converts only
+        `value`, and returns a shallow copy of `data` after the write: the
+        other entries are shared with `data` as they are, integer keys
+        included. Other writes return a converted copy of the whole
+        document.
+
+        With `skip_none`, dictionary entries whose value is None are dropped
+        from both `data` and `value` before writing, at any depth. Writing
+        None itself still stores None at the path.
+
         Args:
             data: The data structure to modify (optional)
             value: The value to write (optional)
             vars: Optional dictionary mapping variable names to values
+            strict_root: Keyword-only flag to raise instead of replacing a
+                scalar root
+            skip_none: Keyword-only flag to omit dictionary entries whose
+                value is None
+            duplicate_keys: Keyword-only policy for dictionary keys that
+                collide once integer keys are converted to strings, like 1
+                and "1": "error", "last_wins" or "first_wins"
 
         Returns:
             The modified data structure
 
         Raises:
-            TypeError: If the path is invalid for the data structure
-            ValueError: If a variable in the path is missing from vars
+            TypeError: If the path is invalid for the data structure, or
+                `strict_root` is set and the root is a scalar
+            ValueError: If a variable in the path is missing from vars, or
+                keys collide and `duplicate_keys` is "error"
 
         Examples:
             >>> data = {"users": []}
@@ -223,15 +674,70 @@ class Structpath:
             >>> result = path.write(data, "Alice")
             >>> result
             {'users': [{'name': 'Alice'}]}
-            >>> path = Structpath.parse("$users.#userId.name")
+            >>> path = Structpath.parse("$accounts.#userId.name")
             >>> result = path.write(data, "Charlie", {"userId": "user3"})
             >>> result
-            {'users': [{'name': 'Alice'}], 'user3': {'name': 'Charlie'}}
+            {'accounts': {'user3': {'name': 'Charlie'}}, 'users': [{'name': 'Alice'}]}
         """
         pass
 
     @staticmethod
-    def walk(data: T) -> Iterator[tuple["Structpath", Any]]:
+    def get_from_json_file(
+        path: Union[str, "Structpath"],
+        file_path: Union[str, "os.PathLike[str]"],
+        vars: dict[str, Any] | None = None,
+    ) -> Any:
+        """
+        Get the value at a path in a JSON file.
+
+        The file is parsed without building a Python object for the whole
+        document; only the value found is converted. This keeps extracting
+        one field from a large file cheap.
+
+        Args:
+            path: The path to resolve, as a string or Structpath
+            file_path: The JSON file to read
+            vars: Optional dictionary of variable values
+
+        Returns:
+            The value at the path
+
+        Raises:
+            OSError: If the file cannot be read
+            ValueError: If the file is not valid JSON
+            KeyError: If the path is not found
+        """
+        pass
+
+    @overload
+    @staticmethod
+    def walk(
+        data: T,
+        root: Union[str, "Structpath", None] = None,
+        max_nodes: int | None = None,
+        *,
+        include_kind: Literal[False] = False,
+        duplicate_keys: DuplicateKeys = "error",
+    ) -> "Walker": ...
+    @overload
+    @staticmethod
+    def walk(
+        data: T,
+        root: Union[str, "Structpath", None] = None,
+        max_nodes: int | None = None,
+        *,
+        include_kind: Literal[True],
+        duplicate_keys: DuplicateKeys = "error",
+    ) -> Iterator[tuple["Structpath", Any, str]]: ...
+    @staticmethod
+    def walk(
+        data: T,
+        root: Union[str, "Structpath", None] = None,
+        max_nodes: int | None = None,
+        *,
+        include_kind: bool = False,
+        duplicate_keys: DuplicateKeys = "error",
+    ) -> "Walker":
         """
         Walk through all paths in a data structure.
 
@@ -240,6 +746,18 @@ class Structpath:
 
         Args:
             data: The data structure to walk through
+            root: Optional path (string or Structpath) to start walking from.
+                Only the subtree at this path is walked, and every yielded
+                path starts with it.
+            max_nodes: Optional limit on the number of nodes yielded, for
+                walking untrusted documents. The returned walker's
+                `truncated` attribute tells whether nodes were left out.
+            include_kind: Keyword-only flag to also yield the kind of each
+                node, "object", "array" or "scalar", as
+                (path, value, kind).
+            duplicate_keys: Keyword-only policy for dictionary keys that
+                collide once integer keys are converted to strings, as for
+                `write`
 
         Returns:
             An iterator yielding (path, value) tuples
@@ -256,6 +774,80 @@ class Structpath:
             $a[2].b: 3
             $c: {'d': 4}
             $c.d: 4
+
+        Raises:
+            KeyError: If root is not found in the data
+            ValueError: If keys collide and `duplicate_keys` is "error"
+        """
+        pass
+
+    @staticmethod
+    def paths(data: Any) -> list[str]:
+        """
+        List the string of every path in a data structure.
+
+        This is the same as `[str(p) for p, _ in Structpath.walk(data)]`,
+        without creating a Structpath object for each node.
+
+        Args:
+            data: The data structure to walk through
+
+        Returns:
+            The path strings, in the order `walk` yields them
+
+        Examples:
+            >>> Structpath.paths({"a": [1], "b": 2})
+            ['$a[0]', '$a', '$b', '$']
+        """
+        pass
+
+    @staticmethod
+    def leaf_paths(data: Any) -> list[str]:
+        """
+        List the strings of the paths to leaves in a data structure.
+
+        Leaves are the nodes without children: scalar values and empty
+        dictionaries or lists.
+
+        Args:
+            data: The data structure to walk through
+
+        Returns:
+            The leaf path strings, in the order `walk` yields them
+
+        Examples:
+            >>> Structpath.leaf_paths({"a": [1, {}], "b": 2})
+            ['$a[0]', '$a[1]', '$b']
+        """
+        pass
+
+    def format(
+        self, *, root: str = "$", separator: str = ".", ascii_only: bool = False
+    ) -> str:
+        """
+        Render the path with another root or key separator, e.g. for a
+        system with its own path syntax.
+
+        Keys are escaped as in `str(path)`, and keys containing the
+        separator escape it with a backslash. Only the defaults give a
+        string that `parse` reads back as the same path.
+
+        Args:
+            root: Keyword-only text written at the start, possibly empty
+            separator: Keyword-only character written between keys
+            ascii_only: Keyword-only flag to write characters that are not
+                printable ASCII as `\\u{...}` escapes
+
+        Returns:
+            The formatted path
+
+        Raises:
+            ValueError: If `separator` is not a single character
+
+        Examples:
+            >>> path = Structpath.parse("$users[0].name")
+            >>> path.format(root="@", separator="/")
+            '@users[0]/name'
         """
         pass
 
@@ -285,4 +877,103 @@ class Structpath:
         """
         pass
 
-__all__ = ["Structpath"]
+class Walker(Iterator[tuple[Structpath, Any]]):
+    """
+    The iterator returned by `Structpath.walk`.
+    """
+
+    truncated: bool
+    """Whether `max_nodes` cut the walk short."""
+
+    def __iter__(self) -> "Walker": ...
+    def __next__(self) -> tuple[Structpath, Any]: ...
+
+class LazyValue:
+    """
+    A value returned by `Structpath.get(..., lazy=True)`.
+
+    The value is kept in its converted form, so looking further into it
+    neither converts the original data again nor builds Python objects for
+    the parts that are skipped. Call `value()` to build the Python object.
+    """
+
+    def get(
+        self,
+        path: Union[str, Structpath],
+        vars: dict[str, Any] | None = None,
+        **kwargs: Any,
+    ) -> Any:
+        """
+        Get a value inside this one, like `Structpath.get`.
+
+        Args:
+            path: The path to resolve, relative to this value
+            vars: Optional dictionary mapping variable names to values
+            default: Keyword-only value to return if the path is missing
+            lazy: Keyword-only flag to return another LazyValue
+
+        Examples:
+            >>> data = {"a": {"b": {"c": [1, 2]}}}
+            >>> handle = Structpath.parse("$a").get(data, lazy=True)
+            >>> handle.get("$b", lazy=True).get("$c[1]")
+            2
+        """
+        pass
+
+    def value(self) -> Any:
+        """
+        Build the Python object for this value.
+        """
+        pass
+
+def serialization_diagnostics(data: Any) -> list[tuple[Structpath, str]]:
+    """
+    List the lossy conversions made when converting data for a path lookup.
+
+    Values that cannot be represented exactly, such as NaN floats (stored as
+    null), integers beyond the 64-bit range (stored as floats) and integer
+    dictionary keys (stored as strings), are converted silently by `get`,
+    `write` and friends. This function reports each such conversion.
+
+    Args:
+        data: The data structure to inspect
+
+    Returns:
+        A list of (path, message) tuples, one per lossy conversion
+
+    Examples:
+        >>> data = {"a": [1.0, float("nan")]}
+        >>> [(str(p), msg) for p, msg in serialization_diagnostics(data)]
+        [('$a[1]', 'Float NaN was replaced by null')]
+    """
+    pass
+
+def apply_merge_patch(data: Any, patch: Any) -> Any:
+    """
+    Apply an RFC 7386 JSON Merge Patch to a whole document.
+
+    Objects in the patch are merged key by key, a None member deletes the key
+    and any other value replaces the target. A dictionary passed as data is
+    updated in place when the result is still a dictionary.
+
+    Args:
+        data: The document to patch
+        patch: The merge patch to apply
+
+    Returns:
+        The patched document
+
+    Examples:
+        >>> data = {"title": "Hello", "author": {"name": "Jo", "email": "x"}}
+        >>> apply_merge_patch(data, {"title": "Hi", "author": {"email": None}})
+        {'author': {'name': 'Jo'}, 'title': 'Hi'}
+    """
+    pass
+
+__all__ = [
+    "LazyValue",
+    "Structpath",
+    "Walker",
+    "apply_merge_patch",
+    "serialization_diagnostics",
+]
@@ -72,3 +72,47 @@ class TestStructpathBuild(StructpathTestCase):
 
         value = path.get(data, {"id": "user1"})
         self.assertEqual(value, 85)
+
+    def test_contains_variable(self):
+        path = Structpath.parse("$a.#x.b")
+
+        self.assert_true(path.contains_variable("x"))
+        self.assert_false(path.contains_variable("y"))
+        self.assert_false(path.contains_variable("a"))
+
+    def test_append(self):
+        path = Structpath.parse("$a")
+
+        path.append("b.c[0]")
+        self.assert_equal(str(path), "$a.b.c[0]")
+        path.append("[#i].#k")
+        self.assert_equal(str(path), "$a.b.c[0][#i].#k")
+
+        with self.assert_raises(ValueError):
+            path.append("x.#k")
+        with self.assert_raises(ValueError):
+            path.append("x[")
+        self.assert_equal(str(path), "$a.b.c[0][#i].#k")
+
+    def test_append_segments(self):
+        path = Structpath.parse("$a")
+
+        path.append_segments([("index", 0), ("key", "b")])
+
+        self.assert_equal(str(path), str(Structpath.parse("$a[0].b")))
+        self.assert_equal(path.get({"a": [{"b": 1}]}), 1)
+
+        path.append_segments([("key", 3), ("index_variable", "i")])
+        self.assert_equal(str(path), "$a[0].b.3[#i]")
+
+    def test_append_segments_is_all_or_nothing(self):
+        path = Structpath.parse("$a.#x")
+
+        with self.assert_raises(ValueError):
+            path.append_segments([("key", "b"), ("key_variable", "x")])
+        with self.assert_raises(ValueError):
+            path.append_segments([("slice", 1)])
+        with self.assert_raises(TypeError):
+            path.append_segments([("index", "zero")])
+
+        self.assert_equal(str(path), "$a.#x")
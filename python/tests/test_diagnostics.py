@@ -0,0 +1,36 @@
+from uneedtest import TestCase
+
+from structpath import serialization_diagnostics
+
+
+class TestSerializationDiagnostics(TestCase):
+    def test_nan_float(self):
+        data = {"a": [1.0, float("nan")]}
+
+        diagnostics = serialization_diagnostics(data)
+
+        self.assert_equal(len(diagnostics), 1)
+        path, message = diagnostics[0]
+        self.assert_equal(str(path), "$a[1]")
+        self.assert_in("NaN", message)
+
+    def test_out_of_range_int(self):
+        data = {"id": 2**70}
+
+        diagnostics = serialization_diagnostics(data)
+
+        self.assert_equal(len(diagnostics), 1)
+        path, message = diagnostics[0]
+        self.assert_equal(str(path), "$id")
+        self.assert_in(str(2**70), message)
+
+    def test_int_key(self):
+        diagnostics = serialization_diagnostics({"a": {1: "x"}})
+
+        self.assert_equal(len(diagnostics), 1)
+        self.assert_equal(str(diagnostics[0][0]), "$a.1")
+
+    def test_lossless_data(self):
+        data = {"a": [1, 2.5, "x", None, True], "b": {"c": 2**62}}
+
+        self.assert_equal(serialization_diagnostics(data), [])
@@ -3,6 +3,17 @@ from base import StructpathTestCase
 from structpath import Structpath
 
 
+class _Mutating:
+    """Resizes a container when converted to an integer."""
+
+    def __init__(self, mutate):
+        self.mutate = mutate
+
+    def __index__(self):
+        self.mutate()
+        return 1
+
+
 class TestStructpathErrors(StructpathTestCase):
     def test_parse_errors(self):
         with self.assertRaises(ValueError):
@@ -25,3 +36,69 @@ class TestStructpathErrors(StructpathTestCase):
         )  # String doesn't support indexing
         with self.assertRaises(TypeError):
             path.get(self.test_data)
+
+    def test_missing_path_reported_with_escapes(self):
+        data = {"a.b": {"c": 1}, "$x": 2}
+
+        for typed in [r"$a\.b.d", r"$a\.b.\d"]:
+            with self.assert_raises(KeyError) as ctx:
+                Structpath.parse(typed).get(data)
+            self.assert_equal(
+                ctx.exception.args[0], r"Path not found: $a\.b.d"
+            )
+
+        with self.assert_raises(KeyError) as ctx:
+            Structpath.parse(r"$a\[0\].x").get(data)
+        self.assert_equal(ctx.exception.args[0], r"Path not found: $a\[0\].x")
+
+        with self.assert_raises(KeyError) as ctx:
+            Structpath.get_first(data, [r"$a\.c", r"$\$z"])
+        self.assert_equal(
+            ctx.exception.args[0], r"None of the paths found: $a\.c, $$z"
+        )
+
+    def test_error_fields(self):
+        with self.assert_raises(TypeError) as ctx:
+            Structpath.parse("$user.name[0]").get(self.test_data)
+        self.assert_equal(ctx.exception.variant, "InvalidPath")
+        self.assert_equal(ctx.exception.expected, "array")
+        self.assert_equal(ctx.exception.found, 'String("John Doe")')
+
+        with self.assert_raises(TypeError) as ctx:
+            Structpath.parse("$products.first").write(self.test_data, 1)
+        self.assert_equal(ctx.exception.variant, "InvalidPath")
+        self.assert_equal(ctx.exception.expected, "object")
+
+        with self.assert_raises(ValueError) as ctx:
+            Structpath.parse("$users.#id").get({"users": {}}, {})
+        self.assert_equal(ctx.exception.variant, "MissingVariable")
+        self.assert_equal(ctx.exception.variable, "id")
+
+        with self.assert_raises(ValueError) as ctx:
+            Structpath.parse("$users[abc]")
+        self.assert_equal(ctx.exception.variant, "ParseError")
+        self.assert_equal(ctx.exception.position, 6)
+
+        with self.assert_raises(ValueError) as ctx:
+            Structpath.parse("$#a.#a")
+        self.assert_equal(ctx.exception.variant, "DuplicateVariable")
+        self.assert_equal(ctx.exception.variable, "a")
+
+        with self.assert_raises(KeyError) as ctx:
+            Structpath.parse("$missing").get({})
+        self.assert_equal(ctx.exception.variant, "NotFound")
+
+    def test_container_resized_during_serialization(self):
+        path = Structpath.parse("$a")
+
+        data = {"a": 1}
+        data["b"] = _Mutating(lambda: data.update(c=2))
+        with self.assert_raises(RuntimeError) as ctx:
+            path.get(data)
+        self.assert_in("dict changed size", str(ctx.exception))
+
+        items = []
+        items.append(_Mutating(lambda: items.append(0)))
+        with self.assert_raises(RuntimeError) as ctx:
+            path.get({"a": items})
+        self.assert_in("list changed size", str(ctx.exception))
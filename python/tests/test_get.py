@@ -1,6 +1,26 @@
+import json
+import os
+import tempfile
+from types import MappingProxyType
+
 from base import StructpathTestCase
 
-from structpath import Structpath
+from structpath import LazyValue, Structpath
+
+
+class _Squares:
+    """A sequence by protocol only, without subclassing list."""
+
+    def __init__(self, n):
+        self.n = n
+
+    def __len__(self):
+        return self.n
+
+    def __getitem__(self, idx):
+        if idx >= self.n:
+            raise IndexError(idx)
+        return {"square": idx * idx}
 
 
 class TestStructpathGet(StructpathTestCase):
@@ -64,3 +84,267 @@ class TestStructpathGet(StructpathTestCase):
         # Using get with a path containing variables should error
         with self.assertRaises(ValueError):
             path.get(data)
+
+    def test_get_with_default(self):
+        data = {"a": {"b": [1, 2]}}
+        default = {"fallback": []}
+
+        # A miss returns the very same object, unchanged
+        result = Structpath.parse("$a.missing").get(data, default=default)
+        self.assert_is(result, default)
+        result = Structpath.parse("$a.b[5]").get(data, default=default)
+        self.assert_is(result, default)
+        self.assert_equal(default, {"fallback": []})
+
+        # A hit ignores the default
+        result = Structpath.parse("$a.b[1]").get(data, default=default)
+        self.assert_equal(result, 2)
+        self.assert_equal(default, {"fallback": []})
+
+        # None is a valid default
+        self.assert_is_none(Structpath.parse("$x").get(data, default=None))
+
+        # Type mismatches still raise
+        with self.assert_raises(TypeError):
+            Structpath.parse("$a.b.c").get(data, default=default)
+
+        with self.assert_raises(TypeError):
+            Structpath.parse("$a").get(data, fallback=default)
+
+    def test_get_length(self):
+        data = {"items": [1, 2, 3], "users": {"a": 1}, "meta": {"length": 9}}
+
+        self.assert_equal(Structpath.parse("$items.length").get(data), 3)
+        self.assert_equal(Structpath.parse("$items[#]").get(data), 3)
+        self.assert_equal(Structpath.parse("$users.length").get(data), 1)
+        self.assert_equal(Structpath.parse(r"$meta.\length").get(data), 9)
+
+    def test_get_first(self):
+        data = {"old": {"field": 1}, "v2": {"field": 2}}
+
+        result = Structpath.get_first(
+            data, ["$new.field", Structpath.parse("$old.field")]
+        )
+        self.assert_equal(result, 1)
+
+        result = Structpath.get_first(
+            data, ["$new.field", "$#v.field"], {"v": "v2"}
+        )
+        self.assert_equal(result, 2)
+
+        with self.assert_raises(KeyError):
+            Structpath.get_first(data, ["$new.field", "$old.other"])
+
+        with self.assert_raises(ValueError):
+            Structpath.get_first(data, ["$#v.field", "$old.field"], {})
+
+    def test_exists_many(self):
+        data = {"id": 1, "user": {"name": "Ann", "tags": ["a"]}, "n": 5}
+
+        result = Structpath.exists_many(
+            data,
+            [
+                "$id",
+                "$email",
+                Structpath.parse("$user.name"),
+                "$user.tags[0]",
+                "$user.tags[1]",
+                "$n.x",
+                "$user.#field",
+            ],
+            {"field": "tags"},
+        )
+
+        self.assert_equal(
+            result, [True, False, True, True, False, False, True]
+        )
+        self.assert_equal(Structpath.exists_many(data, []), [])
+        with self.assert_raises(ValueError):
+            Structpath.exists_many(data, ["$id", "$user.#field"], {})
+
+    def test_get_empty_key(self):
+        data = {"": {"x": 1}, "a": {"": 2}}
+
+        self.assert_equal(Structpath.parse('$"".x').get(data), 1)
+        self.assert_equal(Structpath.parse('$a.""').get(data), 2)
+
+        for path, value in Structpath.walk(data):
+            self.assert_equal(Structpath.parse(str(path)).get(data), value)
+
+    def test_get_from_json_file(self):
+        data = {"users": [{"name": "Alice", "tags": ["a", "b"]}]}
+
+        with tempfile.TemporaryDirectory() as tmp_dir:
+            file_path = os.path.join(tmp_dir, "data.json")
+            with open(file_path, "w") as file:
+                json.dump(data, file)
+
+            self.assert_equal(
+                Structpath.get_from_json_file("$users[0].name", file_path),
+                "Alice",
+            )
+            self.assert_equal(
+                Structpath.get_from_json_file(
+                    Structpath.parse("$users[#i].tags"), file_path, {"i": "0"}
+                ),
+                ["a", "b"],
+            )
+
+            with self.assert_raises(KeyError):
+                Structpath.get_from_json_file("$users[0].age", file_path)
+
+            with self.assert_raises(OSError):
+                Structpath.get_from_json_file(
+                    "$a", os.path.join(tmp_dir, "missing.json")
+                )
+
+    def test_get_lazy(self):
+        data = {"a": {"b": {"c": [1, {"d": "leaf"}]}, "e": 2}}
+
+        handle = Structpath.parse("$a").get(data, lazy=True)
+        self.assert_is_instance(handle, LazyValue)
+
+        inner = handle.get("$b.c", lazy=True)
+        self.assert_is_instance(inner, LazyValue)
+        self.assert_equal(inner.get(Structpath.parse("$[1].d")), "leaf")
+        self.assert_equal(inner.get("$[#i]", {"i": "0"}), 1)
+        self.assert_equal(inner.value(), [1, {"d": "leaf"}])
+
+        self.assert_equal(handle.get("$missing", default=None), None)
+        with self.assert_raises(KeyError):
+            handle.get("$missing")
+
+    def test_get_find_value(self):
+        data = {"ids": [3, 5], "users": [{"name": "a"}, "bob"]}
+
+        self.assert_equal(Structpath.parse("$ids[=5]").get(data), 5)
+        self.assert_equal(Structpath.parse('$users[="bob"]').get(data), "bob")
+        with self.assert_raises(KeyError):
+            Structpath.parse("$ids[=4]").get(data)
+
+    def test_get_trace(self):
+        data = {"a": {"x": {"c": 1}}}
+
+        value, steps = Structpath.parse("$a.b.c").get(data, trace=True)
+        self.assert_equal(value, None)
+        self.assert_equal(
+            steps, [(".a", True, "object"), (".b", False, "object")]
+        )
+
+        value, steps = Structpath.parse("$a.x.c").get(data, trace=True)
+        self.assert_equal(value, 1)
+        self.assert_equal(steps[-1], (".c", True, "object"))
+
+        value, steps = Structpath.parse("$f.x").get({"f": True}, trace=True)
+        self.assert_equal(steps[-1], (".x", False, "boolean"))
+
+        with self.assert_raises(ValueError):
+            Structpath.parse("$a.#k").get(data, trace=True)
+        with self.assert_raises(TypeError):
+            Structpath.parse("$a").get(data, trace=True, default=None)
+
+    def test_get_sort_keys(self):
+        data = {"doc": {"b": 1, "a": [{"z": 0, "y": 0}], "c": {"f": 0, "e": 0}}}
+
+        result = Structpath.parse("$doc").get(data, sort_keys=True)
+
+        self.assert_equal(list(result), ["a", "b", "c"])
+        self.assert_equal(list(result["a"][0]), ["y", "z"])
+        self.assert_equal(list(result["c"]), ["e", "f"])
+        with self.assert_raises(TypeError):
+            Structpath.parse("$doc").get(data, sort_keys=True, lazy=True)
+
+    def test_get_unsigned_64_bit_int(self):
+        big_id = 2**64 - 1
+        data = {"ids": [2**63, big_id]}
+
+        result = Structpath.parse("$ids[1]").get(data)
+
+        self.assert_is_instance(result, int)
+        self.assert_equal(result, big_id)
+        self.assert_equal(Structpath.parse("$ids").get(data), [2**63, big_id])
+
+    def test_child_count(self):
+        data = {"users": {"alice": {"age": 30}, "bob": {}}, "tags": [1, 2, 3]}
+
+        self.assert_equal(Structpath.parse("$users").child_count(data), 2)
+        self.assert_equal(Structpath.parse("$tags").child_count(data), 3)
+        self.assert_equal(
+            Structpath.parse("$users.#u").child_count(data, {"u": "bob"}), 0
+        )
+        with self.assert_raises(TypeError):
+            Structpath.parse("$users.alice.age").child_count(data)
+        with self.assert_raises(KeyError):
+            Structpath.parse("$missing").child_count(data)
+
+    def test_get_through_mapping_and_sequence_protocols(self):
+        data = MappingProxyType(
+            {"config": MappingProxyType({"port": 80}), "pairs": (1, (2, 3))}
+        )
+
+        self.assert_equal(Structpath.parse("$config.port").get(data), 80)
+        self.assert_equal(Structpath.parse("$pairs[1][0]").get(data), 2)
+        self.assert_equal(
+            Structpath.parse("$config").get(data), {"port": 80}
+        )
+
+        squares = {"items": _Squares(4)}
+        self.assert_equal(
+            Structpath.parse("$items[3].square").get(squares), 9
+        )
+        self.assert_equal(Structpath.parse("$items.length").get(squares), 4)
+
+        with self.assert_raises(TypeError):
+            Structpath.parse("$raw[0]").get({"raw": b"abc"})
+
+    def test_get_with_type(self):
+        data = {
+            "o": {"k": 1},
+            "a": [True],
+            "s": "text",
+            "n": 1.5,
+            "i": 3,
+            "b": False,
+            "z": None,
+        }
+        cases = [
+            ("$o", {"k": 1}, "object"),
+            ("$a", [True], "array"),
+            ("$s", "text", "string"),
+            ("$n", 1.5, "number"),
+            ("$i", 3, "number"),
+            ("$b", False, "boolean"),
+            ("$a[0]", True, "boolean"),
+            ("$z", None, "null"),
+            ("$a.length", 1, "number"),
+        ]
+        for path_str, value, type_name in cases:
+            result = Structpath.parse(path_str).get(data, with_type=True)
+            self.assert_equal(result, (value, type_name))
+
+        lazy = Structpath.parse("$b").get(data, lazy=True)
+        self.assert_equal(repr(lazy), "LazyValue(boolean)")
+
+        path = Structpath.parse("$missing")
+        self.assert_equal(
+            path.get(data, with_type=True, default=0), (0, None)
+        )
+        with self.assert_raises(KeyError):
+            path.get(data, with_type=True)
+        with self.assert_raises(TypeError):
+            path.get(data, with_type=True, lazy=True)
+        with self.assert_raises(TypeError):
+            path.get(data, with_type=True, trace=True)
+
+    def test_get_duplicate_keys(self):
+        data = {"ids": {1: "a", "1": "b"}}
+        path = Structpath.parse("$ids.1")
+
+        with self.assert_raises(ValueError):
+            path.get(data)
+        with self.assert_raises(ValueError):
+            path.get(data, duplicate_keys="error")
+        self.assert_equal(path.get(data, duplicate_keys="last_wins"), "b")
+        self.assert_equal(path.get(data, duplicate_keys="first_wins"), "a")
+        with self.assert_raises(ValueError):
+            path.get(data, duplicate_keys="merge")
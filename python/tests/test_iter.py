@@ -79,3 +79,102 @@ class TestStructpathIter(StructpathTestCase):
         path = Structpath.parse("$a.#key.c")
         with self.assertRaises(ValueError):
             path.get(data)
+
+    def test_iter_order(self):
+        """Results come out in a fixed order, whatever the dict order."""
+        data = {
+            "teams": {
+                "team2": {"members": {"user4": 88, "user3": 78}},
+                "team1": {"members": {"user2": 92, "user1": 85}},
+            }
+        }
+        path = Structpath.parse("$teams.#teamId.members.#userId")
+
+        results = list(path.iter(data))
+
+        self.assert_equal(
+            results,
+            [
+                ({"teamId": "team1", "userId": "user1"}, 85),
+                ({"teamId": "team1", "userId": "user2"}, 92),
+                ({"teamId": "team2", "userId": "user3"}, 78),
+                ({"teamId": "team2", "userId": "user4"}, 88),
+            ],
+        )
+        for vars_dict, _ in results:
+            self.assert_equal(list(vars_dict), ["teamId", "userId"])
+
+    def test_iter_with_fixed_variables(self):
+        data = {
+            "teams": {
+                "team1": {"members": {"user1": 85, "user2": 92}},
+                "team2": {"members": {"user3": 78, "user4": 88}},
+            }
+        }
+        path = Structpath.parse("$teams.#teamId.members.#userId")
+
+        results = list(path.iter(data, fixed={"teamId": "team1"}))
+
+        self.assert_equal(
+            results,
+            [
+                ({"teamId": "team1", "userId": "user1"}, 85),
+                ({"teamId": "team1", "userId": "user2"}, 92),
+            ],
+        )
+        self.assert_equal(list(path.iter(data, fixed={"teamId": "x"})), [])
+
+    def test_iter_include_path(self):
+        data = {"users": {"alice": {"score": 85}, "bob": {"score": 92}}}
+        path = Structpath.parse("$users.#id.score")
+
+        results = list(path.iter(data, include_path=True))
+
+        self.assert_equal(len(results), 2)
+        for resolved, vars, value in results:
+            self.assert_equal(str(resolved), f"$users.{vars['id']}.score")
+            self.assert_equal(resolved.get(data), value)
+            resolved.write(data, value + 1)
+        self.assert_equal(
+            data, {"users": {"alice": {"score": 86}, "bob": {"score": 93}}}
+        )
+
+        wildcard = Structpath.parse("$users.*.score")
+        with self.assert_raises(ValueError):
+            list(wildcard.iter(data, include_path=True))
+
+    def test_iter_include_path_non_canonical_int_keys(self):
+        data = {"007": 1, "-0": 2, "7": 3}
+
+        results = list(Structpath.parse("$#k").iter(data, include_path=True))
+
+        self.assert_equal(len(results), 3)
+        for resolved, vars, value in results:
+            self.assert_equal(resolved.get(data), value)
+            self.assert_equal(data[vars["k"]], value)
+
+    def test_iter_matches(self):
+        data = {"teams": {"a": {"members": ["Ann", "Bo"]}, "b": {}}}
+        path = Structpath.parse("$teams.*.members[#i]")
+
+        matches = path.iter_matches(data)
+
+        resolved, value = next(matches)
+        self.assert_equal(str(resolved), "$teams.a.members[0]")
+        self.assert_equal(value, "Ann")
+        self.assert_equal(resolved.get(data), value)
+        self.assert_equal(
+            [(str(p), v) for p, v in matches], [("$teams.a.members[1]", "Bo")]
+        )
+        with self.assert_raises(StopIteration):
+            next(matches)
+
+    def test_iter_matches_non_canonical_int_keys(self):
+        data = {"007": 1, "a": 2, "-0": 3}
+
+        matches = list(Structpath.parse("$*").iter_matches(data))
+
+        self.assert_equal(
+            sorted((str(p), v) for p, v in matches),
+            [(r"$\-0", 3), (r"$\007", 1), ("$a", 2)],
+        )
@@ -0,0 +1,47 @@
+from uneedtest import TestCase
+
+from structpath import apply_merge_patch
+
+
+class TestApplyMergePatch(TestCase):
+    def test_rfc_example(self):
+        data = {
+            "title": "Goodbye!",
+            "author": {"givenName": "John", "familyName": "Doe"},
+            "tags": ["example", "sample"],
+            "content": "This will be unchanged",
+        }
+        patch = {
+            "title": "Hello!",
+            "phoneNumber": "+01-123-456-7890",
+            "author": {"familyName": None},
+            "tags": ["example"],
+        }
+
+        result = apply_merge_patch(data, patch)
+
+        expected = {
+            "title": "Hello!",
+            "author": {"givenName": "John"},
+            "tags": ["example"],
+            "content": "This will be unchanged",
+            "phoneNumber": "+01-123-456-7890",
+        }
+        self.assert_equal(result, expected)
+        self.assert_equal(data, expected)
+
+    def test_none_deletes_key(self):
+        data = {"a": "b", "b": "c"}
+
+        result = apply_merge_patch(data, {"a": None, "x": None})
+
+        self.assert_equal(result, {"b": "c"})
+        self.assert_equal(data, {"b": "c"})
+
+    def test_non_object_patch_replaces(self):
+        data = {"a": "b"}
+
+        result = apply_merge_patch(data, ["c"])
+
+        self.assert_equal(result, ["c"])
+        self.assert_equal(data, {"a": "b"})
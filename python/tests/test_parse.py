@@ -28,6 +28,15 @@ class TestStructpathParse(TestCase):
             self.assert_equal(str(new_path), new_path_str)
             # tests/test_structpath.py
 
+    def test_literal_word_keys(self):
+        # "true", "false" and "null" are plain string keys
+        data = {"true": 1, "false": 2, "null": 3}
+
+        for word, expected in data.items():
+            path = Structpath.parse("$" + word)
+            self.assert_equal(str(path), "$" + word)
+            self.assert_equal(path.get(data), expected)
+
     def test_parse_with_variables(self):
         # Test parsing paths with variables
         path = Structpath.parse("$a.#var.c")
@@ -40,3 +49,42 @@ class TestStructpathParse(TestCase):
         # Test with escaped characters
         path = Structpath.parse(r"$a\.#var\[0\].c")
         self.assertEqual(str(path), "$a\\.\\#var\\[0\\].c")
+
+    def test_format(self):
+        path = Structpath.parse(r"$users[0].a\.b.#id")
+
+        self.assert_equal(path.format(), str(path))
+        self.assert_equal(
+            path.format(root="@", separator="/"), r"@users[0]/a\.b/#id"
+        )
+        self.assert_equal(
+            Structpath.parse("$a/b.c").format(root="", separator="/"),
+            r"a\/b/c",
+        )
+        self.assert_equal(
+            Structpath.parse("$café").format(ascii_only=True),
+            r"$caf\u{E9}",
+        )
+        with self.assert_raises(ValueError):
+            path.format(separator="::")
+
+    def test_parse_many(self):
+        text = """
+            // Fields to export
+            $users[0].name
+
+            $users.#id.email
+        """
+
+        paths = Structpath.parse_many(text)
+
+        self.assert_equal(
+            [str(path) for path in paths],
+            ["$users[0].name", "$users.#id.email"],
+        )
+        self.assert_equal(Structpath.parse_many(""), [])
+
+        with self.assert_raises(ValueError) as ctx:
+            Structpath.parse_many("$a\n\n// skipped\n$a[oops]\n$b")
+        self.assert_equal(ctx.exception.line, 4)
+        self.assert_true(ctx.exception.args[0].startswith("Line 4: "))
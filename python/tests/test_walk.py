@@ -244,3 +244,128 @@ class TestStructpathWalk(TestCase):
 
         self.assert_equal(path_value_map["$truthy"], True)
         self.assert_equal(path_value_map["$falsy"], False)
+
+    def test_walk_from_root(self):
+        """Test walking only the subtree at a root path."""
+        data = {
+            "users": [{"name": "Alice"}, {"name": "Bob"}],
+            "config": {"debug": True},
+        }
+
+        for root in ("$users", Structpath.parse("$users")):
+            results = list(Structpath.walk(data, root=root))
+            paths = [str(path) for path, _ in results]
+
+            self.assert_true(paths)
+            for path in paths:
+                self.assert_true(path.startswith("$users"))
+
+            path_value_map = dict(zip(paths, (v for _, v in results)))
+            self.assert_equal(path_value_map["$users[1].name"], "Bob")
+            self.assert_not_in("$config.debug", path_value_map)
+
+    def test_walk_from_missing_root(self):
+        """Test walking from a root that does not exist."""
+        with self.assert_raises(KeyError):
+            list(Structpath.walk({"a": 1}, root="$b"))
+
+    def test_paths_matches_walk(self):
+        """Test that paths() lists the same strings as walk()."""
+        data = {"a": [1, 2, {"b": 3}], "c": {"d": 4}, "e": [], "f.g": None}
+
+        expected = [str(path) for path, _ in Structpath.walk(data)]
+
+        self.assert_equal(Structpath.paths(data), expected)
+
+    def test_leaf_paths(self):
+        """Test that leaf_paths() lists only nodes without children."""
+        data = {"a": [1, 2, {"b": 3}], "c": {"d": 4}, "e": [], "f": {}}
+
+        self.assert_equal(
+            Structpath.leaf_paths(data),
+            ["$a[0]", "$a[1]", "$a[2].b", "$c.d", "$e", "$f"],
+        )
+        self.assert_equal(Structpath.leaf_paths(42), ["$"])
+
+    def test_get_sibling_during_walk(self):
+        """Test resolving a sibling field of each walked node."""
+        data = {
+            "users": [
+                {"name": "Alice", "role": "admin"},
+                {"name": "Bob", "role": "user"},
+            ]
+        }
+
+        roles = {}
+        for path, value in Structpath.walk(data):
+            if str(path).endswith(".name"):
+                roles[value] = path.get_sibling(data, "role")
+
+        self.assert_equal(roles, {"Alice": "admin", "Bob": "user"})
+
+        with self.assert_raises(KeyError):
+            Structpath.parse("$users[0].name").get_sibling(data, "email")
+        with self.assert_raises(ValueError):
+            Structpath().get_sibling(data, "users")
+
+    def test_walk_max_nodes(self):
+        """Test that max_nodes caps the walk and reports truncation."""
+        data = {"items": [{"id": i, "tags": ["x"]} for i in range(1000)]}
+        total = len(list(Structpath.walk(data)))
+
+        walker = Structpath.walk(data, max_nodes=50)
+        results = list(walker)
+
+        self.assert_equal(len(results), 50)
+        self.assert_true(walker.truncated)
+        self.assert_equal(
+            [str(p) for p, _ in results],
+            [str(p) for p, _ in Structpath.walk(data)][:50],
+        )
+
+        walker = Structpath.walk(data, max_nodes=total)
+        self.assert_equal(len(list(walker)), total)
+        self.assert_false(walker.truncated)
+        self.assert_false(Structpath.walk(data).truncated)
+
+    def test_walk_include_kind(self):
+        data = {"a": {"b": [1, {"c": None}], "d": "x"}, "e": []}
+
+        kinds = {
+            str(path): kind
+            for path, _, kind in Structpath.walk(data, include_kind=True)
+        }
+
+        self.assert_equal(
+            kinds,
+            {
+                "$": "object",
+                "$a": "object",
+                "$a.b": "array",
+                "$a.b[0]": "scalar",
+                "$a.b[1]": "object",
+                "$a.b[1].c": "scalar",
+                "$a.d": "scalar",
+                "$e": "array",
+            },
+        )
+        walker = Structpath.walk(data, "$a.b[1]", include_kind=True)
+        self.assert_equal(
+            [(str(path), value, kind) for path, value, kind in walker],
+            [
+                ("$a.b[1].c", None, "scalar"),
+                ("$a.b[1]", {"c": None}, "object"),
+            ],
+        )
+
+    def test_walk_duplicate_keys(self):
+        data = {1: "a", "1": "b"}
+
+        with self.assert_raises(ValueError):
+            list(Structpath.walk(data))
+        for policy, value in [("last_wins", "b"), ("first_wins", "a")]:
+            walked = {
+                str(path): v
+                for path, v in Structpath.walk(data, duplicate_keys=policy)
+            }
+            self.assert_equal(walked, {"$": {"1": value}, "$1": value})
@@ -20,6 +20,12 @@ class TestStructpathWrite(TestCase):
 
         self.assert_equal(result, {"a": {"b": {"c": 42}}})
 
+    def test_write_without_data_or_value(self):
+        path = Structpath.parse("$a[1]")
+
+        self.assert_equal(path.write(), {"a": [None, None]})
+        self.assert_equal(path.write(None, None), {"a": [None, None]})
+
     def test_write_with_array_indices(self):
         path = Structpath.parse("$a[0].b[1]")
         data = {}
@@ -77,6 +83,30 @@ class TestStructpathWrite(TestCase):
 
         self.assert_equal(result, {"a": [None, None, {"c": 42}]})
 
+    def test_write_with_int_index_variable(self):
+        path = Structpath.parse("$items[#i]")
+        data = {"items": ["a", "b", "c"]}
+
+        result = path.write(data, "z", {"i": 2})
+
+        self.assert_equal(result, {"items": ["a", "b", "z"]})
+        self.assert_equal(path.get(data, {"i": 2}), "z")
+        self.assert_equal(
+            list(path.iter(data, fixed={"i": 0})), [({"i": 0}, "a")]
+        )
+        with self.assert_raises(TypeError):
+            path.write(data, "z", {"i": True})
+        with self.assert_raises(TypeError):
+            path.write(data, "z", {"i": 1.0})
+
+    def test_write_creates_array_before_index_variable(self):
+        for data in [None, {}]:
+            result = Structpath.parse("$a[#i].b").write(data, 1, {"i": "0"})
+            self.assert_equal(result, {"a": [{"b": 1}]})
+
+            result = Structpath.parse("$a[0].b").write(data, 1)
+            self.assert_equal(result, {"a": [{"b": 1}]})
+
     def test_write_with_mixed_variables(self):
         path = Structpath.parse("$teams[#idx].members.#name")
         data = {}
@@ -151,6 +181,16 @@ class TestStructpathWrite(TestCase):
         with self.assert_raises(ValueError):
             path.write(data, 42, vars)
 
+    def test_write_terminal_index_variable_invalid_value(self):
+        path = Structpath.parse("$a[#idx]")
+        data = {"a": [1]}
+        vars = {"idx": "not_a_number"}
+
+        with self.assert_raises(ValueError):
+            path.write(data, 42, vars)
+
+        self.assert_equal(data, {"a": [1]})
+
     def test_write_path_starting_with_index(self):
         path = Structpath.parse("$[0].name")
 
@@ -181,3 +221,167 @@ class TestStructpathWrite(TestCase):
             "settings": {"theme": "dark"},
         }
         self.assert_equal(result3, expected)
+
+    def test_write_single_key_into_large_dict(self):
+        siblings = {f"key{i}": {"nested": [i, str(i)]} for i in range(10_000)}
+        data = dict(siblings)
+        blob = {"items": [{"id": i} for i in range(1_000)]}
+
+        result = Structpath.parse("$blob").write(data, blob)
+
+        self.assert_equal(data["blob"], blob)
+        self.assert_equal(result, data)
+        self.assert_equal(len(data), 10_001)
+        # The value is stored as a converted copy, as with any other write
+        self.assert_is_not(data["blob"], blob)
+        # The result is a shallow copy, so the untouched siblings are
+        # neither converted nor rebuilt in data or in the result
+        self.assert_is_not(result, data)
+        self.assert_is(result["blob"], data["blob"])
+        for key, value in siblings.items():
+            self.assert_is(data[key], value)
+            self.assert_is(result[key], value)
+
+    def test_write_single_key_like_general_write(self):
+        data = {1: "a", "b": 2}
+
+        result = Structpath.parse("$1").write(data, 5)
+
+        # The int key is updated in place, as by a deeper write, and kept
+        # in the shallow copy returned
+        self.assert_equal(data, {1: 5, "b": 2})
+        self.assert_equal(result, {1: 5, "b": 2})
+        # Only the written value is converted, not the siblings
+        bad = object()
+        result = Structpath.parse("$x").write({"bad": bad}, 1)
+        self.assert_is(result["bad"], bad)
+        with self.assert_raises(TypeError):
+            Structpath.parse("$x").write({"bad": 1}, object())
+        with self.assert_raises(TypeError):
+            Structpath.parse("$x").write({(1, 2): 1}, 1)
+
+    def test_write_single_key_converts_value(self):
+        data = {"a": 1}
+
+        Structpath.parse("$b").write(data, [3, {"d": None}])
+        Structpath.parse("$1").write(data, "one")
+
+        self.assert_equal(data, {"a": 1, "b": [3, {"d": None}], "1": "one"})
+        self.assert_equal(
+            Structpath.parse("$b").write(None, [3, {"d": None}]),
+            {"b": [3, {"d": None}]},
+        )
+
+    def test_write_root_type_follows_first_segment(self):
+        self.assert_equal(Structpath.parse("$0").write(None, "a"), {"0": "a"})
+        self.assert_equal(Structpath.parse("$[0]").write(None, "a"), ["a"])
+        self.assert_equal(
+            Structpath.parse("$0[1]").write(None, "a"), {"0": [None, "a"]}
+        )
+        self.assert_equal(
+            Structpath.parse("$[1].0").write(None, "a"), [None, {"0": "a"}]
+        )
+
+    def test_write_into_scalar_root(self):
+        path = Structpath.parse("$a")
+
+        self.assert_equal(path.write(5, 1), {"a": 1})
+        with self.assert_raises(TypeError):
+            path.write(5, 1, strict_root=True)
+        self.assert_equal(path.write(None, 1, strict_root=True), {"a": 1})
+        self.assert_equal(
+            path.write({"b": 2}, 1, strict_root=True), {"a": 1, "b": 2}
+        )
+
+    def test_write_skip_none(self):
+        path = Structpath.parse("$user")
+        value = {"name": "Ann", "email": None, "tags": [None]}
+
+        result = path.write({"id": None, "ok": True}, value, skip_none=True)
+        self.assert_equal(
+            result, {"ok": True, "user": {"name": "Ann", "tags": [None]}}
+        )
+
+        result = path.write({"id": None}, value)
+        self.assert_equal(result, {"id": None, "user": value})
+
+    def test_write_keeps_untouched_values_in_place(self):
+        settings = {"theme": "dark"}
+        items = [{"id": 1}, {"id": 2}]
+        data = {"user": {"settings": settings, "items": items}}
+
+        Structpath.parse("$user.name").write(data, "Ann")
+        Structpath.parse("$user.items[1].id").write(data, 3)
+        Structpath.parse("$user.items[3]").write(data, {"id": 4})
+
+        self.assert_equal(
+            data,
+            {
+                "user": {
+                    "settings": {"theme": "dark"},
+                    "items": [{"id": 1}, {"id": 3}, None, {"id": 4}],
+                    "name": "Ann",
+                }
+            },
+        )
+        self.assert_is(data["user"]["settings"], settings)
+        self.assert_is(data["user"]["items"], items)
+
+    def test_write_updates_list_in_place(self):
+        first = {"name": "a"}
+        data = [first, {"name": "b"}]
+
+        Structpath.parse("$[#i].name").write(data, "c", {"i": 1})
+
+        self.assert_equal(data, [{"name": "a"}, {"name": "c"}])
+        self.assert_is(data[0], first)
+
+    def test_write_duplicate_keys(self):
+        path = Structpath.parse("$ids.2")
+
+        with self.assert_raises(ValueError):
+            path.write({"ids": {1: "a", "1": "b"}}, "c")
+        result = path.write(
+            {"ids": {1: "a", "1": "b"}}, "c", duplicate_keys="last_wins"
+        )
+        self.assert_equal(result, {"ids": {"1": "b", "2": "c"}})
+        result = path.write(
+            {"ids": {1: "a", "1": "b"}}, "c", duplicate_keys="first_wins"
+        )
+        self.assert_equal(result, {"ids": {"1": "a", "2": "c"}})
+
+    def test_write_single_key_duplicate_keys(self):
+        path = Structpath.parse("$x")
+
+        with self.assert_raises(ValueError):
+            path.write({1: "a", "1": "b"}, 5)
+        result = path.write({1: "a", "1": "b"}, 5, duplicate_keys="last_wins")
+        self.assert_equal(result, {"1": "b", "x": 5})
+        result = path.write(
+            {1: "a", "1": "b"}, 5, duplicate_keys="first_wins"
+        )
+        self.assert_equal(result, {"1": "a", "x": 5})
+
+    def test_unset_vs_write_none(self):
+        data = {"config": {"timeout": 30, "retries": 3}}
+        timeout = Structpath.parse("$config.timeout")
+
+        timeout.write(data, None)
+        self.assert_in("timeout", data["config"])
+        self.assert_is_none(timeout.get(data))
+
+        self.assert_true(timeout.unset(data))
+        self.assert_not_in("timeout", data["config"])
+        with self.assert_raises(KeyError):
+            timeout.get(data)
+
+        self.assert_false(timeout.unset(data))
+        self.assert_false(Structpath.parse("$missing.key").unset(data))
+        self.assert_equal(data, {"config": {"retries": 3}})
+
+    def test_unset_list_element(self):
+        items = ["a", "b", "c"]
+
+        self.assert_true(Structpath.parse("$[#i]").unset(items, {"i": "1"}))
+
+        self.assert_equal(items, ["a", "c"])
@@ -1,12 +1,258 @@
+use crate::target::PathTarget;
 use crate::types::{Segment, SegmentKey, Structpath, StructpathError};
-use serde_json::Value;
+use serde_json::{Map, Value};
+use std::borrow::Cow;
 use std::collections::HashMap;
 
-pub fn get<'a>(
+/// Options controlling how paths are resolved by `get_with`.
+#[derive(Debug, Clone, Default)]
+pub struct GetOptions {
+    /// Let key segments look inside arrays of `[key, value]` pairs, matching
+    /// the first element of each pair against the key. Off by default, where
+    /// a key segment on an array is an `InvalidPath` error.
+    pub assoc_arrays: bool,
+    /// Parse a string that is reached with segments left as JSON and keep
+    /// resolving inside it, for documents embedding JSON as strings. Only
+    /// strings holding an object or array are parsed. As the parsed value
+    /// is not part of the data, only `get_value_with` honors this; `get_with`
+    /// still fails with `InvalidPath` on such a string.
+    pub parse_embedded_json: bool,
+    /// A namespace prefix of object keys: a key segment that has no exact
+    /// match also matches the key with this prefix, so `$user.name` finds
+    /// `{"ns:user": {"ns:name": ..}}` with the prefix `ns:`. An exact
+    /// match always wins.
+    pub key_prefix: Option<String>,
+    /// Clamp indices to the array instead of failing: an index past the end
+    /// selects the last element, and a negative index variable value
+    /// counts from the end, so `-1` is the last element and anything before
+    /// the start is the first. This changes what a path means, as a
+    /// mistyped index then silently finds a different element. Only an
+    /// empty array still fails with `IndexOutOfBounds`.
+    pub clamp_indices: bool,
+}
+
+pub fn get<'a, T: PathTarget>(
+    path: &Structpath,
+    data: &'a T,
+    vars: Option<&HashMap<String, String>>,
+) -> Result<&'a T, StructpathError> {
+    get_with(path, data, vars, &GetOptions::default())
+}
+
+pub fn get_with<'a, T: PathTarget>(
+    path: &Structpath,
+    data: &'a T,
+    vars: Option<&HashMap<String, String>>,
+    options: &GetOptions,
+) -> Result<&'a T, StructpathError> {
+    check_resolvable(path, vars)?;
+    resolve_segments(path.segments(), data, vars, options)
+}
+
+/// Resolves `path` like `get`, computing a final length segment from the
+/// array or object it applies to.
+pub fn get_value<'a>(
+    path: &Structpath,
+    data: &'a Value,
+    vars: Option<&HashMap<String, String>>,
+) -> Result<Cow<'a, Value>, StructpathError> {
+    get_value_with(path, data, vars, &GetOptions::default())
+}
+
+pub fn get_value_with<'a>(
     path: &Structpath,
     data: &'a Value,
     vars: Option<&HashMap<String, String>>,
+    options: &GetOptions,
+) -> Result<Cow<'a, Value>, StructpathError> {
+    check_resolvable(path, vars)?;
+
+    let Some((Segment::Length, parent)) = path.segments().split_last() else {
+        return resolve_value_segments(path.segments(), data, vars, options);
+    };
+
+    let node = resolve_value_segments(parent, data, vars, options)?;
+    let len = match embedded_json(&node, options) {
+        Some(embedded) => node_len(&embedded)?,
+        None => node_len(node.as_ref())?,
+    };
+    Ok(Cow::Owned(Value::from(len)))
+}
+
+/// Resolves `segments` like `resolve_segments`, continuing inside strings
+/// holding JSON if `options.parse_embedded_json` is set.
+fn resolve_value_segments<'a>(
+    segments: &[Segment],
+    data: &'a Value,
+    vars: Option<&HashMap<String, String>>,
+    options: &GetOptions,
+) -> Result<Cow<'a, Value>, StructpathError> {
+    if !options.parse_embedded_json {
+        return resolve_segments(segments, data, vars, options)
+            .map(Cow::Borrowed);
+    }
+
+    let mut current = data;
+    for (i, segment) in segments.iter().enumerate() {
+        if let Some(embedded) = embedded_json(current, options) {
+            let rest = resolve_value_segments(
+                &segments[i..],
+                &embedded,
+                vars,
+                options,
+            )?;
+            return Ok(Cow::Owned(rest.into_owned()));
+        }
+        current = match segment {
+            // The occurrence counts keys the rest of the path resolves
+            // under, embedded JSON included
+            Segment::NthKeyVariable(var_name, n) => {
+                let rest = &segments[i + 1..];
+                nth_entry(current, var_name, *n, |_, child| {
+                    resolve_value_segments(rest, child, vars, options).is_ok()
+                })?
+                .1
+            }
+            _ => resolve_segments(
+                std::slice::from_ref(segment),
+                current,
+                vars,
+                options,
+            )?,
+        };
+    }
+    Ok(Cow::Borrowed(current))
+}
+
+/// Parses `node` if it is a string holding a JSON object or array and
+/// embedded JSON is to be parsed.
+fn embedded_json(node: &Value, options: &GetOptions) -> Option<Value> {
+    let Value::String(text) = node else {
+        return None;
+    };
+    if !options.parse_embedded_json {
+        return None;
+    }
+    serde_json::from_str(text)
+        .ok()
+        .filter(|parsed: &Value| parsed.is_object() || parsed.is_array())
+}
+
+/// Returns the number of keys or elements of the node at `path`.
+pub fn child_count<T: PathTarget>(
+    path: &Structpath,
+    data: &T,
+    vars: Option<&HashMap<String, String>>,
+) -> Result<usize, StructpathError> {
+    node_len(get(path, data, vars)?)
+}
+
+fn node_len<T: PathTarget>(node: &T) -> Result<usize, StructpathError> {
+    node.as_array()
+        .map(<[T]>::len)
+        .or_else(|| node.object_len())
+        .ok_or_else(|| StructpathError::InvalidPath {
+            expected: "array or object".to_string(),
+            found: format!("{:?}", node),
+        })
+}
+
+/// Resolves `path` with `map` as the root object, without wrapping it in a
+/// `Value`. The path must have at least one segment.
+pub fn get_in_object<'a>(
+    path: &Structpath,
+    map: &'a Map<String, Value>,
+    vars: Option<&HashMap<String, String>>,
+) -> Result<&'a Value, StructpathError> {
+    check_resolvable(path, vars)?;
+    let (first, rest) = split_root(path, "object")?;
+
+    let child = match first {
+        Segment::Key(key) => {
+            let mut buffer = itoa::Buffer::new();
+            let key = match key {
+                SegmentKey::String(s) => s.as_str(),
+                SegmentKey::Int(i) => buffer.format(*i),
+            };
+            map.get(key).ok_or(StructpathError::NotFound)?
+        }
+        Segment::KeyVariable(var_name) => map
+            .get(variable_value(vars, var_name)?)
+            .ok_or(StructpathError::NotFound)?,
+        Segment::NthKeyVariable(var_name, n) => {
+            let options = GetOptions::default();
+            let mut resolving = map.values().filter(|child| {
+                resolve_segments(rest, *child, vars, &options).is_ok()
+            });
+            resolving
+                .nth(*n)
+                .ok_or_else(|| nth_out_of_bounds(var_name, *n, map.len()))?
+        }
+        Segment::Index(_)
+        | Segment::FindValue(_)
+        | Segment::IndexVariable(_) => {
+            return Err(StructpathError::InvalidPath {
+                expected: "array".to_string(),
+                found: "object".to_string(),
+            })
+        }
+        Segment::Length => return Err(StructpathError::UnexpectedLength),
+        _ => return Err(StructpathError::UnexpectedWildcard),
+    };
+
+    resolve_segments(rest, child, vars, &GetOptions::default())
+}
+
+/// Resolves `path` with `arr` as the root array, without wrapping it in a
+/// `Value`. The path must have at least one segment.
+pub fn get_in_array<'a>(
+    path: &Structpath,
+    arr: &'a [Value],
+    vars: Option<&HashMap<String, String>>,
 ) -> Result<&'a Value, StructpathError> {
+    check_resolvable(path, vars)?;
+    let (first, rest) = split_root(path, "array")?;
+
+    let idx = match first {
+        Segment::Index(idx) => *idx,
+        Segment::FindValue(value) => arr
+            .iter()
+            .position(|element| element == value)
+            .ok_or(StructpathError::NotFound)?,
+        Segment::IndexVariable(var_name) => {
+            let var_value = variable_value(vars, var_name)?;
+            var_value.parse::<usize>().map_err(|_| {
+                StructpathError::InvalidVariableValue(var_value.to_string())
+            })?
+        }
+        Segment::Key(_)
+        | Segment::KeyVariable(_)
+        | Segment::NthKeyVariable(_, _) => {
+            return Err(StructpathError::InvalidPath {
+                expected: "object".to_string(),
+                found: "array".to_string(),
+            })
+        }
+        Segment::Length => return Err(StructpathError::UnexpectedLength),
+        _ => return Err(StructpathError::UnexpectedWildcard),
+    };
+    let child = arr.get(idx).ok_or_else(|| {
+        StructpathError::IndexOutOfBounds(format!(
+            "Index {} out of bounds for array of length {}",
+            idx,
+            arr.len()
+        ))
+    })?;
+
+    resolve_segments(rest, child, vars, &GetOptions::default())
+}
+
+/// Checks the conditions every single-value lookup needs: a variable
+/// context if the path has variables, and no parent segments.
+fn check_resolvable(
+    path: &Structpath,
+    vars: Option<&HashMap<String, String>>,
+) -> Result<(), StructpathError> {
     // Check if path contains variables
     let has_variables = path.segments().iter().any(|segment| {
         matches!(segment, Segment::KeyVariable(_) | Segment::IndexVariable(_))
@@ -20,16 +266,69 @@ pub fn get<'a>(
         ));
     }
 
+    if path.segments().contains(&Segment::Parent) {
+        return Err(StructpathError::UnnormalizedPath);
+    }
+
+    Ok(())
+}
+
+/// Splits off the segment resolved directly against a bare root container,
+/// which cannot itself be returned as a `Value`.
+fn split_root<'p>(
+    path: &'p Structpath,
+    container: &str,
+) -> Result<(&'p Segment, &'p [Segment]), StructpathError> {
+    path.segments()
+        .split_first()
+        .ok_or_else(|| StructpathError::InvalidPath {
+            expected: "a path below the root".to_string(),
+            found: format!("the root {}", container),
+        })
+}
+
+fn variable_value<'v>(
+    vars: Option<&'v HashMap<String, String>>,
+    var_name: &str,
+) -> Result<&'v str, StructpathError> {
+    vars.and_then(|variables| variables.get(var_name))
+        .map(String::as_str)
+        .ok_or_else(|| StructpathError::MissingVariable(var_name.to_string()))
+}
+
+pub(crate) fn resolve_segments<'a, T: PathTarget>(
+    segments: &[Segment],
+    data: &'a T,
+    vars: Option<&HashMap<String, String>>,
+    options: &GetOptions,
+) -> Result<&'a T, StructpathError> {
     let mut current = data;
 
-    for segment in path.segments() {
+    for (i, segment) in segments.iter().enumerate() {
         match segment {
             Segment::Key(key) => {
-                current = get_by_key(current, key)?;
+                // Keys are borrowed, and integer keys are formatted on the
+                // stack, so a successful lookup never allocates.
+                current = match key {
+                    SegmentKey::String(s) => {
+                        get_by_string_key(current, s, options)?
+                    }
+                    SegmentKey::Int(i) => {
+                        let mut buffer = itoa::Buffer::new();
+                        get_by_string_key(current, buffer.format(*i), options)?
+                    }
+                };
+            }
+            Segment::Index(idx) if options.clamp_indices => {
+                let idx = i64::try_from(*idx).unwrap_or(i64::MAX);
+                current = get_by_clamped_index(current, idx)?;
             }
             Segment::Index(idx) => {
                 current = get_by_index(current, *idx)?;
             }
+            Segment::FindValue(value) => {
+                current = find_value(current, value)?.1;
+            }
             Segment::KeyVariable(var_name) => {
                 // Safe to unwrap here because we already checked that vars is Some if path has variables
                 let variables = vars.unwrap();
@@ -40,7 +339,7 @@ pub fn get<'a>(
                 })?;
 
                 // Use it as a string key - this is a key variable
-                current = get_by_string_key(current, var_value)?;
+                current = get_by_string_key(current, var_value, options)?;
             }
             Segment::IndexVariable(var_name) => {
                 // Safe to unwrap here because we already checked that vars is Some if path has variables
@@ -52,11 +351,35 @@ pub fn get<'a>(
                 })?;
 
                 // Parse as index - this is an index variable
-                let idx = var_value.parse::<usize>().map_err(|_| {
-                    StructpathError::InvalidVariableValue(var_value.clone())
-                })?;
-
-                current = get_by_index(current, idx)?;
+                let invalid =
+                    || StructpathError::InvalidVariableValue(var_value.clone());
+                current = if options.clamp_indices {
+                    let idx =
+                        var_value.parse::<i64>().map_err(|_| invalid())?;
+                    get_by_clamped_index(current, idx)?
+                } else {
+                    let idx =
+                        var_value.parse::<usize>().map_err(|_| invalid())?;
+                    get_by_index(current, idx)?
+                };
+            }
+            Segment::NthKeyVariable(var_name, n) => {
+                let rest = &segments[i + 1..];
+                current = nth_entry(current, var_name, *n, |_, child| {
+                    resolve_segments(rest, child, vars, options).is_ok()
+                })?
+                .1;
+            }
+            Segment::Wildcard
+            | Segment::IndexWildcard
+            | Segment::KeyPattern(_) => {
+                return Err(StructpathError::UnexpectedWildcard);
+            }
+            Segment::Parent => {
+                return Err(StructpathError::UnnormalizedPath);
+            }
+            Segment::Length => {
+                return Err(StructpathError::UnexpectedLength);
             }
         }
     }
@@ -64,39 +387,475 @@ pub fn get<'a>(
     Ok(current)
 }
 
-fn get_by_key<'a>(
+/// Tells whether `path` fits the shape of `data`: every segment that can be
+/// followed meets the kind of node it needs, an object for keys and an
+/// array for indexes. A missing key or index ends the walk as a fit, since
+/// the path is then only absent. Wildcards must fit for every child they
+/// match, and a variable missing from `vars` only has the kind of its node
+/// checked.
+pub fn is_valid_for<T: PathTarget>(
+    path: &Structpath,
+    data: &T,
+    vars: Option<&HashMap<String, String>>,
+) -> bool {
+    fits_shape(path.segments(), data, vars)
+}
+
+fn fits_shape<T: PathTarget>(
+    segments: &[Segment],
+    node: &T,
+    vars: Option<&HashMap<String, String>>,
+) -> bool {
+    let Some((segment, rest)) = segments.split_first() else {
+        return true;
+    };
+
+    let children: Vec<&T> = match segment {
+        Segment::Wildcard => match node.entries() {
+            Some(entries) => entries.map(|(_, child)| child).collect(),
+            None => return false,
+        },
+        Segment::KeyPattern(pattern) => match node.entries() {
+            Some(entries) => entries
+                .filter(|(key, _)| pattern_matches(pattern, key))
+                .map(|(_, child)| child)
+                .collect(),
+            None => return false,
+        },
+        Segment::IndexWildcard => match node.as_array() {
+            Some(arr) => arr.iter().collect(),
+            None => return false,
+        },
+        Segment::Length => {
+            return rest.is_empty() && (node.is_object() || node.is_array())
+        }
+        Segment::Parent => return false,
+        Segment::KeyVariable(var_name)
+            if variable_value(vars, var_name).is_err() =>
+        {
+            return node.is_object()
+        }
+        Segment::IndexVariable(var_name)
+            if variable_value(vars, var_name).is_err() =>
+        {
+            return node.is_array()
+        }
+        _ => match resolve_segments(
+            std::slice::from_ref(segment),
+            node,
+            vars,
+            &GetOptions::default(),
+        ) {
+            Ok(child) => vec![child],
+            Err(
+                StructpathError::NotFound
+                | StructpathError::IndexOutOfBounds(_),
+            ) => return true,
+            Err(_) => return false,
+        },
+    };
+    children
+        .into_iter()
+        .all(|child| fits_shape(rest, child, vars))
+}
+
+/// One segment of a traced lookup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceStep {
+    pub segment: Segment,
+    /// Whether the segment found a value
+    pub resolved: bool,
+    /// The JSON type of the value the segment was applied to, as named by
+    /// `json_type_name`
+    pub found: &'static str,
+}
+
+/// Resolves `path` like `get`, recording a step for every segment tried.
+/// A failed lookup ends with the step of the segment that failed; errors
+/// found before any segment is tried leave the trace empty.
+pub fn trace_get<'a>(
+    path: &Structpath,
     data: &'a Value,
-    key: &SegmentKey,
-) -> Result<&'a Value, StructpathError> {
-    if let Value::Object(map) = data {
-        let lookup_key = match key {
-            SegmentKey::String(s) => s.clone(),
-            SegmentKey::Int(i) => i.to_string(),
+    vars: Option<&HashMap<String, String>>,
+) -> (Result<&'a Value, StructpathError>, Vec<TraceStep>) {
+    let mut steps = Vec::new();
+    if let Err(err) = check_resolvable(path, vars) {
+        return (Err(err), steps);
+    }
+
+    let options = GetOptions::default();
+    let mut current = data;
+    for segment in path.segments() {
+        let found = json_type_name(current);
+        let result = resolve_segments(
+            std::slice::from_ref(segment),
+            current,
+            vars,
+            &options,
+        );
+        steps.push(TraceStep {
+            segment: segment.clone(),
+            resolved: result.is_ok(),
+            found,
+        });
+        match result {
+            Ok(next) => current = next,
+            Err(err) => return (Err(err), steps),
+        }
+    }
+
+    (Ok(current), steps)
+}
+
+/// The JSON type name of `value`: "object", "array", "string", "number",
+/// "boolean" or "null".
+pub fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Resolves `path` like `get` and returns the concrete path of the node
+/// found, with every variable replaced by the key or index it stood for.
+/// Keys are written the way the walker writes them, so the same location
+/// always gives the same path. Only keys that are an integer in canonical
+/// form become int keys; "007" or "-0" stay string keys.
+pub fn resolved_path<T: PathTarget>(
+    path: &Structpath,
+    data: &T,
+    vars: Option<&HashMap<String, String>>,
+) -> Result<Structpath, StructpathError> {
+    check_resolvable(path, vars)?;
+    let options = GetOptions::default();
+
+    let segments = path.segments();
+    let mut resolved = Structpath::new();
+    let mut current = data;
+    for (i, segment) in segments.iter().enumerate() {
+        let concrete = match segment {
+            Segment::Key(SegmentKey::String(key)) => concrete_key(key),
+            Segment::KeyVariable(var_name) => {
+                concrete_key(variable_value(vars, var_name)?)
+            }
+            Segment::IndexVariable(var_name) => {
+                let var_value = variable_value(vars, var_name)?;
+                let idx = var_value.parse::<usize>().map_err(|_| {
+                    StructpathError::InvalidVariableValue(var_value.to_string())
+                })?;
+                Segment::Index(idx)
+            }
+            Segment::NthKeyVariable(var_name, n) => {
+                let rest = &segments[i + 1..];
+                let (key, _) = nth_entry(current, var_name, *n, |_, child| {
+                    resolve_segments(rest, child, vars, &options).is_ok()
+                })?;
+                concrete_key(&key)
+            }
+            Segment::FindValue(value) => {
+                Segment::Index(find_value(current, value)?.0)
+            }
+            other => other.clone(),
         };
+        current = resolve_segments(
+            std::slice::from_ref(&concrete),
+            current,
+            None,
+            &options,
+        )?;
+        // Concrete segments have no variables, so this cannot fail
+        let _ = resolved.push_segment(concrete);
+    }
 
-        if let Some(value) = map.get(&lookup_key) {
-            Ok(value)
-        } else {
-            Err(StructpathError::NotFound)
+    Ok(resolved)
+}
+
+/// The key segment for an object key, an int key if the key is the exact
+/// text of an integer. Other keys that parse as one, like "007", "+5" or
+/// "-0", would be looked up as a different key and stay string keys.
+pub(crate) fn concrete_key(key: &str) -> Segment {
+    match key.parse::<i64>() {
+        Ok(int_key) if int_key.to_string() == key => {
+            Segment::Key(SegmentKey::Int(int_key))
         }
-    } else {
-        Err(StructpathError::InvalidPath {
+        _ => Segment::Key(SegmentKey::String(key.to_string())),
+    }
+}
+
+/// Finds the first element of an array equal to `value`, for a value
+/// search segment.
+pub(crate) fn find_value<'a, T: PathTarget>(
+    data: &'a T,
+    value: &Value,
+) -> Result<(usize, &'a T), StructpathError> {
+    let Some(arr) = data.as_array() else {
+        return Err(StructpathError::InvalidPath {
+            expected: "array".to_string(),
+            found: format!("{:?}", data),
+        });
+    };
+
+    arr.iter()
+        .enumerate()
+        .find(|(_, element)| element.equals_json(value))
+        .ok_or(StructpathError::NotFound)
+}
+
+/// Resolves the `n`th entry of an object for an occurrence-selecting key
+/// variable. Entries are taken in the map's iteration order, and only
+/// those `resolves` accepts count as an occurrence, so that a key the rest
+/// of the path cannot follow is skipped.
+pub(crate) fn nth_entry<'a, T: PathTarget>(
+    data: &'a T,
+    var_name: &str,
+    n: usize,
+    mut resolves: impl FnMut(&str, &'a T) -> bool,
+) -> Result<(String, &'a T), StructpathError> {
+    let (Some(entries), Some(len)) = (data.entries(), data.object_len()) else {
+        return Err(StructpathError::InvalidPath {
             expected: "object".to_string(),
             found: format!("{:?}", data),
-        })
+        });
+    };
+
+    entries
+        .filter(|(key, child)| resolves(key, child))
+        .nth(n)
+        .map(|(key, child)| (key.into_owned(), child))
+        .ok_or_else(|| nth_out_of_bounds(var_name, n, len))
+}
+
+fn nth_out_of_bounds(var_name: &str, n: usize, len: usize) -> StructpathError {
+    StructpathError::IndexOutOfBounds(format!(
+        "Occurrence {} of #{} out of bounds: fewer than {} of the object's \
+         {} keys resolve the rest of the path",
+        n,
+        var_name,
+        n + 1,
+        len
+    ))
+}
+
+pub fn get_all_entries<'a, T: PathTarget>(
+    path: &Structpath,
+    data: &'a T,
+    vars: Option<&HashMap<String, String>>,
+) -> Vec<(String, &'a T)> {
+    entries_along(path.segments(), data, vars)
+}
+
+fn entries_along<'a, T: PathTarget>(
+    segments: &[Segment],
+    data: &'a T,
+    vars: Option<&HashMap<String, String>>,
+) -> Vec<(String, &'a T)> {
+    // Breadth-first over the segments: every match of the previous segment
+    // is expanded by the next one. Segments that fail to resolve (including
+    // unbound variables) simply drop that match.
+    let mut current = vec![(String::new(), data)];
+
+    for (i, segment) in segments.iter().enumerate() {
+        let mut next = Vec::new();
+
+        for (_, value) in current {
+            match segment {
+                Segment::Key(key) => {
+                    let lookup_key = match key {
+                        SegmentKey::String(s) => s.clone(),
+                        SegmentKey::Int(i) => i.to_string(),
+                    };
+                    if let Some(child) = value.get_key(&lookup_key) {
+                        next.push((lookup_key, child));
+                    }
+                }
+                Segment::Index(idx) => {
+                    if let Some(child) = value.get_index(*idx) {
+                        next.push((idx.to_string(), child));
+                    }
+                }
+                Segment::FindValue(literal) => {
+                    if let Ok((idx, child)) = find_value(value, literal) {
+                        next.push((idx.to_string(), child));
+                    }
+                }
+                Segment::KeyVariable(var_name) => {
+                    let var_value = vars.and_then(|v| v.get(var_name));
+                    if let Some(key) = var_value {
+                        if let Some(child) = value.get_key(key) {
+                            next.push((key.clone(), child));
+                        }
+                    }
+                }
+                Segment::IndexVariable(var_name) => {
+                    let idx = vars
+                        .and_then(|v| v.get(var_name))
+                        .and_then(|v| v.parse::<usize>().ok());
+                    if let Some(idx) = idx {
+                        if let Some(child) = value.get_index(idx) {
+                            next.push((idx.to_string(), child));
+                        }
+                    }
+                }
+                Segment::NthKeyVariable(var_name, n) => {
+                    let rest = &segments[i + 1..];
+                    let entry = nth_entry(value, var_name, *n, |_, child| {
+                        !entries_along(rest, child, vars).is_empty()
+                    });
+                    if let Ok(entry) = entry {
+                        next.push(entry);
+                    }
+                }
+                Segment::Wildcard => {
+                    if let Some(entries) = value.entries() {
+                        next.extend(
+                            entries
+                                .map(|(key, child)| (key.into_owned(), child)),
+                        );
+                    }
+                }
+                Segment::KeyPattern(pattern) => {
+                    if let Some(entries) = value.entries() {
+                        next.extend(
+                            entries
+                                .filter(|(key, _)| {
+                                    pattern_matches(pattern, key)
+                                })
+                                .map(|(key, child)| (key.into_owned(), child)),
+                        );
+                    }
+                }
+                Segment::IndexWildcard => {
+                    if let Some(arr) = value.as_array() {
+                        next.extend(
+                            arr.iter()
+                                .enumerate()
+                                .map(|(idx, child)| (idx.to_string(), child)),
+                        );
+                    }
+                }
+                // Unnormalized paths and lengths match nothing
+                Segment::Parent | Segment::Length => {}
+            }
+        }
+
+        current = next;
     }
+
+    current
 }
 
-fn get_by_string_key<'a>(
-    data: &'a Value,
+/// Like `get_all_entries`, but first checks that the path can be evaluated
+/// at all: every variable is bound (index variables to an index) and there
+/// are no parent or length segments.
+pub fn try_get_all_entries<'a, T: PathTarget>(
+    path: &Structpath,
+    data: &'a T,
+    vars: Option<&HashMap<String, String>>,
+) -> Result<Vec<(String, &'a T)>, StructpathError> {
+    for segment in path.segments() {
+        match segment {
+            Segment::KeyVariable(var_name) => {
+                variable_value(vars, var_name)?;
+            }
+            Segment::IndexVariable(var_name) => {
+                let var_value = variable_value(vars, var_name)?;
+                var_value.parse::<usize>().map_err(|_| {
+                    StructpathError::InvalidVariableValue(var_value.to_string())
+                })?;
+            }
+            Segment::Parent => return Err(StructpathError::UnnormalizedPath),
+            Segment::Length => return Err(StructpathError::UnexpectedLength),
+            _ => {}
+        }
+    }
+
+    Ok(get_all_entries(path, data, vars))
+}
+
+/// Returns true if `key` matches the glob `pattern` of a key pattern
+/// segment.
+pub(crate) fn pattern_matches(pattern: &str, key: &str) -> bool {
+    enum Token {
+        Literal(char),
+        AnyChar,
+        AnyRun,
+    }
+
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '\\' => Token::Literal(chars.next().unwrap_or('\\')),
+            '?' => Token::AnyChar,
+            '*' => Token::AnyRun,
+            _ => Token::Literal(c),
+        });
+    }
+    let key: Vec<char> = key.chars().collect();
+
+    // On a mismatch, let the most recent `*` swallow one more character and
+    // retry from the token after it.
+    let (mut t, mut k) = (0, 0);
+    let mut last_run: Option<(usize, usize)> = None;
+    while k < key.len() {
+        match tokens.get(t) {
+            Some(Token::AnyRun) => {
+                last_run = Some((t, k));
+                t += 1;
+            }
+            Some(Token::AnyChar) => {
+                t += 1;
+                k += 1;
+            }
+            Some(Token::Literal(c)) if *c == key[k] => {
+                t += 1;
+                k += 1;
+            }
+            _ => match last_run {
+                Some((run_t, run_k)) => {
+                    last_run = Some((run_t, run_k + 1));
+                    t = run_t + 1;
+                    k = run_k + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    tokens[t..]
+        .iter()
+        .all(|token| matches!(token, Token::AnyRun))
+}
+
+fn get_by_string_key<'a, T: PathTarget>(
+    data: &'a T,
     key: &str,
-) -> Result<&'a Value, StructpathError> {
-    if let Value::Object(map) = data {
-        if let Some(value) = map.get(key) {
+    options: &GetOptions,
+) -> Result<&'a T, StructpathError> {
+    if data.is_object() {
+        if let Some(value) = data.get_key(key) {
             Ok(value)
+        } else if let Some(prefix) = &options.key_prefix {
+            data.get_key(&format!("{}{}", prefix, key))
+                .ok_or(StructpathError::NotFound)
         } else {
             Err(StructpathError::NotFound)
         }
+    } else if let Some(pairs) = data.as_array().filter(|_| options.assoc_arrays)
+    {
+        pairs
+            .iter()
+            .filter_map(|pair| match pair.as_array() {
+                Some([k, v]) => Some((k, v)),
+                _ => None,
+            })
+            .find(|(k, _)| k.as_key().as_deref() == Some(key))
+            .map(|(_, v)| v)
+            .ok_or(StructpathError::NotFound)
     } else {
         Err(StructpathError::InvalidPath {
             expected: "object".to_string(),
@@ -105,8 +864,28 @@ fn get_by_string_key<'a>(
     }
 }
 
-fn get_by_index(data: &Value, idx: usize) -> Result<&Value, StructpathError> {
-    if let Value::Array(arr) = data {
+/// Looks up `idx` for `GetOptions::clamp_indices`, counting a negative
+/// index from the end and clamping it to the array.
+fn get_by_clamped_index<T: PathTarget>(
+    data: &T,
+    idx: i64,
+) -> Result<&T, StructpathError> {
+    let len = data.as_array().map_or(0, |arr| arr.len());
+    let len = i64::try_from(len).unwrap_or(i64::MAX);
+    let idx = if idx < 0 {
+        idx.saturating_add(len)
+    } else {
+        idx
+    };
+    // An empty array or a non-array still fails on index 0
+    get_by_index(data, idx.clamp(0, (len - 1).max(0)) as usize)
+}
+
+fn get_by_index<T: PathTarget>(
+    data: &T,
+    idx: usize,
+) -> Result<&T, StructpathError> {
+    if let Some(arr) = data.as_array() {
         if let Some(value) = arr.get(idx) {
             Ok(value)
         } else {
@@ -158,6 +937,397 @@ mod tests {
         assert_eq!(*value, json!("integer key"));
     }
 
+    #[test]
+    fn test_get_with_assoc_arrays() {
+        let data = json!({
+            "config": [["timeout", 30], ["retries", 3], [5, "five"]]
+        });
+        let options = GetOptions {
+            assoc_arrays: true,
+            ..GetOptions::default()
+        };
+
+        let path = parse("$config.timeout").unwrap();
+        assert_eq!(*get_with(&path, &data, None, &options).unwrap(), json!(30));
+
+        let path = parse("$config.5").unwrap();
+        let value = get_with(&path, &data, None, &options).unwrap();
+        assert_eq!(*value, json!("five"));
+
+        let path = parse("$config.missing").unwrap();
+        assert!(matches!(
+            get_with(&path, &data, None, &options),
+            Err(StructpathError::NotFound)
+        ));
+
+        // Without the option, arrays are not searched by key
+        let path = parse("$config.timeout").unwrap();
+        assert!(matches!(
+            get(&path, &data, None),
+            Err(StructpathError::InvalidPath { .. })
+        ));
+    }
+
+    #[test]
+    fn test_get_with_key_prefix() {
+        let data = json!({
+            "ns:user": {"ns:name": "Ann", "name": "exact", "ns:tags": ["a"]},
+            "ns:2": "two"
+        });
+        let options = GetOptions {
+            key_prefix: Some("ns:".to_string()),
+            ..GetOptions::default()
+        };
+
+        let path = parse("$user.name").unwrap();
+        let namespaced = json!({"ns:user": {"ns:name": "Ann"}});
+        let value = get_with(&path, &namespaced, None, &options).unwrap();
+        assert_eq!(*value, json!("Ann"));
+        let path = parse("$user.tags[0]").unwrap();
+        let value = get_with(&path, &data, None, &options).unwrap();
+        assert_eq!(*value, json!("a"));
+        let path = parse("$2").unwrap();
+        assert_eq!(*get_with(&path, &data, None, &options).unwrap(), "two");
+
+        // An exact match wins over the prefixed key
+        let path = parse("$user.name").unwrap();
+        let value = get_with(&path, &data, None, &options).unwrap();
+        assert_eq!(*value, json!("exact"));
+
+        let path = parse("$user.missing").unwrap();
+        assert!(matches!(
+            get_with(&path, &data, None, &options),
+            Err(StructpathError::NotFound)
+        ));
+        let path = parse("$user.tags").unwrap();
+        assert!(matches!(
+            get(&path, &data, None),
+            Err(StructpathError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_resolved_path_of_non_canonical_int_keys() {
+        let data = json!({"007": 1, "-0": 2, "+5": 3, "7": 4});
+
+        for key in ["007", "-0", "+5", "7"] {
+            let path = parse("$#k").unwrap();
+            let vars: HashMap<String, String> =
+                [("k".to_string(), key.to_string())].into();
+            let resolved = resolved_path(&path, &data, Some(&vars)).unwrap();
+            assert_eq!(
+                get(&resolved, &data, None).unwrap(),
+                get(&path, &data, Some(&vars)).unwrap(),
+                "{}",
+                key
+            );
+        }
+
+        let path = parse("$#k").unwrap();
+        let vars: HashMap<String, String> =
+            [("k".to_string(), "007".to_string())].into();
+        assert_eq!(
+            resolved_path(&path, &data, Some(&vars)).unwrap().segments(),
+            [Segment::Key(SegmentKey::String("007".to_string()))]
+        );
+        let vars: HashMap<String, String> =
+            [("k".to_string(), "7".to_string())].into();
+        assert_eq!(
+            resolved_path(&path, &data, Some(&vars)).unwrap().segments(),
+            [Segment::Key(SegmentKey::Int(7))]
+        );
+    }
+
+    #[test]
+    fn test_get_with_clamped_indices() {
+        let data = json!({"items": [10, 20, 30], "empty": []});
+        let clamp = GetOptions {
+            clamp_indices: true,
+            ..GetOptions::default()
+        };
+        let vars = |i: &str| -> HashMap<String, String> {
+            [("i".to_string(), i.to_string())].into()
+        };
+        let path = parse("$items[#i]").unwrap();
+
+        let cases = [
+            ("1", 20),
+            ("3", 30),
+            ("99", 30),
+            ("-1", 30),
+            ("-3", 10),
+            ("-99", 10),
+        ];
+        for (i, expected) in cases {
+            let value = get_with(&path, &data, Some(&vars(i)), &clamp);
+            assert_eq!(*value.unwrap(), json!(expected), "{}", i);
+        }
+        let literal = parse("$items[5]").unwrap();
+        assert_eq!(*get_with(&literal, &data, None, &clamp).unwrap(), 30);
+
+        // Without clamping, the same indices fail
+        assert!(matches!(
+            get(&path, &data, Some(&vars("3"))),
+            Err(StructpathError::IndexOutOfBounds(_))
+        ));
+        assert!(matches!(
+            get(&path, &data, Some(&vars("-1"))),
+            Err(StructpathError::InvalidVariableValue(_))
+        ));
+        assert!(matches!(
+            get(&literal, &data, None),
+            Err(StructpathError::IndexOutOfBounds(_))
+        ));
+
+        // Clamping has nothing to select in an empty array
+        let path = parse("$empty[#i]").unwrap();
+        assert!(matches!(
+            get_with(&path, &data, Some(&vars("-1")), &clamp),
+            Err(StructpathError::IndexOutOfBounds(_))
+        ));
+        let path = parse("$items[#i]").unwrap();
+        assert!(matches!(
+            get_with(&path, &data, Some(&vars("x")), &clamp),
+            Err(StructpathError::InvalidVariableValue(_))
+        ));
+    }
+
+    #[test]
+    fn test_get_value_with_embedded_json() {
+        let data = json!({
+            "payload": "{\"x\": 1, \"items\": \"[10, 20]\"}",
+            "note": "plain text",
+            "number": "5"
+        });
+        let options = GetOptions {
+            parse_embedded_json: true,
+            ..GetOptions::default()
+        };
+
+        let path = parse("$payload.x").unwrap();
+        let value = get_value_with(&path, &data, None, &options).unwrap();
+        assert_eq!(*value, json!(1));
+
+        // Strings embedded in embedded JSON are parsed too
+        let path = parse("$payload.items[1]").unwrap();
+        let value = get_value_with(&path, &data, None, &options).unwrap();
+        assert_eq!(*value, json!(20));
+        let path = parse("$payload.length").unwrap();
+        let value = get_value_with(&path, &data, None, &options).unwrap();
+        assert_eq!(*value, json!(2));
+
+        // The string itself is returned as it is
+        let path = parse("$payload").unwrap();
+        let value = get_value_with(&path, &data, None, &options).unwrap();
+        assert!(matches!(value, Cow::Borrowed(Value::String(_))));
+
+        let path = parse("$payload.x").unwrap();
+        assert!(matches!(
+            get_value(&path, &data, None),
+            Err(StructpathError::InvalidPath { .. })
+        ));
+        assert!(matches!(
+            get_with(&path, &data, None, &options),
+            Err(StructpathError::InvalidPath { .. })
+        ));
+        for path_str in ["$note.x", "$number.x"] {
+            let path = parse(path_str).unwrap();
+            assert!(matches!(
+                get_value_with(&path, &data, None, &options),
+                Err(StructpathError::InvalidPath { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_get_in_bare_containers() {
+        let data = json!({
+            "users": {"alice": {"tags": ["admin", "dev"]}},
+            "1": "one"
+        });
+        let map = data.as_object().unwrap();
+        let arr = data["users"]["alice"]["tags"].as_array().unwrap();
+        let vars: HashMap<String, String> =
+            [("i".to_string(), "1".to_string())].into();
+
+        let path = parse("$users.alice.tags[0]").unwrap();
+        assert_eq!(get_in_object(&path, map, None).unwrap(), "admin");
+        let path = parse("$1").unwrap();
+        assert_eq!(get_in_object(&path, map, None).unwrap(), "one");
+
+        let path = parse("$[#i]").unwrap();
+        assert_eq!(get_in_array(&path, arr, Some(&vars)).unwrap(), "dev");
+
+        let path = parse("$[2]").unwrap();
+        assert!(matches!(
+            get_in_array(&path, arr, None),
+            Err(StructpathError::IndexOutOfBounds(_))
+        ));
+        let path = parse("$users").unwrap();
+        assert!(matches!(
+            get_in_array(&path, arr, None),
+            Err(StructpathError::InvalidPath { .. })
+        ));
+        let path = parse("$missing").unwrap();
+        assert!(matches!(
+            get_in_object(&path, map, None),
+            Err(StructpathError::NotFound)
+        ));
+        assert!(matches!(
+            get_in_object(&Structpath::new(), map, None),
+            Err(StructpathError::InvalidPath { .. })
+        ));
+    }
+
+    #[test]
+    fn test_get_value_length() {
+        let data = json!({
+            "items": [1, 2, 3],
+            "users": {"alice": {}, "bob": {}},
+            "meta": {"length": "literal"},
+            "name": "x"
+        });
+        let length = |path_str: &str| {
+            get_value(&parse(path_str).unwrap(), &data, None)
+                .map(|value| value.into_owned())
+        };
+
+        assert_eq!(length("$items.length").unwrap(), json!(3));
+        assert_eq!(length("$items[#]").unwrap(), json!(3));
+        assert_eq!(length("$users.length").unwrap(), json!(2));
+        assert_eq!(length("$length").unwrap(), json!(4));
+        assert_eq!(length(r"$meta.\length").unwrap(), json!("literal"));
+        assert!(matches!(
+            length("$name.length"),
+            Err(StructpathError::InvalidPath { .. })
+        ));
+
+        // Borrowed values come through untouched
+        let value = get_value(&parse("$items").unwrap(), &data, None).unwrap();
+        assert!(matches!(value, Cow::Borrowed(_)));
+
+        // A length can only be computed at the end, and not by get
+        assert!(matches!(
+            length("$items.length.x"),
+            Err(StructpathError::UnexpectedLength)
+        ));
+        assert!(matches!(
+            get(&parse("$items.length").unwrap(), &data, None),
+            Err(StructpathError::UnexpectedLength)
+        ));
+    }
+
+    #[test]
+    fn test_get_empty_key_roundtrip() {
+        let data = json!({"": {"x": 1}, "a": {"": 2}});
+
+        for (path, value) in crate::walk::new_walker(&data) {
+            let reparsed = parse(&path.to_string()).unwrap();
+            assert_eq!(reparsed, path);
+            assert_eq!(get(&reparsed, &data, None).unwrap(), value);
+        }
+
+        let path = parse(r#"$a."""#).unwrap();
+        assert_eq!(*get(&path, &data, None).unwrap(), json!(2));
+    }
+
+    #[test]
+    fn test_trace_get() {
+        let data = json!({"a": {"x": {"c": 1}}});
+        let path = parse("$a.b.c").unwrap();
+
+        let (result, steps) = trace_get(&path, &data, None);
+
+        assert!(matches!(result, Err(StructpathError::NotFound)));
+        assert_eq!(
+            steps,
+            vec![
+                TraceStep {
+                    segment: path.segments()[0].clone(),
+                    resolved: true,
+                    found: "object",
+                },
+                TraceStep {
+                    segment: path.segments()[1].clone(),
+                    resolved: false,
+                    found: "object",
+                },
+            ]
+        );
+
+        let path = parse("$a.x.c[0]").unwrap();
+        let (result, steps) = trace_get(&path, &data, None);
+        assert!(matches!(result, Err(StructpathError::InvalidPath { .. })));
+        assert_eq!(steps.len(), 4);
+        assert_eq!((steps[3].resolved, steps[3].found), (false, "number"));
+
+        let path = parse("$a.x.c").unwrap();
+        let (result, steps) = trace_get(&path, &data, None);
+        assert_eq!(result.unwrap(), &json!(1));
+        assert!(steps.iter().all(|step| step.resolved));
+
+        let data = json!({"flag": true});
+        let path = parse("$flag.x").unwrap();
+        let (_, steps) = trace_get(&path, &data, None);
+        assert_eq!((steps[1].resolved, steps[1].found), (false, "boolean"));
+    }
+
+    #[test]
+    fn test_get_find_value() {
+        let data = json!({
+            "ids": [3, 5, 5],
+            "tags": ["a", "b", {"c": 1}],
+            "items": [{"id": 1}, {"id": 2}]
+        });
+
+        let path = parse("$ids[=5]").unwrap();
+        assert_eq!(path.get(&data, None).unwrap(), &json!(5));
+        assert_eq!(path.index_of(&data, None).unwrap(), 1);
+
+        let path = parse(r#"$tags[="b"]"#).unwrap();
+        assert_eq!(path.get(&data, None).unwrap(), &json!("b"));
+        assert_eq!(path.index_of(&data, None).unwrap(), 1);
+
+        let path = parse("$ids[=4]").unwrap();
+        assert!(matches!(
+            path.get(&data, None),
+            Err(StructpathError::NotFound)
+        ));
+        let path = parse(r#"$tags[="5"]"#).unwrap();
+        assert!(matches!(
+            path.get(&data, None),
+            Err(StructpathError::NotFound)
+        ));
+        let path = parse("$items[=1]").unwrap();
+        assert!(matches!(
+            path.get(&data, None),
+            Err(StructpathError::NotFound)
+        ));
+
+        let path = parse("$items[=5]").unwrap();
+        assert!(matches!(
+            path.get(&data["ids"], None),
+            Err(StructpathError::InvalidPath { .. })
+        ));
+        assert!(path.index_of(&data, None).is_err());
+    }
+
+    #[test]
+    fn test_child_count() {
+        let data = json!({"a": {"x": 1, "y": [1, 2, 3]}, "e": {}});
+
+        let count = |s| child_count(&parse(s).unwrap(), &data, None);
+        assert_eq!(count("$a").unwrap(), 2);
+        assert_eq!(count("$a.y").unwrap(), 3);
+        assert_eq!(count("$e").unwrap(), 0);
+        assert_eq!(count("$").unwrap(), 2);
+        assert!(matches!(
+            count("$a.x"),
+            Err(StructpathError::InvalidPath { .. })
+        ));
+        assert!(matches!(count("$b"), Err(StructpathError::NotFound)));
+    }
+
     #[test]
     fn test_get_not_found() {
         let data = json!({"a": {"b": 1}});
@@ -174,6 +1344,183 @@ mod tests {
         assert!(matches!(result, Err(StructpathError::InvalidPath { .. })));
     }
 
+    #[test]
+    fn test_get_with_wildcard() {
+        let data = json!({"users": {"alice": 1}});
+        let path = parse("$users.*").unwrap();
+        let result = get(&path, &data, None);
+        assert!(matches!(result, Err(StructpathError::UnexpectedWildcard)));
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("log_?", "log_a"));
+        assert!(pattern_matches("log_?", "log_1"));
+        assert!(!pattern_matches("log_?", "log_"));
+        assert!(!pattern_matches("log_?", "log_ab"));
+
+        assert!(pattern_matches("a*b", "ab"));
+        assert!(pattern_matches("a*b", "axxbxb"));
+        assert!(!pattern_matches("a*b", "axxbx"));
+        assert!(pattern_matches("*", ""));
+        assert!(pattern_matches("*?*", "é"));
+        assert!(!pattern_matches("*?*", ""));
+
+        assert!(pattern_matches(r"a\?", "a?"));
+        assert!(!pattern_matches(r"a\?", "ab"));
+        assert!(pattern_matches(r"a\**", "a*xyz"));
+        assert!(!pattern_matches(r"a\**", "axyz"));
+    }
+
+    #[test]
+    fn test_get_all_entries_key_pattern() {
+        let data = json!({
+            "log_a": 1,
+            "log_1": 2,
+            "log_10": 3,
+            "log_?": 4,
+            "other": 5
+        });
+
+        let keys = |path_str: &str| -> Vec<String> {
+            let path = parse(path_str).unwrap();
+            let mut keys: Vec<String> = get_all_entries(&path, &data, None)
+                .into_iter()
+                .map(|(key, _)| key)
+                .collect();
+            keys.sort();
+            keys
+        };
+
+        assert_eq!(keys("$log_?"), vec!["log_1", "log_?", "log_a"]);
+        assert_eq!(keys("$log_*"), vec!["log_1", "log_10", "log_?", "log_a"]);
+        assert_eq!(keys(r"$log_\?"), vec!["log_?"]);
+        assert_eq!(keys("$*_1*"), vec!["log_1", "log_10"]);
+
+        // A single lookup cannot resolve a pattern
+        let path = parse("$log_?").unwrap();
+        assert!(matches!(
+            get(&path, &data, None),
+            Err(StructpathError::UnexpectedWildcard)
+        ));
+    }
+
+    #[test]
+    fn test_get_all_entries_key_wildcard() {
+        let data = json!({
+            "users": {
+                "alice": {"age": 30},
+                "bob": {"age": 25}
+            }
+        });
+
+        let path = parse("$users.*").unwrap();
+        let mut entries = get_all_entries(&path, &data, None);
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(
+            entries,
+            vec![
+                ("alice".to_string(), &json!({"age": 30})),
+                ("bob".to_string(), &json!({"age": 25})),
+            ]
+        );
+
+        let path = parse("$users.*.age").unwrap();
+        let mut entries = get_all_entries(&path, &data, None);
+        entries.sort_by_key(|(_, value)| value.as_i64());
+
+        assert_eq!(
+            entries,
+            vec![
+                ("age".to_string(), &json!(25)),
+                ("age".to_string(), &json!(30)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_all_entries_index_wildcard() {
+        let data = json!({"items": ["a", "b", "c"]});
+
+        let path = parse("$items[*]").unwrap();
+        let entries = get_all_entries(&path, &data, None);
+
+        assert_eq!(
+            entries,
+            vec![
+                ("0".to_string(), &json!("a")),
+                ("1".to_string(), &json!("b")),
+                ("2".to_string(), &json!("c")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_all_entries_type_mismatch() {
+        let data = json!({"items": ["a", "b"], "users": {"alice": 1}});
+
+        let path = parse("$items.*").unwrap();
+        assert!(get_all_entries(&path, &data, None).is_empty());
+
+        let path = parse("$users[*]").unwrap();
+        assert!(get_all_entries(&path, &data, None).is_empty());
+    }
+
+    #[test]
+    fn test_get_nth_key_variable() {
+        let data = json!({
+            "users": {
+                "alice": {"age": 30},
+                "bob": {"age": 25},
+                "carol": {"age": 41}
+            }
+        });
+
+        let path = parse("$users.#id{0}.age").unwrap();
+        assert_eq!(get(&path, &data, None).unwrap(), &json!(30));
+
+        let path = parse("$users.#id{1}.age").unwrap();
+        assert_eq!(get(&path, &data, None).unwrap(), &json!(25));
+
+        let path = parse("$users.#id{3}.age").unwrap();
+        let result = get(&path, &data, None);
+        assert!(matches!(result, Err(StructpathError::IndexOutOfBounds(_))));
+    }
+
+    #[test]
+    fn test_nth_key_variable_counts_resolving_keys() {
+        // Keys come in sorted order, and "amy" lacks the rest of the path
+        let data = json!({
+            "users": {
+                "zed": {"age": 30},
+                "amy": {"name": "Amy"},
+                "bob": {"age": 25}
+            }
+        });
+
+        let path = parse("$users.#id{0}.age").unwrap();
+        assert_eq!(get(&path, &data, None).unwrap(), &json!(25));
+        assert_eq!(
+            resolved_path(&path, &data, None).unwrap().to_string(),
+            "$users.bob.age"
+        );
+
+        let path = parse("$users.#id{1}.age").unwrap();
+        assert_eq!(get(&path, &data, None).unwrap(), &json!(30));
+
+        let path = parse("$users.#id{2}.age").unwrap();
+        let result = get(&path, &data, None);
+        assert!(matches!(result, Err(StructpathError::IndexOutOfBounds(_))));
+
+        let path = parse("$users.#id{0}.*").unwrap();
+        let values: Vec<_> = get_all_entries(&path, &data, None)
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect();
+        assert_eq!(values, vec![&json!("Amy")]);
+    }
+
     #[test]
     fn test_get_index_out_of_bounds() {
         let data = json!({"a": [1, 2]});
@@ -181,4 +1528,49 @@ mod tests {
         let result = get(&path, &data, None);
         assert!(matches!(result, Err(StructpathError::IndexOutOfBounds(_))));
     }
+
+    #[test]
+    fn test_is_valid_for() {
+        let data = json!({
+            "users": [{"name": "Ann", "tags": ["a"]}, {"name": "Bo"}],
+            "meta": {"count": 2},
+        });
+        let vars: HashMap<String, String> =
+            [("i".to_string(), "1".to_string())].into();
+
+        // Absent but of the right shape
+        let fitting = [
+            "$users[0].name",
+            "$users[5].name.first",
+            "$users[0].email.domain",
+            "$missing[0].x",
+            "$users[*].tags[0]",
+            "$users[#i].tags",
+            "$meta.#k",
+            "$meta.*",
+            "$users.length",
+            "$users[=null].name",
+        ];
+        for path_str in fitting {
+            let path = parse(path_str).unwrap();
+            assert!(is_valid_for(&path, &data, Some(&vars)), "{}", path_str);
+        }
+        let path = parse("$users[#j].name").unwrap();
+        assert!(is_valid_for(&path, &data, None));
+
+        let mismatched = [
+            "$users.name",
+            "$meta[0]",
+            "$users[0].name.first",
+            "$users[*].name[0]",
+            "$meta.count.x",
+            "$meta.count.length",
+            "$users[0][#j]",
+            "$meta[=2]",
+        ];
+        for path_str in mismatched {
+            let path = parse(path_str).unwrap();
+            assert!(!is_valid_for(&path, &data, Some(&vars)), "{}", path_str);
+        }
+    }
 }
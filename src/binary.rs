@@ -0,0 +1,276 @@
+use crate::types::{Segment, SegmentKey, Structpath, StructpathError};
+use serde_json::Value;
+
+/// The first byte of every encoding, bumped if the layout ever changes.
+const FORMAT_VERSION: u8 = 1;
+
+// Segment tags. These are part of the stored format and must not be
+// renumbered; new kinds get new tags.
+const TAG_STRING_KEY: u8 = 0;
+const TAG_INT_KEY: u8 = 1;
+const TAG_INDEX: u8 = 2;
+const TAG_FIND_VALUE: u8 = 3;
+const TAG_KEY_VARIABLE: u8 = 4;
+const TAG_INDEX_VARIABLE: u8 = 5;
+const TAG_NTH_KEY_VARIABLE: u8 = 6;
+const TAG_WILDCARD: u8 = 7;
+const TAG_INDEX_WILDCARD: u8 = 8;
+const TAG_KEY_PATTERN: u8 = 9;
+const TAG_PARENT: u8 = 10;
+const TAG_LENGTH: u8 = 11;
+
+/// Encodes `path` as the version byte followed by one tag byte per segment
+/// and its payload. Strings are written as their UTF-8 length and bytes,
+/// numbers as LEB128 varints (int keys zigzag-encoded first) and searched
+/// values as their JSON text.
+pub fn to_bytes(path: &Structpath) -> Vec<u8> {
+    let mut out = vec![FORMAT_VERSION];
+    for segment in path.segments() {
+        match segment {
+            Segment::Key(SegmentKey::String(key)) => {
+                out.push(TAG_STRING_KEY);
+                write_str(&mut out, key);
+            }
+            Segment::Key(SegmentKey::Int(key)) => {
+                out.push(TAG_INT_KEY);
+                write_varint(&mut out, zigzag(*key));
+            }
+            Segment::Index(idx) => {
+                out.push(TAG_INDEX);
+                write_varint(&mut out, *idx as u64);
+            }
+            Segment::FindValue(value) => {
+                out.push(TAG_FIND_VALUE);
+                write_str(&mut out, &value.to_string());
+            }
+            Segment::KeyVariable(name) => {
+                out.push(TAG_KEY_VARIABLE);
+                write_str(&mut out, name);
+            }
+            Segment::IndexVariable(name) => {
+                out.push(TAG_INDEX_VARIABLE);
+                write_str(&mut out, name);
+            }
+            Segment::NthKeyVariable(name, n) => {
+                out.push(TAG_NTH_KEY_VARIABLE);
+                write_str(&mut out, name);
+                write_varint(&mut out, *n as u64);
+            }
+            Segment::Wildcard => out.push(TAG_WILDCARD),
+            Segment::IndexWildcard => out.push(TAG_INDEX_WILDCARD),
+            Segment::KeyPattern(pattern) => {
+                out.push(TAG_KEY_PATTERN);
+                write_str(&mut out, pattern);
+            }
+            Segment::Parent => out.push(TAG_PARENT),
+            Segment::Length => out.push(TAG_LENGTH),
+        }
+    }
+    out
+}
+
+/// Decodes bytes written by `to_bytes`. Truncated or malformed input and
+/// an unknown version or tag are parse errors. A variable name used more
+/// than once is kept, as `to_bytes` encodes such paths too.
+pub fn from_bytes(bytes: &[u8]) -> Result<Structpath, StructpathError> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let version = reader.byte()?;
+    if version != FORMAT_VERSION {
+        return Err(invalid(format!("unsupported version {}", version)));
+    }
+
+    let mut path = Structpath::new();
+    while !reader.is_at_end() {
+        let segment = match reader.byte()? {
+            TAG_STRING_KEY => {
+                Segment::Key(SegmentKey::String(reader.string()?))
+            }
+            TAG_INT_KEY => {
+                Segment::Key(SegmentKey::Int(unzigzag(reader.varint()?)))
+            }
+            TAG_INDEX => Segment::Index(reader.usize()?),
+            TAG_FIND_VALUE => {
+                let text = reader.string()?;
+                match serde_json::from_str::<Value>(&text) {
+                    Ok(value) if !value.is_array() && !value.is_object() => {
+                        Segment::FindValue(value)
+                    }
+                    _ => {
+                        return Err(invalid(format!(
+                            "invalid value to search for: {}",
+                            text
+                        )))
+                    }
+                }
+            }
+            TAG_KEY_VARIABLE => Segment::KeyVariable(reader.string()?),
+            TAG_INDEX_VARIABLE => Segment::IndexVariable(reader.string()?),
+            TAG_NTH_KEY_VARIABLE => {
+                let name = reader.string()?;
+                Segment::NthKeyVariable(name, reader.usize()?)
+            }
+            TAG_WILDCARD => Segment::Wildcard,
+            TAG_INDEX_WILDCARD => Segment::IndexWildcard,
+            TAG_KEY_PATTERN => Segment::KeyPattern(reader.string()?),
+            TAG_PARENT => Segment::Parent,
+            TAG_LENGTH => Segment::Length,
+            tag => return Err(invalid(format!("unknown segment tag {}", tag))),
+        };
+        path.push_segment_allow_dup(segment);
+    }
+    Ok(path)
+}
+
+fn invalid(message: String) -> StructpathError {
+    StructpathError::ParseError(format!("Invalid binary path: {}", message))
+}
+
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn unzigzag(n: u64) -> i64 {
+    ((n >> 1) as i64) ^ -((n & 1) as i64)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    write_varint(out, s.len() as u64);
+    out.extend_from_slice(s.as_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn is_at_end(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    fn byte(&mut self) -> Result<u8, StructpathError> {
+        let byte = *self
+            .bytes
+            .get(self.pos)
+            .ok_or_else(|| invalid("unexpected end of input".to_string()))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u64, StructpathError> {
+        let mut result = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            let bits = u64::from(byte & 0x7f);
+            if shift == 63 && bits > 1 {
+                break;
+            }
+            result |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        Err(invalid("varint does not fit 64 bits".to_string()))
+    }
+
+    fn usize(&mut self) -> Result<usize, StructpathError> {
+        let n = self.varint()?;
+        usize::try_from(n)
+            .map_err(|_| invalid(format!("number {} is too large", n)))
+    }
+
+    fn string(&mut self) -> Result<String, StructpathError> {
+        let len = self.usize()?;
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| invalid("unexpected end of input".to_string()))?;
+        let s = std::str::from_utf8(&self.bytes[self.pos..end])
+            .map_err(|_| invalid("string is not valid UTF-8".to_string()))?;
+        self.pos = end;
+        Ok(s.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse;
+
+    #[test]
+    fn test_roundtrip_all_segment_kinds() {
+        let path_strs = [
+            "$",
+            r#"$users[0].name.-12.#id[#i].#n{2}[=5][="a]"][=null]"#,
+            "$a.*[*].b?*.\\..\\length.length",
+            "$a[#]",
+            "$\u{e9}t\u{e9}.\"\"",
+        ];
+        for path_str in path_strs {
+            let path = parse(path_str).unwrap();
+            let decoded = from_bytes(&to_bytes(&path)).unwrap();
+            assert_eq!(decoded, path, "{}", path_str);
+            assert_eq!(decoded.to_string(), path.to_string());
+        }
+
+        let mut path = Structpath::new();
+        path.push_parent();
+        path.push_int_key(i64::MIN);
+        path.push_index(usize::MAX);
+        path.push_find_value(serde_json::json!(-1.5));
+        assert_eq!(from_bytes(&to_bytes(&path)).unwrap(), path);
+
+        // Repeated variables, which only the allow_dup methods can build
+        let mut path = parse("$teams.#id").unwrap();
+        path.push_key_variable_allow_dup("id");
+        path.push_index_variable_allow_dup("id");
+        let decoded = from_bytes(&to_bytes(&path)).unwrap();
+        assert_eq!(decoded, path);
+        assert!(decoded.contains_variable("id"));
+    }
+
+    #[test]
+    fn test_encoding_is_stable() {
+        let path = parse("$users[300].#id.-1[*]").unwrap();
+        assert_eq!(
+            to_bytes(&path),
+            [
+                1, // version
+                0, 5, b'u', b's', b'e', b'r', b's', // key "users"
+                2, 0xac, 0x02, // index 300
+                4, 2, b'i', b'd', // key variable "id"
+                1, 1, // int key -1
+                8, // index wildcard
+            ]
+        );
+        assert_eq!(to_bytes(&Structpath::new()), [1]);
+    }
+
+    #[test]
+    fn test_from_invalid_bytes() {
+        let invalid_inputs: [&[u8]; 7] = [
+            &[],
+            &[2],
+            &[1, 12],
+            &[1, 0, 5, b'a'],
+            &[
+                1, 2, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                0x7f,
+            ],
+            &[1, 0, 1, 0xff],
+            &[1, 3, 2, b'[', b']'],
+        ];
+        for bytes in invalid_inputs {
+            assert!(from_bytes(bytes).is_err(), "{:?}", bytes);
+        }
+    }
+}
@@ -1,73 +1,289 @@
-use crate::types::{Segment, SegmentKey, Structpath};
+use crate::parse::{EMPTY_KEY, LENGTH};
+use crate::types::{Segment, SegmentKey, Structpath, StructpathError};
+use std::fmt::Write;
 
-pub fn to_string(path: &Structpath) -> String {
-    if path.segments().is_empty() {
-        return "$".to_string();
+/// Options controlling how paths are rendered as strings.
+///
+/// These only affect output: `parse` always expects `$` and `.` between
+/// keys, so a path rendered with another root or separator may not parse
+/// back to the same path.
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// The text written at the start of the path, `$` by default. It may
+    /// be empty.
+    pub root: String,
+    /// The character written between key segments, `.` by default. Index
+    /// brackets are written as usual, and keys containing the separator
+    /// escape it with a backslash.
+    pub key_separator: char,
+    /// Write every character of a key that is not printable ASCII as a
+    /// `\u{...}` escape, so the path is safe to store in ASCII-only
+    /// contexts. Such paths still parse back to the same path.
+    pub ascii_only: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            root: "$".to_string(),
+            key_separator: '.',
+            ascii_only: false,
+        }
     }
+}
+
+pub fn to_string(path: &Structpath) -> String {
+    to_string_with(path, &FormatOptions::default())
+}
 
-    let mut result = String::from("$");
+pub fn to_string_with(path: &Structpath, options: &FormatOptions) -> String {
+    let mut result = options.root.clone();
     let mut first = true;
 
+    let segments = path.segments();
+    for (i, segment) in segments.iter().enumerate() {
+        format_segment(
+            &mut result,
+            segment,
+            segments.get(i + 1),
+            &mut first,
+            options,
+        );
+    }
+
+    result
+}
+
+/// Renders the root and then each segment on its own line, indented one
+/// step deeper than the one before, for logging long paths.
+pub fn to_tree_string(path: &Structpath) -> String {
+    let mut result = "$".to_string();
+    for (depth, segment) in path.segments().iter().enumerate() {
+        let _ = write!(
+            result,
+            "\n{:indent$}{}",
+            "",
+            segment,
+            indent = 2 * (depth + 1)
+        );
+    }
+    result
+}
+
+pub fn to_dot_notation(path: &Structpath) -> Result<String, StructpathError> {
+    let mut parts = Vec::with_capacity(path.segments().len());
+    for segment in path.segments() {
+        let part = match segment {
+            Segment::Key(SegmentKey::String(key)) => key.replace('.', "_"),
+            Segment::Key(SegmentKey::Int(key)) => key.to_string(),
+            Segment::Index(idx) => idx.to_string(),
+            Segment::FindValue(value) => {
+                return Err(StructpathError::InvalidPath {
+                    expected: "a concrete index".to_string(),
+                    found: format!("a search for {}", value),
+                })
+            }
+            Segment::KeyVariable(name)
+            | Segment::IndexVariable(name)
+            | Segment::NthKeyVariable(name, _) => {
+                return Err(StructpathError::UnexpectedVariable(name.clone()))
+            }
+            Segment::Wildcard
+            | Segment::IndexWildcard
+            | Segment::KeyPattern(_) => {
+                return Err(StructpathError::UnexpectedWildcard)
+            }
+            Segment::Parent => return Err(StructpathError::UnnormalizedPath),
+            Segment::Length => return Err(StructpathError::UnexpectedLength),
+        };
+        parts.push(part);
+    }
+
+    Ok(parts.join("."))
+}
+
+pub fn to_jq(path: &Structpath) -> Result<String, StructpathError> {
+    let mut result = String::new();
     for segment in path.segments() {
         match segment {
-            Segment::Key(key) => {
-                format_key_segment(&mut result, key, &mut first);
+            Segment::Key(SegmentKey::String(key)) if is_jq_identifier(key) => {
+                result.push('.');
+                result.push_str(key);
+            }
+            Segment::Key(SegmentKey::String(key)) => {
+                // jq string literals share JSON's escapes
+                result.push('.');
+                result.push_str(
+                    &serde_json::Value::from(key.as_str()).to_string(),
+                );
+            }
+            Segment::Key(SegmentKey::Int(key)) => {
+                let _ = write!(result, ".\"{}\"", key);
             }
             Segment::Index(idx) => {
-                result.push_str(&format!("[{}]", idx));
+                if result.is_empty() {
+                    result.push('.');
+                }
+                let _ = write!(result, "[{}]", idx);
+            }
+            Segment::Wildcard | Segment::IndexWildcard => {
+                if result.is_empty() {
+                    result.push('.');
+                }
+                result.push_str("[]");
+            }
+            Segment::KeyVariable(name)
+            | Segment::IndexVariable(name)
+            | Segment::NthKeyVariable(name, _) => {
+                return Err(StructpathError::UnexpectedVariable(name.clone()))
             }
-            Segment::KeyVariable(var_name) => {
-                format_key_variable(&mut result, var_name, &mut first);
+            Segment::FindValue(value) => {
+                return Err(StructpathError::InvalidPath {
+                    expected: "a concrete index".to_string(),
+                    found: format!("a search for {}", value),
+                })
             }
-            Segment::IndexVariable(var_name) => {
-                format_index_variable(&mut result, var_name);
+            Segment::KeyPattern(_) => {
+                return Err(StructpathError::UnexpectedWildcard)
             }
+            Segment::Parent => return Err(StructpathError::UnnormalizedPath),
+            Segment::Length => return Err(StructpathError::UnexpectedLength),
         }
     }
 
-    result
+    if result.is_empty() {
+        result.push('.');
+    }
+    Ok(result)
+}
+
+/// Returns true if `key` can follow a `.` in jq without quotes.
+fn is_jq_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Writes a single segment in path syntax. `first` is true while nothing
+/// has been written that the segment needs a separator from, and `next` is
+/// the following segment, which decides how a run of parents ends.
+pub(crate) fn format_segment(
+    result: &mut String,
+    segment: &Segment,
+    next: Option<&Segment>,
+    first: &mut bool,
+    options: &FormatOptions,
+) {
+    match segment {
+        Segment::Key(key) => {
+            format_key_segment(result, key, first, options);
+        }
+        Segment::Index(idx) => {
+            let _ = write!(result, "[{}]", idx);
+        }
+        Segment::FindValue(value) => {
+            format_find_value(result, value, options);
+        }
+        Segment::KeyVariable(var_name) => {
+            format_key_variable(result, var_name, first, options);
+        }
+        Segment::IndexVariable(var_name) => {
+            format_index_variable(result, var_name);
+        }
+        Segment::NthKeyVariable(var_name, n) => {
+            format_key_variable(result, var_name, first, options);
+            let _ = write!(result, "{{{}}}", n);
+        }
+        Segment::Wildcard => {
+            format_wildcard(result, first, options);
+        }
+        Segment::IndexWildcard => {
+            result.push_str("[*]");
+        }
+        Segment::KeyPattern(pattern) => {
+            format_key_pattern(result, pattern, first, options);
+        }
+        Segment::Parent => {
+            format_parent(result, next, first, options);
+        }
+        Segment::Length => {
+            format_length(result, first, options);
+        }
+    }
 }
 
-fn format_key_segment(result: &mut String, key: &SegmentKey, first: &mut bool) {
+pub(crate) fn format_key_segment(
+    result: &mut String,
+    key: &SegmentKey,
+    first: &mut bool,
+    options: &FormatOptions,
+) {
     match key {
         SegmentKey::String(string_key) => {
-            format_string_key(result, string_key, first);
+            format_string_key(result, string_key, first, options);
         }
         SegmentKey::Int(int_key) => {
-            format_int_key(result, *int_key, first);
+            format_int_key(result, *int_key, first, options);
         }
     }
 }
 
-fn format_string_key(result: &mut String, string_key: &str, first: &mut bool) {
+fn format_string_key(
+    result: &mut String,
+    string_key: &str,
+    first: &mut bool,
+    options: &FormatOptions,
+) {
     if *first {
         *first = false;
     } else {
-        result.push('.');
+        result.push(options.key_separator);
+    }
+
+    if string_key.is_empty() {
+        result.push_str(EMPTY_KEY);
+        return;
     }
 
-    if string_key.parse::<i64>().is_ok() {
+    // Keys that would parse as an int key, a length segment or the empty
+    // key get a leading escape to stay string keys.
+    if string_key.parse::<i64>().is_ok()
+        || string_key == LENGTH
+        || string_key == EMPTY_KEY
+    {
         result.push('\\');
     }
 
-    result.push_str(&escape_special_chars(string_key));
+    escape_special_chars(result, string_key, options);
 }
 
-fn format_int_key(result: &mut String, int_key: i64, first: &mut bool) {
+fn format_int_key(
+    result: &mut String,
+    int_key: i64,
+    first: &mut bool,
+    options: &FormatOptions,
+) {
     if *first {
         *first = false;
     } else {
-        result.push('.');
+        result.push(options.key_separator);
     }
 
-    result.push_str(&int_key.to_string());
+    let _ = write!(result, "{}", int_key);
 }
 
-fn format_key_variable(result: &mut String, var_name: &str, first: &mut bool) {
+fn format_key_variable(
+    result: &mut String,
+    var_name: &str,
+    first: &mut bool,
+    options: &FormatOptions,
+) {
     if *first {
         *first = false;
     } else {
-        result.push('.');
+        result.push(options.key_separator);
     }
 
     // Format key variable with leading #
@@ -77,16 +293,136 @@ fn format_key_variable(result: &mut String, var_name: &str, first: &mut bool) {
 
 fn format_index_variable(result: &mut String, var_name: &str) {
     // Format index variable with [#name]
-    result.push_str(&format!("[#{}]", var_name));
+    let _ = write!(result, "[#{}]", var_name);
+}
+
+fn format_wildcard(
+    result: &mut String,
+    first: &mut bool,
+    options: &FormatOptions,
+) {
+    if *first {
+        *first = false;
+    } else {
+        result.push(options.key_separator);
+    }
+
+    result.push('*');
+}
+
+fn format_key_pattern(
+    result: &mut String,
+    pattern: &str,
+    first: &mut bool,
+    options: &FormatOptions,
+) {
+    if *first {
+        *first = false;
+    } else {
+        result.push(options.key_separator);
+    }
+
+    // The pattern already escapes its literal `?`, `*` and `\`; only the
+    // path syntax characters and the key separator need escaping here.
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                result.push('\\');
+                let escaped = chars.next().unwrap_or('\\');
+                push_char(result, escaped, options.ascii_only);
+            }
+            '.' | '[' | ']' | '#' => {
+                result.push('\\');
+                result.push(c);
+            }
+            _ if c == options.key_separator => {
+                result.push('\\');
+                result.push(c);
+            }
+            _ => push_char(result, c, options.ascii_only),
+        }
+    }
+}
+
+fn format_length(
+    result: &mut String,
+    first: &mut bool,
+    options: &FormatOptions,
+) {
+    if *first {
+        *first = false;
+    } else {
+        result.push(options.key_separator);
+    }
+
+    result.push_str(LENGTH);
+}
+
+fn format_parent(
+    result: &mut String,
+    next: Option<&Segment>,
+    first: &mut bool,
+    options: &FormatOptions,
+) {
+    // Each parent adds a separator to the run separating its neighbours. A
+    // run followed by a key gets its separator from that key; otherwise the
+    // separator has to be written here.
+    *first = false;
+    result.push(options.key_separator);
+
+    match next {
+        Some(
+            Segment::Key(_)
+            | Segment::KeyVariable(_)
+            | Segment::NthKeyVariable(_, _)
+            | Segment::Wildcard
+            | Segment::KeyPattern(_)
+            | Segment::Parent
+            | Segment::Length,
+        ) => {}
+        _ => result.push('.'),
+    }
+}
+
+fn escape_special_chars(result: &mut String, s: &str, options: &FormatOptions) {
+    for c in s.chars() {
+        if matches!(c, '.' | '[' | ']' | '\\' | '#' | '*' | '?')
+            || c == options.key_separator
+        {
+            // Also escape #, * and ? characters
+            result.push('\\');
+        }
+        push_char(result, c, options.ascii_only);
+    }
+}
+
+/// Writes a `[=value]` segment. The literal is JSON, so non-ASCII characters
+/// in a string are escaped the JSON way rather than with `\u{...}`.
+fn format_find_value(
+    result: &mut String,
+    value: &serde_json::Value,
+    options: &FormatOptions,
+) {
+    result.push_str("[=");
+    for c in value.to_string().chars() {
+        if options.ascii_only && !c.is_ascii() {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                let _ = write!(result, "\\u{:04x}", unit);
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result.push(']');
 }
 
-fn escape_special_chars(s: &str) -> String {
-    s.chars()
-        .map(|c| match c {
-            '.' | '[' | ']' | '\\' | '#' => format!("\\{}", c), // Also escape # character
-            _ => c.to_string(),
-        })
-        .collect::<String>()
+fn push_char(result: &mut String, c: char, ascii_only: bool) {
+    if ascii_only && (!c.is_ascii() || c.is_ascii_control()) {
+        let _ = write!(result, "\\u{{{:X}}}", c as u32);
+    } else {
+        result.push(c);
+    }
 }
 
 #[cfg(test)]
@@ -109,12 +445,23 @@ mod tests {
         assert_eq!(path_str, r"$a[0].b\.c.d\[e\].\123.456");
     }
 
+    #[test]
+    fn test_to_tree_string() {
+        let path = parse::parse("$users[0].#field[*]").unwrap();
+
+        let tree = to_tree_string(&path);
+
+        assert_eq!(tree.lines().count(), path.segments().len() + 1);
+        assert_eq!(tree, "$\n  .users\n    [0]\n      .#field\n        [*]");
+        assert_eq!(to_tree_string(&Structpath::new()), "$");
+    }
+
     #[test]
     fn test_with_key_variable() {
         let mut path = Structpath::new();
-        let _ = path.push_string_key("a");
+        path.push_string_key("a");
         let _ = path.push_key_variable("var");
-        let _ = path.push_string_key("c");
+        path.push_string_key("c");
 
         let path_str = to_string(&path);
         assert_eq!(path_str, "$a.#var.c");
@@ -123,9 +470,9 @@ mod tests {
     #[test]
     fn test_with_index_variable() {
         let mut path = Structpath::new();
-        let _ = path.push_string_key("a");
+        path.push_string_key("a");
         let _ = path.push_index_variable("idx");
-        let _ = path.push_string_key("b");
+        path.push_string_key("b");
 
         let path_str = to_string(&path);
         assert_eq!(path_str, "$a[#idx].b");
@@ -134,9 +481,9 @@ mod tests {
     #[test]
     fn test_with_mixed_variables() {
         let mut path = Structpath::new();
-        let _ = path.push_string_key("teams");
+        path.push_string_key("teams");
         let _ = path.push_index_variable("idx");
-        let _ = path.push_string_key("members");
+        path.push_string_key("members");
         let _ = path.push_key_variable("name");
 
         let path_str = to_string(&path);
@@ -146,14 +493,225 @@ mod tests {
     #[test]
     fn test_with_hash_in_key() {
         let mut path = Structpath::new();
-        let _ = path.push_string_key("a");
-        let _ = path.push_string_key("#notvar"); // Regular key with hash character
-        let _ = path.push_string_key("c");
+        path.push_string_key("a");
+        path.push_string_key("#notvar"); // Regular key with hash character
+        path.push_string_key("c");
 
         let path_str = to_string(&path);
         assert_eq!(path_str, r"$a.\#notvar.c");
     }
 
+    #[test]
+    fn test_with_wildcards() {
+        let mut path = Structpath::new();
+        path.push_string_key("users");
+        path.push_wildcard();
+        path.push_string_key("tags");
+        path.push_index_wildcard();
+        path.push_string_key("*");
+
+        let path_str = to_string(&path);
+        assert_eq!(path_str, r"$users.*.tags[*].\*");
+    }
+
+    #[test]
+    fn test_with_parents() {
+        let mut path = Structpath::new();
+        path.push_string_key("a");
+        path.push_string_key("b");
+        path.push_parent();
+        path.push_string_key("c");
+        assert_eq!(to_string(&path), "$a.b..c");
+
+        let mut path = Structpath::new();
+        path.push_parent();
+        path.push_index(0);
+        path.push_parent();
+        path.push_parent();
+        assert_eq!(to_string(&path), "$..[0]...");
+    }
+
+    #[test]
+    fn test_key_separator() {
+        let path = parse::parse(r"$a.b\.c[0].#v[#i].*").unwrap();
+
+        let options = FormatOptions::default();
+        assert_eq!(to_string_with(&path, &options), to_string(&path));
+
+        let options = FormatOptions {
+            key_separator: '/',
+            ..FormatOptions::default()
+        };
+        assert_eq!(to_string_with(&path, &options), r"$a/b\.c[0]/#v[#i]/*");
+
+        let path = parse::parse("$a/b.c").unwrap();
+        assert_eq!(to_string_with(&path, &options), r"$a\/b/c");
+
+        let path = parse::parse("$a.x/y*").unwrap();
+        assert_eq!(to_string_with(&path, &options), r"$a/x\/y*");
+    }
+
+    #[test]
+    fn test_root() {
+        let path = parse::parse("$a[0].b").unwrap();
+        let options = FormatOptions {
+            root: "@".to_string(),
+            key_separator: '/',
+            ..FormatOptions::default()
+        };
+        assert_eq!(to_string_with(&path, &options), "@a[0]/b");
+        assert_eq!(to_string_with(&Structpath::new(), &options), "@");
+
+        let options = FormatOptions {
+            root: String::new(),
+            ..FormatOptions::default()
+        };
+        assert_eq!(to_string_with(&path, &options), "a[0].b");
+    }
+
+    #[test]
+    fn test_ascii_only() {
+        let options = FormatOptions {
+            ascii_only: true,
+            ..FormatOptions::default()
+        };
+
+        let mut path = Structpath::new();
+        path.push_string_key("smile\u{1F600}");
+        path.push_string_key("tab\there");
+        path.push_key_pattern("caf\u{E9}*");
+
+        assert_eq!(to_string(&path), "$smile\u{1F600}.tab\there.caf\u{E9}*");
+        let ascii = to_string_with(&path, &options);
+        assert_eq!(ascii, r"$smile\u{1F600}.tab\u{9}here.caf\u{E9}*");
+        assert_eq!(parse::parse(&ascii).unwrap(), path);
+    }
+
+    #[test]
+    fn test_to_dot_notation() {
+        let dotted = |s| to_dot_notation(&parse::parse(s).unwrap());
+
+        assert_eq!(dotted("$users[0].name").unwrap(), "users.0.name");
+        assert_eq!(dotted(r"$a\.b[1][2].3").unwrap(), "a_b.1.2.3");
+        assert_eq!(dotted("$").unwrap(), "");
+
+        assert!(matches!(
+            dotted("$users[#i].name"),
+            Err(StructpathError::UnexpectedVariable(name)) if name == "i"
+        ));
+        assert!(matches!(
+            dotted("$users.*"),
+            Err(StructpathError::UnexpectedWildcard)
+        ));
+    }
+
+    #[test]
+    fn test_to_jq() {
+        let jq = |s| to_jq(&parse::parse(s).unwrap());
+
+        assert_eq!(jq("$users[0].name").unwrap(), ".users[0].name");
+        assert_eq!(jq(r"$a\.b.c").unwrap(), r#"."a.b".c"#);
+        assert_eq!(jq(r#"$x.a\ "b".1"#).unwrap(), r#".x."a \"b\""."1""#);
+        assert_eq!(jq("$m[0][1][2]").unwrap(), ".m[0][1][2]");
+        assert_eq!(jq("$[0][1]").unwrap(), ".[0][1]");
+        assert_eq!(jq("$users.*.tags[*]").unwrap(), ".users[].tags[]");
+        assert_eq!(jq("$").unwrap(), ".");
+
+        assert!(matches!(
+            jq("$users.#id.name"),
+            Err(StructpathError::UnexpectedVariable(name)) if name == "id"
+        ));
+        assert!(matches!(
+            jq("$a.b*"),
+            Err(StructpathError::UnexpectedWildcard)
+        ));
+    }
+
+    #[test]
+    fn test_display_segments() {
+        let cases = [
+            (Segment::Key(SegmentKey::String("a".into())), ".a"),
+            (Segment::Key(SegmentKey::String("b.c".into())), r".b\.c"),
+            (Segment::Key(SegmentKey::Int(7)), ".7"),
+            (Segment::Index(0), "[0]"),
+            (Segment::FindValue(serde_json::json!("x")), r#"[="x"]"#),
+            (Segment::KeyVariable("id".into()), ".#id"),
+            (Segment::IndexVariable("i".into()), "[#i]"),
+            (Segment::NthKeyVariable("id".into(), 2), ".#id{2}"),
+            (Segment::Wildcard, ".*"),
+            (Segment::IndexWildcard, "[*]"),
+            (Segment::KeyPattern("log_?".into()), ".log_?"),
+            (Segment::Parent, ".."),
+            (Segment::Length, ".length"),
+        ];
+
+        for (segment, expected) in cases {
+            assert_eq!(segment.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_display_segment_keys() {
+        assert_eq!(SegmentKey::String("a".into()).to_string(), "a");
+        assert_eq!(SegmentKey::String("a[0]".into()).to_string(), r"a\[0\]");
+        assert_eq!(SegmentKey::String("12".into()).to_string(), r"\12");
+        assert_eq!(SegmentKey::Int(12).to_string(), "12");
+    }
+
+    #[test]
+    fn test_roundtrip_with_navigation() {
+        let options = parse::ParseOptions { navigation: true };
+        let path_strs = vec!["$a.b..c", "$..a", "$a..[0]", "$a...", "$..[0]"];
+
+        for path_str in path_strs {
+            let path = parse::parse_with(path_str, &options).unwrap();
+            let new_path_str = format!("{}", path);
+            assert_eq!(new_path_str, path_str);
+            let new_path = parse::parse_with(&new_path_str, &options).unwrap();
+            assert_eq!(path, new_path);
+        }
+    }
+
+    #[test]
+    fn test_with_key_patterns() {
+        let mut path = Structpath::new();
+        path.push_key_pattern("log_?");
+        path.push_key_pattern(r"a.b\**");
+        path.push_string_key("c?");
+
+        assert_eq!(format!("{}", path), r"$log_?.a\.b\**.c\?");
+    }
+
+    #[test]
+    fn test_with_length() {
+        let mut path = Structpath::new();
+        path.push_length();
+        assert_eq!(format!("{}", path), "$length");
+
+        let mut path = Structpath::new();
+        path.push_string_key("items");
+        path.push_length();
+        assert_eq!(format!("{}", path), "$items.length");
+
+        let mut path = Structpath::new();
+        path.push_string_key("length");
+        assert_eq!(format!("{}", path), r"$\length");
+    }
+
+    #[test]
+    fn test_with_empty_key() {
+        let mut path = Structpath::new();
+        path.push_string_key("");
+        path.push_string_key("a");
+        path.push_string_key("");
+        assert_eq!(format!("{}", path), r#"$"".a."""#);
+
+        // A key made of two quotes is escaped
+        let mut path = Structpath::new();
+        path.push_string_key(r#""""#);
+        assert_eq!(format!("{}", path), r#"$\"""#);
+    }
+
     #[test]
     fn test_roundtrip() {
         let path_strs = vec![
@@ -167,6 +725,18 @@ mod tests {
             "$a.#var.c",
             "$teams.#teamId.members.#userId",
             "$items[#idx].value",
+            "$users.*.tags[*]",
+            "$users.#id{2}.name",
+            r"$a\*b.\*",
+            r"$logs.log_?.a*b",
+            r"$x\.y?.b\*?.c\\?.d\?",
+            r"$items.length",
+            r"$items.\length",
+            r#"$a."".b"#,
+            r#"$"""#,
+            r#"$a.\"""#,
+            r#"$items[=5].name"#,
+            r#"$items[="a]b\"c"][=null][=true]"#,
         ];
 
         for path_str in path_strs {
@@ -1,6 +1,7 @@
-use crate::types::{Segment, Structpath};
+use crate::access::{concrete_key, find_value, nth_entry, pattern_matches};
+use crate::types::{Segment, SegmentKey, Structpath};
 use serde_json::Value;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::HashMap;
 
 /// A state item for the variable iterator
 #[derive(Clone)]
@@ -11,29 +12,64 @@ struct VariableIterState<'a> {
 }
 
 /// An iterator that finds all possible variable resolutions for a path in a data structure
+///
+/// Results come out depth-first, with object entries sorted by key (the
+/// order of a `serde_json` map, which does not keep the order of the
+/// source) and array elements by ascending index. A result whose binding
+/// for an earlier segment comes first in that order is always yielded
+/// first.
 pub struct VariableIterator<'a> {
-    stack: VecDeque<VariableIterState<'a>>,
+    /// Pending states, with the next one to expand on top
+    stack: Vec<VariableIterState<'a>>,
     path: &'a Structpath,
-    visited: HashSet<String>, // Track visited paths to avoid duplicates
+    /// Variables bound up front, which are not iterated
+    fixed: HashMap<String, String>,
 }
 
 impl<'a> VariableIterator<'a> {
     /// Create a new iterator to find all possible variable resolutions for a path
     pub fn new(path: &'a Structpath, data: &'a Value) -> Self {
-        let mut stack = VecDeque::new();
+        Self::with_fixed(path, data, HashMap::new())
+    }
 
+    /// Like `new`, but the variables in `fixed` keep their given value and
+    /// only lead into the matching key or index. Results still include
+    /// them in their bindings.
+    pub fn with_fixed(
+        path: &'a Structpath,
+        data: &'a Value,
+        fixed: HashMap<String, String>,
+    ) -> Self {
         // Initial state with empty path and variable values
-        stack.push_back(VariableIterState {
+        let stack = vec![VariableIterState {
             value: data,
             current_segment_idx: 0,
             variable_values: HashMap::new(),
-        });
+        }];
 
-        VariableIterator {
-            stack,
-            path,
-            visited: HashSet::new(),
+        VariableIterator { stack, path, fixed }
+    }
+}
+
+impl VariableIterator<'_> {
+    /// Returns the value `var_name` must take at this point: its fixed
+    /// value, or the value bound by an earlier occurrence of the name in a
+    /// path with duplicate variables.
+    fn bound_value(
+        &self,
+        state: &VariableIterState<'_>,
+        var_name: &str,
+    ) -> Option<String> {
+        if let Some(fixed) = self.fixed.get(var_name) {
+            return Some(fixed.clone());
         }
+        state
+            .variable_values
+            .get(var_name)
+            .map(|value| match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
     }
 }
 
@@ -41,21 +77,18 @@ impl<'a> Iterator for VariableIterator<'a> {
     type Item = (&'a Value, HashMap<String, Value>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(state) = self.stack.pop_front() {
-            // If we've processed all segments, we found a match
+        while let Some(state) = self.stack.pop() {
+            // If we've processed all segments, we found a match. Every state
+            // descends into a distinct child, so matches are never repeated;
+            // with wildcards, several may share the same variable bindings.
             if state.current_segment_idx >= self.path.segments().len() {
-                // Create a unique key for this result to avoid duplicates
-                let key = format!("{:?}", state.variable_values);
-                if !self.visited.insert(key) {
-                    continue; // Skip if we've already seen this combination
-                }
-
                 return Some((state.value, state.variable_values));
             }
 
             // Get the current segment to process
             let current_segment =
                 &self.path.segments()[state.current_segment_idx];
+            let mut children = Vec::new();
 
             match current_segment {
                 Segment::Key(key_segment) => {
@@ -67,7 +100,7 @@ impl<'a> Iterator for VariableIterator<'a> {
                                     let mut new_state = state.clone();
                                     new_state.value = next_value;
                                     new_state.current_segment_idx += 1;
-                                    self.stack.push_back(new_state);
+                                    children.push(new_state);
                                 }
                             }
                         }
@@ -78,7 +111,7 @@ impl<'a> Iterator for VariableIterator<'a> {
                                     let mut new_state = state.clone();
                                     new_state.value = next_value;
                                     new_state.current_segment_idx += 1;
-                                    self.stack.push_back(new_state);
+                                    children.push(new_state);
                                 }
                             }
                         }
@@ -91,15 +124,14 @@ impl<'a> Iterator for VariableIterator<'a> {
                             let mut new_state = state.clone();
                             new_state.value = next_value;
                             new_state.current_segment_idx += 1;
-                            self.stack.push_back(new_state);
+                            children.push(new_state);
                         }
                     }
                 }
                 Segment::KeyVariable(var_name) => {
                     // Handle key variable
                     if let Value::Object(map) = state.value {
-                        // Try all object keys as possible values for the variable
-                        for (key, next_value) in map {
+                        let mut push_child = |key: &String, next_value| {
                             let mut new_state = state.clone();
                             // Store key as a string Value
                             new_state.variable_values.insert(
@@ -108,15 +140,44 @@ impl<'a> Iterator for VariableIterator<'a> {
                             );
                             new_state.value = next_value;
                             new_state.current_segment_idx += 1;
-                            self.stack.push_back(new_state);
+                            children.push(new_state);
+                        };
+
+                        match self.bound_value(&state, var_name) {
+                            // A fixed or already bound variable only
+                            // descends into its key
+                            Some(fixed_key) => {
+                                if let Some((key, next_value)) =
+                                    map.get_key_value(&fixed_key)
+                                {
+                                    push_child(key, next_value);
+                                }
+                            }
+                            // Try all object keys as possible values for the variable
+                            None => {
+                                for (key, next_value) in map {
+                                    push_child(key, next_value);
+                                }
+                            }
+                        }
+                    }
+                }
+                Segment::FindValue(literal) => {
+                    if let Value::Array(arr) = state.value {
+                        if let Some(next_value) =
+                            arr.iter().find(|element| *element == literal)
+                        {
+                            let mut new_state = state.clone();
+                            new_state.value = next_value;
+                            new_state.current_segment_idx += 1;
+                            children.push(new_state);
                         }
                     }
                 }
                 Segment::IndexVariable(var_name) => {
                     // Handle index variable
                     if let Value::Array(arr) = state.value {
-                        // Try all array indices as possible values for the variable
-                        for (idx, next_value) in arr.iter().enumerate() {
+                        let mut push_child = |idx: usize, next_value| {
                             let mut new_state = state.clone();
                             // Store index as a number Value
                             new_state.variable_values.insert(
@@ -127,17 +188,276 @@ impl<'a> Iterator for VariableIterator<'a> {
                             );
                             new_state.value = next_value;
                             new_state.current_segment_idx += 1;
-                            self.stack.push_back(new_state);
+                            children.push(new_state);
+                        };
+
+                        match self.bound_value(&state, var_name) {
+                            // A fixed or already bound variable that is not
+                            // an index in the array matches nothing
+                            Some(fixed_idx) => {
+                                let idx = fixed_idx.parse::<usize>().ok();
+                                if let Some((idx, next_value)) =
+                                    idx.and_then(|idx| {
+                                        arr.get(idx).map(|v| (idx, v))
+                                    })
+                                {
+                                    push_child(idx, next_value);
+                                }
+                            }
+                            // Try all array indices as possible values for the variable
+                            None => {
+                                for (idx, next_value) in arr.iter().enumerate()
+                                {
+                                    push_child(idx, next_value);
+                                }
+                            }
+                        }
+                    }
+                }
+                Segment::NthKeyVariable(var_name, n) => {
+                    // Bind only the selected occurrence, among the keys the
+                    // rest of the path matches anything under
+                    let bind = |key: &str, next_value| {
+                        let mut new_state = state.clone();
+                        new_state.variable_values.insert(
+                            var_name.clone(),
+                            Value::String(key.to_string()),
+                        );
+                        new_state.value = next_value;
+                        new_state.current_segment_idx += 1;
+                        new_state
+                    };
+                    let occurrence = nth_entry(
+                        state.value,
+                        var_name,
+                        *n,
+                        |key, next_value| {
+                            let mut rest = VariableIterator {
+                                stack: vec![bind(key, next_value)],
+                                path: self.path,
+                                fixed: self.fixed.clone(),
+                            };
+                            rest.next().is_some()
+                        },
+                    );
+                    if let Ok((key, next_value)) = occurrence {
+                        children.push(bind(&key, next_value));
+                    }
+                }
+                Segment::Wildcard => {
+                    // Expand every child without binding anything
+                    if let Value::Object(map) = state.value {
+                        for next_value in map.values() {
+                            let mut new_state = state.clone();
+                            new_state.value = next_value;
+                            new_state.current_segment_idx += 1;
+                            children.push(new_state);
+                        }
+                    }
+                }
+                Segment::KeyPattern(pattern) => {
+                    if let Value::Object(map) = state.value {
+                        for (key, next_value) in map {
+                            if pattern_matches(pattern, key) {
+                                let mut new_state = state.clone();
+                                new_state.value = next_value;
+                                new_state.current_segment_idx += 1;
+                                children.push(new_state);
+                            }
+                        }
+                    }
+                }
+                Segment::IndexWildcard => {
+                    if let Value::Array(arr) = state.value {
+                        for next_value in arr {
+                            let mut new_state = state.clone();
+                            new_state.value = next_value;
+                            new_state.current_segment_idx += 1;
+                            children.push(new_state);
                         }
                     }
                 }
+                // Unnormalized paths and lengths yield no results
+                Segment::Parent | Segment::Length => {}
             }
+
+            // Push in reverse so the first child is expanded next
+            self.stack.extend(children.into_iter().rev());
         }
 
         None
     }
 }
 
+/// The state of a lazy search for every node a path matches, kept apart
+/// from the data so that the data can be owned elsewhere, as by the Python
+/// iterator.
+///
+/// Each pending state is the concrete path of a node matched so far, so
+/// its next segment is the one at the same position in the pattern path.
+/// Variables, wildcards and key patterns expand to every child they can
+/// stand for, and a variable used twice takes the key or index of its
+/// first occurrence.
+#[derive(Debug, Clone)]
+pub(crate) struct MatchCursor {
+    path: Structpath,
+    stack: Vec<Vec<Segment>>,
+}
+
+impl MatchCursor {
+    pub(crate) fn new(path: Structpath) -> Self {
+        MatchCursor {
+            path,
+            stack: vec![Vec::new()],
+        }
+    }
+
+    /// Finds the next match in `data`, which must be the same document on
+    /// every call.
+    pub(crate) fn next_in<'a>(
+        &mut self,
+        data: &'a Value,
+    ) -> Option<(Structpath, &'a Value)> {
+        let segments = self.path.segments();
+        while let Some(concrete) = self.stack.pop() {
+            let Some(node) = node_at(data, &concrete) else {
+                continue;
+            };
+            let Some(segment) = segments.get(concrete.len()) else {
+                // Concrete segments have no variables, so this cannot fail
+                let path = Structpath::try_from_iter(concrete).ok()?;
+                return Some((path, node));
+            };
+
+            let steps = self.steps(data, node, segment, &concrete);
+            // Push in reverse so the first child is expanded next
+            for step in steps.into_iter().rev() {
+                let mut child = concrete.clone();
+                child.push(step);
+                self.stack.push(child);
+            }
+        }
+        None
+    }
+
+    /// The concrete segments `segment` leads to from `node`, the node at
+    /// `concrete` in `data`, keys in the map's order and indexes ascending.
+    fn steps(
+        &self,
+        data: &Value,
+        node: &Value,
+        segment: &Segment,
+        concrete: &[Segment],
+    ) -> Vec<Segment> {
+        // A repeated variable only follows the step of its first occurrence
+        if let Some(name) = segment.variable_name() {
+            let first = self
+                .path
+                .segments()
+                .iter()
+                .position(|s| s.variable_name() == Some(name));
+            if let Some(bound) = first
+                .filter(|pos| *pos < concrete.len())
+                .map(|pos| &concrete[pos])
+            {
+                return match (segment, bound) {
+                    (Segment::IndexVariable(_), Segment::Index(_))
+                    | (
+                        Segment::KeyVariable(_) | Segment::NthKeyVariable(..),
+                        Segment::Key(_),
+                    ) => vec![bound.clone()],
+                    _ => Vec::new(),
+                };
+            }
+        }
+
+        match (segment, node) {
+            (Segment::Key(SegmentKey::String(key)), _) => {
+                vec![concrete_key(key)]
+            }
+            (Segment::Key(_) | Segment::Index(_), _) => vec![segment.clone()],
+            (Segment::FindValue(literal), _) => find_value(node, literal)
+                .map(|(idx, _)| vec![Segment::Index(idx)])
+                .unwrap_or_default(),
+            (Segment::NthKeyVariable(var_name, n), _) => {
+                // Only keys the rest of the path matches anything under
+                // count as an occurrence
+                let resolves = |key: &str, _: &Value| {
+                    let mut child = concrete.to_vec();
+                    child.push(concrete_key(key));
+                    let mut rest = MatchCursor {
+                        path: self.path.clone(),
+                        stack: vec![child],
+                    };
+                    rest.next_in(data).is_some()
+                };
+                nth_entry(node, var_name, *n, resolves)
+                    .map(|(key, _)| vec![concrete_key(&key)])
+                    .unwrap_or_default()
+            }
+            (
+                Segment::KeyVariable(_) | Segment::Wildcard,
+                Value::Object(map),
+            ) => map.keys().map(|key| concrete_key(key)).collect(),
+            (Segment::KeyPattern(pattern), Value::Object(map)) => map
+                .keys()
+                .filter(|key| pattern_matches(pattern, key))
+                .map(|key| concrete_key(key))
+                .collect(),
+            (
+                Segment::IndexVariable(_) | Segment::IndexWildcard,
+                Value::Array(arr),
+            ) => (0..arr.len()).map(Segment::Index).collect(),
+            // Unnormalized paths and lengths match nothing
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Follows concrete key and index segments from `data`.
+fn node_at<'a>(data: &'a Value, concrete: &[Segment]) -> Option<&'a Value> {
+    concrete
+        .iter()
+        .try_fold(data, |node, segment| match segment {
+            Segment::Key(SegmentKey::String(key)) => node.as_object()?.get(key),
+            Segment::Key(SegmentKey::Int(key)) => {
+                node.as_object()?.get(&key.to_string())
+            }
+            Segment::Index(idx) => node.as_array()?.get(*idx),
+            _ => None,
+        })
+}
+
+/// An iterator over every node a path matches, with its concrete path.
+///
+/// Results come out lazily and depth-first in sorted key order, like those
+/// of `VariableIterator`, but wildcards and key patterns are expanded too
+/// and each result carries the path that leads to it instead of the
+/// variable bindings.
+pub struct MatchIterator<'a> {
+    data: &'a Value,
+    cursor: MatchCursor,
+}
+
+impl<'a> Iterator for MatchIterator<'a> {
+    type Item = (Structpath, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.cursor.next_in(self.data)
+    }
+}
+
+/// Create a MatchIterator for every node `path` matches in `data`
+pub fn iter_matches<'a>(
+    path: &Structpath,
+    data: &'a Value,
+) -> MatchIterator<'a> {
+    MatchIterator {
+        data,
+        cursor: MatchCursor::new(path.clone()),
+    }
+}
+
 /// Create a VariableIterator for all possible variable resolutions in a path
 pub fn iter_variables<'a>(
     path: &'a Structpath,
@@ -146,6 +466,16 @@ pub fn iter_variables<'a>(
     VariableIterator::new(path, data)
 }
 
+/// Create a VariableIterator that keeps the variables in `fixed` bound and
+/// only iterates the others
+pub fn iter_variables_with<'a>(
+    path: &'a Structpath,
+    data: &'a Value,
+    fixed: &HashMap<String, String>,
+) -> VariableIterator<'a> {
+    VariableIterator::with_fixed(path, data, fixed.clone())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -249,6 +579,121 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_iter_with_fixed_variables() {
+        let data = json!({
+            "teams": {
+                "team1": {"members": {"user1": 85, "user2": 92}},
+                "team2": {"members": {"user3": 78}}
+            },
+            "scores": [[1, 2], [3]]
+        });
+
+        let path = parse("$teams.#teamId.members.#userId").unwrap();
+        let fixed: HashMap<String, String> =
+            [("teamId".to_string(), "team1".to_string())].into();
+        let results: Vec<_> = iter_variables_with(&path, &data, &fixed)
+            .map(|(value, vars)| (value.clone(), vars["userId"].clone()))
+            .collect();
+        assert_eq!(
+            results,
+            [(json!(85), json!("user1")), (json!(92), json!("user2"))]
+        );
+
+        // Fixed variables are reported in the bindings too
+        let (_, vars) =
+            iter_variables_with(&path, &data, &fixed).next().unwrap();
+        assert_eq!(vars["teamId"], json!("team1"));
+
+        let path = parse("$scores[#i][#j]").unwrap();
+        let fixed: HashMap<String, String> =
+            [("i".to_string(), "0".to_string())].into();
+        let values: Vec<_> = iter_variables_with(&path, &data, &fixed)
+            .map(|(value, _)| value.clone())
+            .collect();
+        assert_eq!(values, [json!(1), json!(2)]);
+
+        for unmatched in ["5", "x"] {
+            let fixed = [("i".to_string(), unmatched.to_string())].into();
+            assert_eq!(iter_variables_with(&path, &data, &fixed).count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_iter_joins_duplicate_variables() {
+        let data = json!({
+            "grid": {"x": {"x": 1, "y": 2}, "y": {"x": 3, "z": 4}},
+            "pairs": [[0, 1], [5, 6], [7]]
+        });
+
+        // $grid.#k.#k: only the diagonal, where both keys agree
+        let mut path = parse("$grid.#k").unwrap();
+        path.push_key_variable_allow_dup("k");
+        let results: Vec<_> = iter_variables(&path, &data).collect();
+        assert_eq!(results.len(), 1);
+        assert_eq!(*results[0].0, json!(1));
+        assert_eq!(results[0].1["k"], json!("x"));
+
+        // $pairs[#i][#i]
+        let mut path = parse("$pairs[#i]").unwrap();
+        path.push_index_variable_allow_dup("i");
+        let values: Vec<_> = iter_variables(&path, &data)
+            .map(|(value, _)| value)
+            .collect();
+        assert_eq!(values, [&json!(0), &json!(6)]);
+
+        // A fixed value applies to every occurrence
+        let fixed = [("i".to_string(), "1".to_string())].into();
+        let values: Vec<_> = iter_variables_with(&path, &data, &fixed)
+            .map(|(value, _)| value)
+            .collect();
+        assert_eq!(values, [&json!(6)]);
+    }
+
+    #[test]
+    fn test_iter_order_is_sorted_key_order() {
+        // Keys come out sorted, not in the order they were written in
+        let data = json!({
+            "teams": {
+                "team2": {"members": {"user4": 88, "user3": 78}},
+                "team1": {"members": {"user2": 92, "user1": 85}}
+            }
+        });
+
+        let path = parse("$teams.#teamId.members.#userId").unwrap();
+        let order: Vec<_> = iter_variables(&path, &data)
+            .map(|(value, vars)| {
+                (
+                    vars["teamId"].as_str().unwrap().to_string(),
+                    vars["userId"].as_str().unwrap().to_string(),
+                    value.clone(),
+                )
+            })
+            .collect();
+
+        let expected = [
+            ("team1", "user1", json!(85)),
+            ("team1", "user2", json!(92)),
+            ("team2", "user3", json!(78)),
+            ("team2", "user4", json!(88)),
+        ];
+        assert_eq!(order.len(), expected.len());
+        for (actual, (team, user, value)) in order.iter().zip(&expected) {
+            assert_eq!(
+                actual,
+                &(team.to_string(), user.to_string(), value.clone())
+            );
+        }
+
+        // Arrays are visited by ascending index, at every level
+        let data = json!({"a": [[1, 2], [3]], "b": [[4]]});
+        let path = parse("$#k[#i][#j]").unwrap();
+        let values: Vec<_> = iter_variables(&path, &data)
+            .map(|(v, _)| v.clone())
+            .collect();
+        assert_eq!(values, vec![json!(1), json!(2), json!(3), json!(4)]);
+    }
+
     #[test]
     fn test_iter_with_index_variables() {
         let data = json!({
@@ -333,4 +778,125 @@ mod tests {
         );
         assert!(dave_found, "Did not find Dave with teamIdx=1, userId=user4");
     }
+
+    #[test]
+    fn test_iter_with_nth_key_variable() {
+        let data = json!({"users": {"alice": 1, "bob": 2}});
+
+        let path = parse("$users.#id{1}").unwrap();
+        let results: Vec<_> = iter_variables(&path, &data).collect();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(*results[0].0, json!(2));
+        assert_eq!(results[0].1.get("id"), Some(&json!("bob")));
+    }
+
+    #[test]
+    fn test_nth_key_variable_skips_keys_without_matches() {
+        let data = json!({
+            "users": {
+                "amy": {"tags": []},
+                "bob": {"tags": ["a"]},
+                "cy": {"tags": ["b", "c"]}
+            }
+        });
+
+        let path = parse("$users.#id{1}.tags[#i]").unwrap();
+        let results: Vec<_> = iter_variables(&path, &data).collect();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].1.get("id"), Some(&json!("cy")));
+
+        let found: Vec<_> = iter_matches(&path, &data)
+            .map(|(path, _)| path.to_string())
+            .collect();
+        assert_eq!(found, vec!["$users.cy.tags[0]", "$users.cy.tags[1]"]);
+    }
+
+    #[test]
+    fn test_iter_with_key_pattern() {
+        let data = json!({
+            "log_a": {"lines": ["x"]},
+            "log_b": {"lines": ["y", "z"]},
+            "meta": {"lines": ["skip"]}
+        });
+
+        let path = parse("$log_?.lines[#i]").unwrap();
+        let values: Vec<_> = iter_variables(&path, &data)
+            .map(|(v, _)| v.clone())
+            .collect();
+
+        assert_eq!(values, vec![json!("x"), json!("y"), json!("z")]);
+    }
+
+    #[test]
+    fn test_iter_matches() {
+        let data = json!({
+            "users": {
+                "ann": {"tags": ["a", "b"], "id": 1},
+                "bob": {"tags": [], "id": 2},
+                "cy": {"tags": ["c"]}
+            }
+        });
+
+        let path = parse("$users.*.tags[#i]").unwrap();
+        let matches: Vec<(String, &Value)> = iter_matches(&path, &data)
+            .map(|(path, value)| (path.to_string(), value))
+            .collect();
+        assert_eq!(
+            matches,
+            [
+                ("$users.ann.tags[0]".to_string(), &json!("a")),
+                ("$users.ann.tags[1]".to_string(), &json!("b")),
+                ("$users.cy.tags[0]".to_string(), &json!("c")),
+            ]
+        );
+
+        // Each result's path resolves to its value
+        let path = parse("$users.#u.i?").unwrap();
+        let mut iter = iter_matches(&path, &data);
+        let (first, value) = iter.next().unwrap();
+        assert_eq!(first.to_string(), "$users.ann.id");
+        assert_eq!(first.get(&data, None).unwrap(), value);
+        assert_eq!(iter.count(), 1);
+
+        let path = parse("$users.ann.tags.length").unwrap();
+        assert_eq!(iter_matches(&path, &data).count(), 0);
+        let path = parse("$").unwrap();
+        assert_eq!(iter_matches(&path, &data).next().unwrap().1, &data);
+    }
+
+    #[test]
+    fn test_iter_matches_non_canonical_int_keys() {
+        let data = json!({"007": 1, "a": 2, "-0": 3, "7": 4});
+
+        for path_str in ["$*", "$#k", "$?*"] {
+            let path = parse(path_str).unwrap();
+            let matches: Vec<_> = iter_matches(&path, &data).collect();
+            assert_eq!(matches.len(), 4, "{}", path_str);
+            for (resolved, value) in matches {
+                assert_eq!(resolved.get(&data, None).unwrap(), value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_iter_with_wildcards() {
+        let data = json!({
+            "teams": {
+                "team1": {"members": ["Alice", "Bob"]},
+                "team2": {"members": ["Charlie"]}
+            }
+        });
+
+        let path = parse("$teams.*.members[#idx]").unwrap();
+        let results: Vec<_> = iter_variables(&path, &data).collect();
+
+        // Results sharing a binding for idx must all be kept
+        assert_eq!(results.len(), 3);
+        let first_members = results
+            .iter()
+            .filter(|(_, vars)| vars.get("idx") == Some(&json!(0)))
+            .count();
+        assert_eq!(first_members, 2);
+    }
 }
@@ -1,86 +1,206 @@
 use crate::types::{Structpath, StructpathError};
+use serde_json::Value;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// The reserved key segment that parses as a length segment.
+pub(crate) const LENGTH: &str = "length";
+
+/// The segment that stands for the empty string key, as in `$a."".b`.
+pub(crate) const EMPTY_KEY: &str = "\"\"";
+
+/// Options controlling how path strings are parsed.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Treat runs of dots as navigation: `.` stays the plain separator
+    /// (staying at the current segment) and every additional dot in a run
+    /// is a parent segment, so `a.b..c` is `a`, `b`, parent, `c`.
+    /// Off by default, where empty segments between dots are skipped.
+    pub navigation: bool,
+}
 
 pub fn parse(path_str: &str) -> Result<Structpath, StructpathError> {
+    parse_with(path_str, &ParseOptions::default())
+}
+
+/// Parses one path per line of `input`. Lines are trimmed; blank lines and
+/// lines starting with `//` are skipped. On failure, returns the 1-based
+/// number of the first line that does not parse, with its error.
+pub fn parse_many(
+    input: &str,
+) -> Result<Vec<Structpath>, (usize, StructpathError)> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with("//"))
+        .map(|(line_number, line)| {
+            parse(line).map_err(|err| (line_number, err))
+        })
+        .collect()
+}
+
+/// A problem found while parsing, with the byte offset in the input where
+/// the offending segment (or its opening bracket) starts.
+#[derive(Debug)]
+pub struct ParseIssue {
+    pub position: usize,
+    pub error: StructpathError,
+}
+
+pub fn parse_with(
+    path_str: &str,
+    options: &ParseOptions,
+) -> Result<Structpath, StructpathError> {
+    parse_collect(path_str, options)
+        .map_err(|mut issues| issues.remove(0).error)
+}
+
+/// Parses like `parse_with`, but keeps going past segments that fail and
+/// reports every issue in input order. Failing segments are left out of the
+/// path, so later issues are found relative to the rest of the input.
+pub fn parse_collect(
+    path_str: &str,
+    options: &ParseOptions,
+) -> Result<Structpath, Vec<ParseIssue>> {
     let mut path = Structpath::new();
-    let mut chars = path_str.chars().peekable();
+    let mut issues = Vec::new();
+    let mut chars = path_str.char_indices().peekable();
 
-    if chars.peek() == Some(&'$') {
+    if matches!(chars.peek(), Some((_, '$'))) {
         chars.next();
     }
 
     let mut current_segment = String::new();
+    // The segment as a key pattern, keeping escapes of pattern characters,
+    // and whether it has any unescaped `?` or `*`.
+    let mut pattern_segment = String::new();
+    let mut is_pattern = false;
     let mut in_brackets = false;
+    // Inside the quotes of a `[="..."]` string literal, which is taken raw
+    // and decoded as JSON.
+    let mut in_literal_string = false;
     let mut escape_next = false;
     let mut is_escaped_segment = false;
     let mut first_char_escaped = false;
     let mut is_variable = false;
+    let mut dot_run = 0;
+    let mut segment_start = 0;
+    let mut bracket_start = 0;
+
+    while let Some((pos, mut c)) = chars.next() {
+        if dot_run > 0 && (c != '.' || escape_next || in_brackets) {
+            push_parents(&mut path, dot_run);
+            dot_run = 0;
+        }
+
+        if current_segment.is_empty() && !escape_next {
+            segment_start = pos;
+        }
 
-    for c in chars {
         if escape_next {
-            current_segment.push(c);
             escape_next = false;
+            if c == 'u' && matches!(chars.peek(), Some((_, '{'))) {
+                match unicode_escape(&mut chars) {
+                    Ok(decoded) => c = decoded,
+                    Err(err) => {
+                        record(&mut issues, pos - 1, Err(err));
+                        continue;
+                    }
+                }
+            }
 
-            if current_segment.len() == 1 {
+            if current_segment.is_empty() {
                 first_char_escaped = true;
             }
+            current_segment.push(c);
+            if matches!(c, '?' | '*' | '\\') {
+                pattern_segment.push('\\');
+            }
+            pattern_segment.push(c);
             is_escaped_segment = true;
             continue;
         }
 
+        if in_literal_string {
+            current_segment.push(c);
+            if c == '\\' {
+                if let Some((_, escaped)) = chars.next() {
+                    current_segment.push(escaped);
+                }
+            } else if c == '"' {
+                in_literal_string = false;
+            }
+            continue;
+        }
+        if c == '"' && in_brackets && current_segment.starts_with('=') {
+            in_literal_string = true;
+            current_segment.push(c);
+            continue;
+        }
+
         match c {
             '\\' => {
                 escape_next = true;
             }
             '.' if !in_brackets => {
                 if !current_segment.is_empty() {
-                    process_segment(
+                    let result = process_segment(
                         &mut path,
                         &current_segment,
+                        is_pattern.then_some(pattern_segment.as_str()),
                         first_char_escaped,
                         is_escaped_segment,
                         is_variable,
                         in_brackets,
-                    )?;
+                    );
+                    record(&mut issues, segment_start, result);
                     current_segment = String::new();
+                    pattern_segment = String::new();
+                    is_pattern = false;
                     first_char_escaped = false;
                     is_escaped_segment = false;
                     is_variable = false;
                 }
+                if options.navigation {
+                    dot_run += 1;
+                }
             }
             '[' if !in_brackets => {
                 if !current_segment.is_empty() {
-                    process_segment(
+                    let result = process_segment(
                         &mut path,
                         &current_segment,
+                        is_pattern.then_some(pattern_segment.as_str()),
                         first_char_escaped,
                         is_escaped_segment,
                         is_variable,
                         in_brackets,
-                    )?;
+                    );
+                    record(&mut issues, segment_start, result);
                     current_segment = String::new();
+                    pattern_segment = String::new();
+                    is_pattern = false;
                     first_char_escaped = false;
                     is_escaped_segment = false;
                     is_variable = false;
                 }
                 in_brackets = true;
+                bracket_start = pos;
             }
             ']' if in_brackets => {
                 in_brackets = false;
 
-                if current_segment.starts_with('#') && current_segment.len() > 1
-                {
-                    let var_name = &current_segment[1..];
-                    path.push_index_variable(var_name)?;
-                } else if let Ok(index) = current_segment.parse::<usize>() {
-                    path.push_index(index);
-                } else {
-                    return Err(StructpathError::ParseError(format!(
-                        "Invalid index: {}",
-                        current_segment
-                    )));
-                }
+                let result = process_index(
+                    &mut path,
+                    &current_segment,
+                    is_escaped_segment,
+                );
+                record(&mut issues, bracket_start, result);
 
                 current_segment = String::new();
+                pattern_segment = String::new();
+                is_pattern = false;
                 first_char_escaped = false;
                 is_escaped_segment = false;
                 is_variable = false;
@@ -88,34 +208,208 @@ pub fn parse(path_str: &str) -> Result<Structpath, StructpathError> {
             '#' if current_segment.is_empty() && !in_brackets => {
                 is_variable = true;
                 current_segment.push(c);
+                pattern_segment.push(c);
+            }
+            _ => {
+                if matches!(c, '?' | '*') && !in_brackets {
+                    is_pattern = true;
+                }
+                current_segment.push(c);
+                pattern_segment.push(c);
             }
-            _ => current_segment.push(c),
         }
     }
 
     if !current_segment.is_empty() {
-        process_segment(
+        let result = process_segment(
             &mut path,
             &current_segment,
+            is_pattern.then_some(pattern_segment.as_str()),
             first_char_escaped,
             is_escaped_segment,
             is_variable,
             in_brackets,
-        )?;
+        );
+        record(&mut issues, segment_start, result);
     }
 
     if in_brackets {
-        return Err(StructpathError::ParseError(
-            "Unclosed bracket".to_string(),
-        ));
+        issues.push(ParseIssue {
+            position: bracket_start,
+            error: StructpathError::ParseError("Unclosed bracket".to_string()),
+        });
     }
 
-    Ok(path)
+    push_parents(&mut path, dot_run);
+
+    if issues.is_empty() {
+        Ok(path)
+    } else {
+        Err(issues)
+    }
+}
+
+/// Decodes the `{hex}` following `\u`, as in `\u{1F600}`.
+fn unicode_escape(
+    chars: &mut Peekable<CharIndices<'_>>,
+) -> Result<char, StructpathError> {
+    chars.next();
+    let mut hex = String::new();
+    for (_, c) in chars.by_ref() {
+        if c == '}' {
+            return u32::from_str_radix(&hex, 16)
+                .ok()
+                .filter(|_| !hex.is_empty() && hex.len() <= 6)
+                .and_then(char::from_u32)
+                .ok_or_else(|| {
+                    StructpathError::ParseError(format!(
+                        "Invalid unicode escape: \\u{{{}}}",
+                        hex
+                    ))
+                });
+        }
+        hex.push(c);
+    }
+
+    Err(StructpathError::ParseError(format!(
+        "Unclosed unicode escape: \\u{{{}",
+        hex
+    )))
+}
+
+fn record(
+    issues: &mut Vec<ParseIssue>,
+    position: usize,
+    result: Result<(), StructpathError>,
+) {
+    if let Err(error) = result {
+        issues.push(ParseIssue { position, error });
+    }
+}
+
+/// Handles the contents of a closed `[...]`.
+fn process_index(
+    path: &mut Structpath,
+    segment: &str,
+    is_escaped_segment: bool,
+) -> Result<(), StructpathError> {
+    if segment == "*" && !is_escaped_segment {
+        path.push_index_wildcard();
+    } else if segment == "#" && !is_escaped_segment {
+        path.push_length();
+    } else if let Some(literal) = segment.strip_prefix('=') {
+        path.push_find_value(parse_literal(literal)?);
+    } else if segment.starts_with('#') && segment.len() > 1 {
+        let var_name = &segment[1..];
+        if split_occurrence(var_name)?.is_some() {
+            return Err(StructpathError::ParseError(format!(
+                "Occurrence selector is only supported on key variables: {}",
+                segment
+            )));
+        }
+        path.push_index_variable(var_name)?;
+    } else {
+        path.push_index(parse_index(segment)?);
+    }
+
+    Ok(())
+}
+
+/// Parses the JSON scalar of a `[=value]` segment.
+fn parse_literal(literal: &str) -> Result<Value, StructpathError> {
+    match serde_json::from_str(literal.trim()) {
+        Ok(
+            value @ (Value::Null
+            | Value::Bool(_)
+            | Value::Number(_)
+            | Value::String(_)),
+        ) => Ok(value),
+        _ => Err(StructpathError::ParseError(format!(
+            "Invalid value to search for: {}; expected a number, a quoted \
+             string, true, false or null",
+            literal
+        ))),
+    }
+}
+
+/// Parses a decimal index, allowing `_` between digits as in `1_000`. Hex
+/// and scientific notation are rejected with a hint at the decimal form.
+fn parse_index(segment: &str) -> Result<usize, StructpathError> {
+    let digits = segment.replace('_', "");
+    let well_formed = !segment.starts_with('_')
+        && !segment.ends_with('_')
+        && !segment.contains("__");
+    if well_formed {
+        if let Ok(index) = digits.parse::<usize>() {
+            return Ok(index);
+        }
+    }
+
+    let decimal_hint = |value: Option<usize>| match value {
+        Some(value) => format!("write it in decimal, as [{}]", value),
+        None => "write it in decimal, like [31]".to_string(),
+    };
+
+    let hex = segment
+        .strip_prefix("0x")
+        .or_else(|| segment.strip_prefix("0X"));
+    let scientific = segment
+        .contains(['e', 'E'])
+        .then(|| segment.parse::<f64>().ok())
+        .flatten();
+
+    let message = if let Some(hex) = hex {
+        format!(
+            "Hexadecimal index [{}] is not supported; {}",
+            segment,
+            decimal_hint(usize::from_str_radix(hex, 16).ok())
+        )
+    } else if let Some(value) = scientific {
+        let whole =
+            value.fract() == 0.0 && (0.0..usize::MAX as f64).contains(&value);
+        format!(
+            "Scientific notation index [{}] is not supported; {}",
+            segment,
+            decimal_hint(whole.then_some(value as usize))
+        )
+    } else {
+        format!("Invalid index: {}", segment)
+    };
+
+    Err(StructpathError::ParseError(message))
+}
+
+/// Splits a `name{n}` variable into its name and occurrence, if it has one.
+fn split_occurrence(
+    var_name: &str,
+) -> Result<Option<(&str, usize)>, StructpathError> {
+    let Some(stripped) = var_name.strip_suffix('}') else {
+        return Ok(None);
+    };
+    let Some((name, n)) = stripped.split_once('{') else {
+        return Ok(None);
+    };
+
+    match n.parse::<usize>() {
+        Ok(n) if !name.is_empty() => Ok(Some((name, n))),
+        _ => Err(StructpathError::ParseError(format!(
+            "Invalid variable occurrence: #{}",
+            var_name
+        ))),
+    }
+}
+
+/// A run of `n` dots is one separator followed by `n - 1` parent segments.
+fn push_parents(path: &mut Structpath, dot_run: usize) {
+    for _ in 1..dot_run {
+        path.push_parent();
+    }
 }
 
 fn process_segment(
     path: &mut Structpath,
     segment: &str,
+    pattern: Option<&str>,
     first_char_escaped: bool,
     is_escaped_segment: bool,
     is_variable: bool,
@@ -125,6 +419,11 @@ fn process_segment(
         // Variable segment: extract variable name (remove leading #)
         let var_name = &segment[1..];
 
+        if let Some((name, n)) = split_occurrence(var_name)? {
+            path.push_nth_key_variable(name, n)?;
+            return Ok(());
+        }
+
         if in_brackets {
             path.push_index_variable(var_name)?;
         } else {
@@ -133,10 +432,31 @@ fn process_segment(
 
         return Ok(());
     }
+    match pattern {
+        Some("*") => {
+            path.push_wildcard();
+            return Ok(());
+        }
+        Some(pattern) => {
+            path.push_key_pattern(pattern);
+            return Ok(());
+        }
+        None => {}
+    }
     if first_char_escaped || is_escaped_segment {
         path.push_string_key(segment);
         return Ok(());
     }
+    if segment == LENGTH && !in_brackets {
+        path.push_length();
+        return Ok(());
+    }
+    if segment == EMPTY_KEY && !in_brackets {
+        path.push_string_key("");
+        return Ok(());
+    }
+    // Only integers get a typed key; `true`, `false` and `null` are
+    // ordinary string keys like any other word.
     if let Ok(int_key) = segment.parse::<i64>() {
         path.push_int_key(int_key);
         return Ok(());
@@ -149,15 +469,16 @@ fn process_segment(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Segment;
 
     #[test]
     fn test_parse_simple_path() {
         let path = parse("$a.b.c").unwrap();
 
         let mut expected = Structpath::new();
-        let _ = expected.push_string_key("a");
-        let _ = expected.push_string_key("b");
-        let _ = expected.push_string_key("c");
+        expected.push_string_key("a");
+        expected.push_string_key("b");
+        expected.push_string_key("c");
 
         assert_eq!(path, expected);
     }
@@ -167,9 +488,9 @@ mod tests {
         let path = parse("$a.#var.c").unwrap();
 
         let mut expected = Structpath::new();
-        let _ = expected.push_string_key("a");
+        expected.push_string_key("a");
         let _ = expected.push_key_variable("var");
-        let _ = expected.push_string_key("c");
+        expected.push_string_key("c");
 
         assert_eq!(path, expected);
     }
@@ -179,9 +500,9 @@ mod tests {
         let path = parse("$a[#idx].b").unwrap();
 
         let mut expected = Structpath::new();
-        let _ = expected.push_string_key("a");
+        expected.push_string_key("a");
         let _ = expected.push_index_variable("idx");
-        let _ = expected.push_string_key("b");
+        expected.push_string_key("b");
 
         assert_eq!(path, expected);
     }
@@ -191,9 +512,9 @@ mod tests {
         let path = parse("$teams[#idx].members.#name").unwrap();
 
         let mut expected = Structpath::new();
-        let _ = expected.push_string_key("teams");
+        expected.push_string_key("teams");
         let _ = expected.push_index_variable("idx");
-        let _ = expected.push_string_key("members");
+        expected.push_string_key("members");
         let _ = expected.push_key_variable("name");
 
         assert_eq!(path, expected);
@@ -204,9 +525,9 @@ mod tests {
         let path = parse("$a.#var.c").unwrap();
 
         let mut expected = Structpath::new();
-        let _ = expected.push_string_key("a");
+        expected.push_string_key("a");
         let _ = expected.push_key_variable("var");
-        let _ = expected.push_string_key("c");
+        expected.push_string_key("c");
 
         assert_eq!(path, expected);
     }
@@ -216,9 +537,9 @@ mod tests {
         let path = parse("$a[#idx].b").unwrap();
 
         let mut expected = Structpath::new();
-        let _ = expected.push_string_key("a");
+        expected.push_string_key("a");
         let _ = expected.push_index_variable("idx");
-        let _ = expected.push_string_key("b");
+        expected.push_string_key("b");
 
         assert_eq!(path, expected);
     }
@@ -228,9 +549,9 @@ mod tests {
         let path = parse("$teams.#teamId.members.#userId").unwrap();
 
         let mut expected = Structpath::new();
-        let _ = expected.push_string_key("teams");
+        expected.push_string_key("teams");
         let _ = expected.push_key_variable("teamId");
-        let _ = expected.push_string_key("members");
+        expected.push_string_key("members");
         let _ = expected.push_key_variable("userId");
 
         assert_eq!(path, expected);
@@ -248,11 +569,11 @@ mod tests {
         let path = parse("$a[0].b[1].c").unwrap();
 
         let mut expected = Structpath::new();
-        let _ = expected.push_string_key("a");
-        let _ = expected.push_index(0);
-        let _ = expected.push_string_key("b");
-        let _ = expected.push_index(1);
-        let _ = expected.push_string_key("c");
+        expected.push_string_key("a");
+        expected.push_index(0);
+        expected.push_string_key("b");
+        expected.push_index(1);
+        expected.push_string_key("c");
 
         assert_eq!(path, expected);
     }
@@ -262,22 +583,316 @@ mod tests {
         let path = parse(r"$a\.b\[0\].c").unwrap();
 
         let mut expected = Structpath::new();
-        let _ = expected.push_string_key("a.b[0]");
-        let _ = expected.push_string_key("c");
+        expected.push_string_key("a.b[0]");
+        expected.push_string_key("c");
 
         assert_eq!(path, expected);
     }
 
+    #[test]
+    fn test_parse_with_unicode_escapes() {
+        let path = parse(r"$smile\u{1F600}.\u{7}bell.\u{31}").unwrap();
+
+        let mut expected = Structpath::new();
+        expected.push_string_key("smile\u{1F600}");
+        expected.push_string_key("\u{7}bell");
+        expected.push_string_key("1");
+        assert_eq!(path, expected);
+
+        // A plain escaped `u` is still just the letter
+        assert_eq!(parse(r"$\u.a").unwrap(), parse("$u.a").unwrap());
+
+        for path_str in [r"$a\u{}", r"$a\u{110000}", r"$a\u{zz}", r"$a\u{41"] {
+            assert!(
+                matches!(parse(path_str), Err(StructpathError::ParseError(_))),
+                "{} should fail",
+                path_str
+            );
+        }
+    }
+
     #[test]
     fn test_parse_with_int_keys() {
         let path = parse("$123.456.789").unwrap();
 
         let mut expected = Structpath::new();
-        let _ = expected.push_int_key(123);
-        let _ = expected.push_int_key(456);
-        let _ = expected.push_int_key(789);
+        expected.push_int_key(123);
+        expected.push_int_key(456);
+        expected.push_int_key(789);
+
+        assert_eq!(path, expected);
+    }
+
+    #[test]
+    fn test_parse_literal_words_as_string_keys() {
+        for word in ["true", "false", "null"] {
+            let path_str = format!("${}", word);
+            let path = parse(&path_str).unwrap();
+
+            let mut expected = Structpath::new();
+            expected.push_string_key(word);
+
+            assert_eq!(path, expected);
+            assert_eq!(format!("{}", path), path_str);
+        }
+    }
+
+    #[test]
+    fn test_parse_with_wildcards() {
+        let path = parse("$users.*.tags[*]").unwrap();
+
+        let mut expected = Structpath::new();
+        expected.push_string_key("users");
+        expected.push_wildcard();
+        expected.push_string_key("tags");
+        expected.push_index_wildcard();
+
+        assert_eq!(path, expected);
+    }
+
+    #[test]
+    fn test_parse_with_escaped_wildcard() {
+        let path = parse(r"$users.\*.a\*b").unwrap();
+
+        let mut expected = Structpath::new();
+        expected.push_string_key("users");
+        expected.push_string_key("*");
+        expected.push_string_key("a*b");
 
         assert_eq!(path, expected);
+
+        let result = parse(r"$items[\*]");
+        assert!(matches!(result, Err(StructpathError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_parse_with_key_patterns() {
+        let path = parse(r"$logs.log_?.a*b.x\.y?").unwrap();
+
+        let mut expected = Structpath::new();
+        expected.push_string_key("logs");
+        expected.push_key_pattern("log_?");
+        expected.push_key_pattern("a*b");
+        expected.push_key_pattern("x.y?");
+        assert_eq!(path, expected);
+
+        // Escaped pattern characters stay literal, in patterns and keys
+        let path = parse(r"$a\?.b\*?.c\\?").unwrap();
+
+        let mut expected = Structpath::new();
+        expected.push_string_key("a?");
+        expected.push_key_pattern(r"b\*?");
+        expected.push_key_pattern(r"c\\?");
+        assert_eq!(path, expected);
+
+        // Variables take no pattern characters
+        let path = parse("$#v?").unwrap();
+        assert_eq!(path.segments(), &[Segment::KeyVariable("v?".into())]);
+    }
+
+    #[test]
+    fn test_parse_index_formats() {
+        let mut expected = Structpath::new();
+        expected.push_string_key("items");
+        expected.push_index(1000);
+        assert_eq!(parse("$items[1_000]").unwrap(), expected);
+        assert_eq!(parse("$items[1_0_0_0]").unwrap(), expected);
+
+        let error = |path_str: &str| match parse(path_str) {
+            Err(StructpathError::ParseError(message)) => message,
+            other => panic!("{}: unexpected {:?}", path_str, other),
+        };
+
+        assert_eq!(
+            error("$items[0x1F]"),
+            "Hexadecimal index [0x1F] is not supported; write it in decimal, as [31]"
+        );
+        assert_eq!(
+            error("$items[1e3]"),
+            "Scientific notation index [1e3] is not supported; write it in decimal, as [1000]"
+        );
+        assert!(error("$items[1.5E2]").starts_with("Scientific notation"));
+        assert!(error("$items[0xZZ]").ends_with("like [31]"));
+        for bad in ["$items[_1]", "$items[1_]", "$items[1__0]"] {
+            assert!(error(bad).starts_with("Invalid index"), "{}", bad);
+        }
+        assert_eq!(error("$items[abc]"), "Invalid index: abc");
+    }
+
+    #[test]
+    fn test_parse_with_empty_key() {
+        let mut expected = Structpath::new();
+        expected.push_string_key("a");
+        expected.push_string_key("");
+        expected.push_index(0);
+        assert_eq!(parse(r#"$a.""[0]"#).unwrap(), expected);
+
+        let mut expected = Structpath::new();
+        expected.push_string_key(r#""""#);
+        assert_eq!(parse(r#"$\"""#).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_with_find_value() {
+        let path = parse(r#"$a[=5][="x]\"y"][=true][=null][=-1.5]"#).unwrap();
+        assert_eq!(
+            path.segments()[1..],
+            [
+                Segment::FindValue(serde_json::json!(5)),
+                Segment::FindValue(serde_json::json!("x]\"y")),
+                Segment::FindValue(serde_json::json!(true)),
+                Segment::FindValue(serde_json::json!(null)),
+                Segment::FindValue(serde_json::json!(-1.5)),
+            ]
+        );
+
+        for path_str in ["$a[=]", "$a[=x]", r#"$a[="x]"#, "$a[={}]"] {
+            assert!(
+                matches!(parse(path_str), Err(StructpathError::ParseError(_))),
+                "{}",
+                path_str
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_with_length() {
+        let mut expected = Structpath::new();
+        expected.push_string_key("items");
+        expected.push_length();
+
+        assert_eq!(parse("$items.length").unwrap(), expected);
+        assert_eq!(parse("$items[#]").unwrap(), expected);
+
+        // Escaping keeps "length" a string key
+        let mut expected = Structpath::new();
+        expected.push_string_key("items");
+        expected.push_string_key("length");
+        assert_eq!(parse(r"$items.\length").unwrap(), expected);
+
+        assert!(matches!(
+            parse(r"$items[\#]"),
+            Err(StructpathError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_root_prefixes() {
+        let expected = parse("$a.b").unwrap();
+        assert_eq!(parse(".a.b").unwrap(), expected);
+        assert_eq!(parse("a.b").unwrap(), expected);
+        assert_eq!(parse(".[0]").unwrap(), parse("$[0]").unwrap());
+        assert_eq!(parse(".").unwrap(), Structpath::new());
+
+        // With navigation, the leading dot is the root's separator and only
+        // the dots after it step up
+        let options = ParseOptions { navigation: true };
+        assert_eq!(parse_with(".a.b", &options).unwrap(), expected);
+        assert_eq!(
+            parse_with("..a", &options).unwrap(),
+            parse_with("$..a", &options).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_with_navigation() {
+        let options = ParseOptions { navigation: true };
+
+        let path = parse_with("a.b..c", &options).unwrap();
+        let mut expected = Structpath::new();
+        expected.push_string_key("a");
+        expected.push_string_key("b");
+        expected.push_parent();
+        expected.push_string_key("c");
+        assert_eq!(path, expected);
+
+        let path = parse_with("..a[0]...", &options).unwrap();
+        let mut expected = Structpath::new();
+        expected.push_parent();
+        expected.push_string_key("a");
+        expected.push_index(0);
+        expected.push_parent();
+        expected.push_parent();
+        assert_eq!(path, expected);
+
+        // Escaped dots stay part of the key
+        let path = parse_with(r"a\..b", &options).unwrap();
+        let mut expected = Structpath::new();
+        expected.push_string_key("a.");
+        expected.push_string_key("b");
+        assert_eq!(path, expected);
+    }
+
+    #[test]
+    fn test_parse_without_navigation_skips_empty_segments() {
+        let path = parse("a.b..c").unwrap();
+
+        let mut expected = Structpath::new();
+        expected.push_string_key("a");
+        expected.push_string_key("b");
+        expected.push_string_key("c");
+
+        assert_eq!(path, expected);
+    }
+
+    #[test]
+    fn test_parse_with_variable_occurrence() {
+        let path = parse("$users.#id{2}.name").unwrap();
+
+        let mut expected = Structpath::new();
+        expected.push_string_key("users");
+        expected.push_nth_key_variable("id", 2).unwrap();
+        expected.push_string_key("name");
+
+        assert_eq!(path, expected);
+
+        assert!(matches!(
+            parse("$users.#id{x}"),
+            Err(StructpathError::ParseError(_))
+        ));
+        assert!(matches!(
+            parse("$users.#{2}"),
+            Err(StructpathError::ParseError(_))
+        ));
+        assert!(matches!(
+            parse("$users[#i{2}]"),
+            Err(StructpathError::ParseError(_))
+        ));
+        assert!(matches!(
+            parse("$a.#id{0}.b.#id"),
+            Err(StructpathError::DuplicateVariable(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_collect_reports_all_issues() {
+        let options = ParseOptions::default();
+
+        let issues = parse_collect("$a[x].#v.b.#v.c[1", &options).unwrap_err();
+        let found: Vec<_> = issues
+            .iter()
+            .map(|issue| (issue.position, issue.error.to_string()))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (2, "Failed to parse path: Invalid index: x".to_string()),
+                (11, "Duplicate variable name: v".to_string()),
+                (15, "Failed to parse path: Unclosed bracket".to_string()),
+            ]
+        );
+
+        // The single-error entry point reports the first issue
+        assert!(matches!(
+            parse("$a[x].#v.b.#v"),
+            Err(StructpathError::ParseError(_))
+        ));
+
+        assert_eq!(
+            parse_collect("$a[0].#v", &options).unwrap(),
+            parse("$a[0].#v").unwrap()
+        );
     }
 
     #[test]
@@ -285,10 +900,39 @@ mod tests {
         let path = parse(r"$a.\#notvar.c").unwrap();
 
         let mut expected = Structpath::new();
-        let _ = expected.push_string_key("a");
-        let _ = expected.push_string_key("#notvar");
-        let _ = expected.push_string_key("c");
+        expected.push_string_key("a");
+        expected.push_string_key("#notvar");
+        expected.push_string_key("c");
 
         assert_eq!(path, expected);
     }
+
+    #[test]
+    fn test_parse_many() {
+        let input = "
+            // Paths to export
+            $users[0].name
+
+            $users.#id.email
+            config.timeout
+        ";
+        let paths = parse_many(input).unwrap();
+        assert_eq!(paths.len(), 3);
+        assert_eq!(paths[0], parse("$users[0].name").unwrap());
+        assert_eq!(paths[1], parse("$users.#id.email").unwrap());
+        assert!(parse_many("").unwrap().is_empty());
+        assert!(parse_many("\n  // only a comment\n").unwrap().is_empty());
+
+        let input = "$a.b\n\n// skipped\n$a[oops]\n$c[";
+        match parse_many(input) {
+            Err((4, StructpathError::ParseError(_))) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        match parse_many("$a\r\n$#x.#x") {
+            Err((2, StructpathError::DuplicateVariable(name))) => {
+                assert_eq!(name, "x")
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
 }
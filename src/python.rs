@@ -0,0 +1,1222 @@
+use crate::access::json_type_name;
+use crate::format::FormatOptions;
+use crate::iter::MatchCursor;
+use crate::parse::ParseIssue;
+use crate::serialization::{
+    self, DeserializeOptions, DuplicateKeyPolicy, SerializeOptions,
+};
+use crate::types::{Segment, SegmentKey, Structpath, StructpathError};
+use crate::vars::VarContext;
+use crate::walk::NodeKind;
+use crate::write::WriteOptions;
+use pyo3::exceptions::{PyIndexError, PyKeyError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyList, PyString};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+#[pymodule]
+pub fn _structpath(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyStructpath>()?;
+    m.add_class::<PyWalker>()?;
+    m.add_class::<PyVariableIterator>()?;
+    m.add_class::<PyMatchIterator>()?;
+    m.add_class::<PyLazyValue>()?;
+    m.add_function(wrap_pyfunction!(serialization_diagnostics, m)?)?;
+    m.add_function(wrap_pyfunction!(apply_merge_patch, m)?)?;
+    Ok(())
+}
+
+/// Lists the lossy conversions made when serializing `data`, as
+/// `(path, message)` pairs.
+#[pyfunction]
+fn serialization_diagnostics(
+    data: &PyAny,
+) -> PyResult<Vec<(PyStructpath, String)>> {
+    let (_, diagnostics) = serialization::serialize_with_diagnostics(data)?;
+    Ok(diagnostics
+        .into_iter()
+        .map(|d| (PyStructpath { inner: d.path }, d.message))
+        .collect())
+}
+
+/// Applies an RFC 7386 JSON Merge Patch to `data` and returns the result.
+/// A dict `data` is updated in place when the result is still a dict.
+#[pyfunction]
+fn apply_merge_patch(data: &PyAny, patch: &PyAny) -> PyResult<PyObject> {
+    let py = data.py();
+    let mut json_data = serialization::serialize(data)?;
+    let json_patch = serialization::serialize(patch)?;
+
+    Structpath::apply_merge_patch(&mut json_data, &json_patch);
+    let result = serialization::deserialize(&json_data, py)?;
+
+    if let (Ok(dict), Ok(result_dict)) =
+        (data.downcast::<PyDict>(), result.extract::<&PyDict>(py))
+    {
+        dict.clear();
+        for (key, value) in result_dict.iter() {
+            dict.set_item(key, value)?;
+        }
+    }
+
+    Ok(result)
+}
+
+/// Sets `key` in `dict` to a converted copy of `value` without going
+/// through the generic write, which would serialize the whole dict. Only
+/// `value` is converted: the result is a shallow copy of `dict` after the
+/// write, sharing its other values and keys as they are. Returns `None`,
+/// leaving the write to the generic path and its duplicate key policy, if
+/// some key of `dict` is not a string or an int, or an int key collides
+/// with a string key once converted.
+fn write_dict_key(
+    dict: &PyDict,
+    key: &SegmentKey,
+    value: Option<&PyAny>,
+    options: &SerializeOptions,
+) -> PyResult<Option<PyObject>> {
+    let py = dict.py();
+    for dict_key in dict.keys() {
+        if dict_key.downcast::<PyString>().is_ok() {
+            continue;
+        }
+        match dict_key.extract::<i64>() {
+            Ok(int_key) if !dict.contains(int_key.to_string())? => {}
+            _ => return Ok(None),
+        }
+    }
+
+    let json_value = match value {
+        Some(val) => serialization::serialize_with(val, options)?,
+        None => Value::Null,
+    };
+    let key = match key {
+        SegmentKey::String(s) => s.clone(),
+        SegmentKey::Int(i) => i.to_string(),
+    };
+    let dict_key = match existing_dict_key(dict, &key) {
+        Some((existing_key, _)) => existing_key,
+        None => key.to_object(py),
+    };
+    dict.set_item(dict_key, serialization::deserialize(&json_value, py)?)?;
+    Ok(Some(dict.copy()?.to_object(py)))
+}
+
+/// Finds the entry of `dict` for the serialized key `key`, which is either
+/// the key itself or, as int keys are serialized as strings, the int it is
+/// the text of. Returns the key object found along with the value.
+fn existing_dict_key<'py>(
+    dict: &'py PyDict,
+    key: &str,
+) -> Option<(PyObject, &'py PyAny)> {
+    let py = dict.py();
+    match dict.get_item(key) {
+        Some(child) => Some((key.to_object(py), child)),
+        None => key.parse::<i64>().ok().and_then(|int_key| {
+            dict.get_item(int_key)
+                .map(|child| (int_key.to_object(py), child))
+        }),
+    }
+}
+
+/// Converts the `duplicate_keys` argument of the Python methods.
+fn extract_duplicate_keys(name: &str) -> PyResult<DuplicateKeyPolicy> {
+    DuplicateKeyPolicy::from_name(name).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "Unknown duplicate_keys policy '{}', expected 'error', \
+             'last_wins' or 'first_wins'",
+            name
+        ))
+    })
+}
+
+/// Resolves `path` in `value` for the Python `get` methods.
+///
+/// `default` and `lazy` are read from kwargs so that `default=None` can be
+/// told apart from no default at all.
+fn get_in_value(
+    path: &Structpath,
+    value: &Value,
+    vars: Option<&PyDict>,
+    kwargs: Option<&PyDict>,
+    py: Python<'_>,
+) -> PyResult<PyObject> {
+    let mut default = None;
+    let mut lazy = false;
+    let mut trace = false;
+    let mut with_type = false;
+    let mut options = DeserializeOptions::default();
+    if let Some(kwargs) = kwargs {
+        for (key, value) in kwargs.iter() {
+            match key.extract::<&str>()? {
+                "default" => default = Some(value),
+                "lazy" => lazy = value.is_true()?,
+                "trace" => trace = value.is_true()?,
+                "with_type" => with_type = value.is_true()?,
+                "sort_keys" => options.sort_keys = value.is_true()?,
+                other => {
+                    return Err(PyTypeError::new_err(format!(
+                        "get() got an unexpected keyword argument '{}'",
+                        other
+                    )))
+                }
+            }
+        }
+    }
+
+    let rust_vars = extract_vars(vars)?;
+    if lazy && options.sort_keys {
+        return Err(PyTypeError::new_err(
+            "get() cannot combine sort_keys with lazy",
+        ));
+    }
+    if with_type && (lazy || trace) {
+        return Err(PyTypeError::new_err(
+            "get() cannot combine with_type with lazy or trace",
+        ));
+    }
+    if trace {
+        if lazy || default.is_some() {
+            return Err(PyTypeError::new_err(
+                "get() cannot combine trace with default or lazy",
+            ));
+        }
+        return trace_in_value(path, value, rust_vars.as_ref(), &options, py);
+    }
+
+    match (path.get_value(value, rust_vars.as_ref()), default) {
+        (Ok(result), _) if lazy => Ok(PyLazyValue {
+            value: result.into_owned(),
+        }
+        .into_py(py)),
+        (Ok(result), _) if with_type => Ok((
+            serialization::deserialize_with(&result, py, &options)?,
+            json_type_name(&result),
+        )
+            .into_py(py)),
+        (Ok(result), _) => {
+            serialization::deserialize_with(&result, py, &options)
+        }
+        // The default is handed back as the original object, never
+        // serialized, and only on a miss.
+        (
+            Err(
+                StructpathError::NotFound
+                | StructpathError::IndexOutOfBounds(_),
+            ),
+            Some(default),
+        ) if with_type => Ok((default, py.None()).into_py(py)),
+        (
+            Err(
+                StructpathError::NotFound
+                | StructpathError::IndexOutOfBounds(_),
+            ),
+            Some(default),
+        ) => Ok(default.into()),
+        (Err(err), _) => Err(get_error_to_py(err, path)),
+    }
+}
+
+/// Runs a traced lookup and returns `(value_or_none, steps)`. Misses are
+/// reported through the steps; an error raised before any segment is tried
+/// (such as a missing variable) is raised as usual.
+fn trace_in_value(
+    path: &Structpath,
+    value: &Value,
+    vars: Option<&VarContext>,
+    options: &DeserializeOptions,
+    py: Python<'_>,
+) -> PyResult<PyObject> {
+    let (result, steps) = path.trace_get(value, vars);
+    let found = match result {
+        Ok(found) => serialization::deserialize_with(found, py, options)?,
+        Err(err) if steps.is_empty() => return Err(get_error_to_py(err, path)),
+        Err(_) => py.None(),
+    };
+
+    let steps: Vec<PyObject> = steps
+        .iter()
+        .map(|step| {
+            (step.segment.to_string(), step.resolved, step.found).into_py(py)
+        })
+        .collect();
+    Ok((found, steps).into_py(py))
+}
+
+/// Copies the node at the concrete path `resolved` of `result` into
+/// `target`, the Python object `result` was written from. The dicts and
+/// lists on the way are reused and only the child that changed is set, so
+/// the other values in `target` keep their identity. Falls back to
+/// `replace_contents` if `target` does not have the shape of `result`.
+///
+/// This walks the Python objects rather than using `Structpath::get_mut`:
+/// that resolves a `&mut Value` of the converted copy, while the objects
+/// whose identity must be kept are the dicts and lists in `target`.
+fn write_in_place(
+    target: &PyAny,
+    resolved: &Structpath,
+    result: &Value,
+) -> PyResult<()> {
+    let py = target.py();
+    let segments = resolved.segments();
+    let mut current = target;
+    let mut current_value = result;
+    for (pos, segment) in segments.iter().enumerate() {
+        let is_last = pos + 1 == segments.len();
+        match segment {
+            Segment::Key(key) => {
+                let key = match key {
+                    SegmentKey::String(s) => s.clone(),
+                    SegmentKey::Int(i) => i.to_string(),
+                };
+                let (Ok(dict), Some(child_value)) =
+                    (current.downcast::<PyDict>(), current_value.get(&key))
+                else {
+                    break;
+                };
+                match existing_dict_key(dict, &key) {
+                    Some((_, child))
+                        if !is_last && same_kind(child, child_value) =>
+                    {
+                        current = child;
+                        current_value = child_value;
+                    }
+                    Some((existing_key, _)) => {
+                        let new_child =
+                            serialization::deserialize(child_value, py)?;
+                        return dict.set_item(existing_key, new_child);
+                    }
+                    None => {
+                        let new_child =
+                            serialization::deserialize(child_value, py)?;
+                        return dict.set_item(key, new_child);
+                    }
+                }
+            }
+            Segment::Index(idx) => {
+                let (Ok(list), Some(items)) =
+                    (current.downcast::<PyList>(), current_value.as_array())
+                else {
+                    break;
+                };
+                if *idx >= list.len() {
+                    // The write padded the array up to the index
+                    for item in items.iter().skip(list.len()) {
+                        list.append(serialization::deserialize(item, py)?)?;
+                    }
+                    return Ok(());
+                }
+                let child = list.get_item(*idx)?;
+                let child_value = &items[*idx];
+                if !is_last && same_kind(child, child_value) {
+                    current = child;
+                    current_value = child_value;
+                } else {
+                    let new_child =
+                        serialization::deserialize(child_value, py)?;
+                    return list.set_item(*idx, new_child);
+                }
+            }
+            _ => break,
+        }
+    }
+    replace_contents(target, result)
+}
+
+/// Tells whether the Python object `obj` is the same kind of container as
+/// `value`, so that it can be updated rather than replaced.
+fn same_kind(obj: &PyAny, value: &Value) -> bool {
+    (obj.is_instance_of::<PyDict>() && value.is_object())
+        || (obj.is_instance_of::<PyList>() && value.is_array())
+}
+
+/// Replaces the contents of a dict or list `target` with `value`, so that
+/// callers holding `target` see the change.
+fn replace_contents(target: &PyAny, value: &Value) -> PyResult<()> {
+    let py = target.py();
+    let new_obj = serialization::deserialize(value, py)?;
+
+    if let (Ok(dict), Ok(new_dict)) =
+        (target.downcast::<PyDict>(), new_obj.extract::<&PyDict>(py))
+    {
+        dict.clear();
+        for (key, value) in new_dict.iter() {
+            dict.set_item(key, value)?;
+        }
+    } else if let (Ok(list), Ok(new_list)) =
+        (target.downcast::<PyList>(), new_obj.extract::<&PyList>(py))
+    {
+        list.del_slice(0, list.len())?;
+        for item in new_list.iter() {
+            list.append(item)?;
+        }
+    }
+    Ok(())
+}
+
+/// Maps an error from resolving `path` to the matching Python exception.
+/// Messages show `path` in its formatted form, with the escapes that make it
+/// parse back to the same path, whatever spelling it was parsed from.
+fn get_error_to_py(err: StructpathError, path: &Structpath) -> PyErr {
+    let py_err = match &err {
+        StructpathError::NotFound => {
+            PyKeyError::new_err(format!("Path not found: {}", path))
+        }
+        StructpathError::InvalidPath { expected, found } => {
+            PyTypeError::new_err(format!(
+                "Invalid path: expected {}, found {}",
+                expected, found
+            ))
+        }
+        StructpathError::IndexOutOfBounds(msg) => {
+            PyIndexError::new_err(msg.clone())
+        }
+        StructpathError::MissingVariable(var_name) => PyValueError::new_err(
+            format!("Missing variable in context: {}", var_name),
+        ),
+        _ => PyValueError::new_err(err.to_string()),
+    };
+    with_error_fields(py_err, &err, None)
+}
+
+/// Attaches the details of `err` to the Python exception `py_err` as
+/// attributes, so that callers can branch on them without parsing the
+/// message. `variant` names the Rust error; `InvalidPath` adds `expected`
+/// and `found`, `MissingVariable` and `DuplicateVariable` add `variable`,
+/// and parse errors add the byte `position` in the path string, if known.
+fn with_error_fields(
+    py_err: PyErr,
+    err: &StructpathError,
+    position: Option<usize>,
+) -> PyErr {
+    let attached = Python::with_gil(|py| -> PyResult<()> {
+        let exception = py_err.value(py);
+        exception.setattr("variant", error_variant(err))?;
+        match err {
+            StructpathError::InvalidPath { expected, found } => {
+                exception.setattr("expected", expected)?;
+                exception.setattr("found", found)?;
+            }
+            StructpathError::MissingVariable(name)
+            | StructpathError::DuplicateVariable(name) => {
+                exception.setattr("variable", name)?;
+            }
+            StructpathError::ParseError(_) => {
+                exception.setattr("position", position)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    });
+    match attached {
+        Ok(()) => py_err,
+        Err(err) => err,
+    }
+}
+
+fn error_variant(err: &StructpathError) -> &'static str {
+    match err {
+        StructpathError::ParseError(_) => "ParseError",
+        StructpathError::DuplicateVariable(_) => "DuplicateVariable",
+        StructpathError::NotFound => "NotFound",
+        StructpathError::InvalidPath { .. } => "InvalidPath",
+        StructpathError::IndexOutOfBounds(_) => "IndexOutOfBounds",
+        StructpathError::MissingVariable(_) => "MissingVariable",
+        StructpathError::InvalidVariableValue(_) => "InvalidVariableValue",
+        StructpathError::UnexpectedWildcard => "UnexpectedWildcard",
+        StructpathError::UnnormalizedPath => "UnnormalizedPath",
+        StructpathError::ParentAboveRoot(_) => "ParentAboveRoot",
+        StructpathError::UnexpectedLength => "UnexpectedLength",
+        StructpathError::IndexSegments(_) => "IndexSegments",
+        StructpathError::UnexpectedVariable(_) => "UnexpectedVariable",
+    }
+}
+
+/// Converts a variable context dict to the context the Rust API takes.
+/// Values may be strings or ints, so indices need not be stringified;
+/// bools are refused rather than taken as 0 and 1.
+fn extract_vars(vars: Option<&PyDict>) -> PyResult<Option<VarContext>> {
+    let Some(dict) = vars else {
+        return Ok(None);
+    };
+
+    let mut context = VarContext::new();
+    for (key, value) in dict.iter() {
+        let name = key.extract::<&str>()?;
+        if let Ok(s) = value.extract::<&str>() {
+            context.insert(name, s);
+        } else if let (false, Ok(i)) =
+            (value.is_instance_of::<PyBool>(), value.extract::<i64>())
+        {
+            context.insert(name, i);
+        } else {
+            return Err(PyTypeError::new_err(format!(
+                "Variable values must be strings or integers, got {} for {}",
+                value.get_type().name()?,
+                name
+            )));
+        }
+    }
+    Ok(Some(context))
+}
+
+/// Accepts either a `Structpath` or a path string to parse.
+fn extract_path(obj: &PyAny) -> PyResult<Structpath> {
+    match obj.extract::<PyStructpath>() {
+        Ok(py_path) => Ok(py_path.inner),
+        Err(_) => Ok(PyStructpath::py_parse(obj.extract::<&str>()?)?.inner),
+    }
+}
+
+#[pyclass(name = "Structpath")]
+#[derive(Clone)]
+struct PyStructpath {
+    inner: Structpath,
+}
+
+#[derive(Clone)]
+struct WalkerState {
+    path: Structpath,
+    value: Value,
+}
+
+#[pyclass(name = "Walker")]
+struct PyWalker {
+    stack: VecDeque<WalkerState>,
+    /// Whether to yield the kind of each node as a third item
+    include_kind: bool,
+    /// Whether `max_nodes` cut the walk short
+    #[pyo3(get)]
+    truncated: bool,
+}
+
+#[pyclass(name = "VariableIterator")]
+struct PyVariableIterator {
+    results: Vec<(Value, HashMap<String, Value>)>,
+    /// The resolved path of each result, only filled with `include_path`
+    paths: Vec<Structpath>,
+    /// Variable names in path order, so each dict is built in that order
+    variable_names: Vec<String>,
+    current_pos: usize,
+}
+
+#[pymethods]
+impl PyVariableIterator {
+    #[new]
+    fn new() -> Self {
+        PyVariableIterator {
+            results: Vec::new(),
+            paths: Vec::new(),
+            variable_names: Vec::new(),
+            current_pos: 0,
+        }
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(
+        mut slf: PyRefMut<'_, Self>,
+        py: Python<'_>,
+    ) -> PyResult<Option<PyObject>> {
+        if slf.current_pos < slf.results.len() {
+            let idx = slf.current_pos;
+            slf.current_pos += 1;
+            let (ref value, ref vars) = slf.results[idx];
+
+            let py_vars = PyDict::new(py);
+            for name in &slf.variable_names {
+                if let Some(v) = vars.get(name) {
+                    py_vars
+                        .set_item(name, serialization::deserialize(v, py)?)?;
+                }
+            }
+
+            let py_value = serialization::deserialize(value, py)?;
+            let result = match slf.paths.get(idx) {
+                Some(path) => (
+                    PyStructpath {
+                        inner: path.clone(),
+                    },
+                    py_vars,
+                    py_value,
+                )
+                    .into_py(py),
+                None => (py_vars, py_value).into_py(py),
+            };
+
+            return Ok(Some(result));
+        }
+        Ok(None)
+    }
+}
+
+/// The iterator returned by `Structpath.iter_matches`. It owns the
+/// converted data and finds the next match only when asked for it.
+#[pyclass(name = "MatchIterator")]
+struct PyMatchIterator {
+    data: Value,
+    cursor: MatchCursor,
+}
+
+#[pymethods]
+impl PyMatchIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(
+        mut slf: PyRefMut<'_, Self>,
+        py: Python<'_>,
+    ) -> PyResult<Option<PyObject>> {
+        let this = &mut *slf;
+        match this.cursor.next_in(&this.data) {
+            Some((path, value)) => {
+                let py_value = serialization::deserialize(value, py)?;
+                Ok(Some((PyStructpath { inner: path }, py_value).into_py(py)))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// A value found by `get(..., lazy=True)`, kept in its converted form until
+/// it is materialized, so that further lookups skip the Python objects.
+#[pyclass(name = "LazyValue")]
+struct PyLazyValue {
+    value: Value,
+}
+
+#[pymethods]
+impl PyLazyValue {
+    /// Resolves `path` inside this value, taking the same keyword arguments
+    /// as `Structpath.get`.
+    #[pyo3(signature = (path, vars = None, **kwargs))]
+    fn get(
+        &self,
+        py: Python<'_>,
+        path: &PyAny,
+        vars: Option<&PyDict>,
+        kwargs: Option<&PyDict>,
+    ) -> PyResult<PyObject> {
+        let path = extract_path(path)?;
+        get_in_value(&path, &self.value, vars, kwargs, py)
+    }
+
+    /// Builds the Python object for this value.
+    fn value(&self, py: Python<'_>) -> PyResult<PyObject> {
+        serialization::deserialize(&self.value, py)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("LazyValue({})", json_type_name(&self.value))
+    }
+}
+
+#[pymethods]
+impl PyWalker {
+    #[new]
+    #[pyo3(signature = (
+        data, root = None, max_nodes = None, *, include_kind = false,
+        duplicate_keys = "error"
+    ))]
+    fn new(
+        data: &PyAny,
+        root: Option<&PyAny>,
+        max_nodes: Option<usize>,
+        include_kind: bool,
+        duplicate_keys: &str,
+    ) -> PyResult<Self> {
+        let options = SerializeOptions {
+            duplicate_keys: extract_duplicate_keys(duplicate_keys)?,
+            ..SerializeOptions::default()
+        };
+        let json_data = serialization::serialize_with(data, &options)?;
+
+        let root_path = match root {
+            None => Structpath::new(),
+            Some(root) => extract_path(root)?,
+        };
+
+        // Initialize stack with an empty result list
+        let mut stack = VecDeque::new();
+
+        // Set up our walker to mimic the Rust implementation
+        let mut rust_walker = root_path
+            .walk_from(&json_data)
+            .map_err(|err| get_error_to_py(err, &root_path))?;
+        if let Some(max_nodes) = max_nodes {
+            rust_walker = rust_walker.with_max_nodes(max_nodes);
+        }
+
+        // Collect all pairs from the walker
+        let results: Vec<(Structpath, Value)> = rust_walker
+            .by_ref()
+            .map(|(path, value)| (path, value.clone()))
+            .collect();
+
+        // Prepare them for Python iteration
+        for (path, value) in results {
+            stack.push_back(WalkerState { path, value });
+        }
+
+        Ok(PyWalker {
+            stack,
+            include_kind,
+            truncated: rust_walker.is_truncated(),
+        })
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(
+        mut slf: PyRefMut<'_, Self>,
+        py: Python<'_>,
+    ) -> PyResult<Option<PyObject>> {
+        if let Some(state) = slf.stack.pop_front() {
+            // Convert to Python objects
+            let kind = NodeKind::of(&state.value).name();
+            let path_obj = PyStructpath { inner: state.path }.into_py(py);
+            let value_obj = serialization::deserialize(&state.value, py)?;
+
+            let item = if slf.include_kind {
+                (path_obj, value_obj, kind).into_py(py)
+            } else {
+                (path_obj, value_obj).into_py(py)
+            };
+            return Ok(Some(item));
+        }
+        Ok(None)
+    }
+}
+
+#[pymethods]
+impl PyStructpath {
+    #[new]
+    fn new() -> Self {
+        PyStructpath {
+            inner: Structpath::new(),
+        }
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "parse")]
+    fn py_parse(path_str: &str) -> PyResult<Self> {
+        match Structpath::parse_collect(path_str) {
+            Ok(inner) => Ok(PyStructpath { inner }),
+            Err(mut issues) => {
+                let ParseIssue { position, error } = issues.remove(0);
+                let py_err = match &error {
+                    StructpathError::DuplicateVariable(name) => {
+                        PyValueError::new_err(format!(
+                            "Duplicate variable name: {}",
+                            name
+                        ))
+                    }
+                    _ => PyValueError::new_err(error.to_string()),
+                };
+                Err(with_error_fields(py_err, &error, Some(position)))
+            }
+        }
+    }
+
+    /// Parses one path per line, skipping blank lines and `//` comments.
+    #[staticmethod]
+    fn parse_many(input: &str) -> PyResult<Vec<Self>> {
+        match Structpath::parse_many(input) {
+            Ok(paths) => Ok(paths
+                .into_iter()
+                .map(|inner| PyStructpath { inner })
+                .collect()),
+            Err((line, err)) => {
+                let py_err =
+                    PyValueError::new_err(format!("Line {}: {}", line, err));
+                let py_err = with_error_fields(py_err, &err, None);
+                Python::with_gil(|py| {
+                    py_err.value(py).setattr("line", line)?;
+                    Err(py_err)
+                })
+            }
+        }
+    }
+
+    fn push_key(&mut self, key: &PyAny) -> PyResult<()> {
+        if let Ok(int_key) = key.extract::<i64>() {
+            self.inner.push_int_key(int_key);
+            Ok(())
+        } else if let Ok(str_key) = key.extract::<String>() {
+            self.inner.push_string_key(&str_key);
+            Ok(())
+        } else {
+            Err(PyTypeError::new_err("Key must be a string or integer"))
+        }
+    }
+
+    fn push_index(&mut self, index: usize) {
+        self.inner.push_index(index);
+    }
+
+    fn push_key_variable(&mut self, name: &str) -> PyResult<()> {
+        match self.inner.push_key_variable(name) {
+            Ok(()) => Ok(()),
+            Err(err) => match err {
+                StructpathError::DuplicateVariable(name) => {
+                    Err(PyValueError::new_err(format!(
+                        "Duplicate variable name: {}",
+                        name
+                    )))
+                }
+                _ => Err(PyValueError::new_err(err.to_string())),
+            },
+        }
+    }
+
+    fn push_index_variable(&mut self, name: &str) -> PyResult<()> {
+        match self.inner.push_index_variable(name) {
+            Ok(()) => Ok(()),
+            Err(err) => match err {
+                StructpathError::DuplicateVariable(name) => {
+                    Err(PyValueError::new_err(format!(
+                        "Duplicate variable name: {}",
+                        name
+                    )))
+                }
+                _ => Err(PyValueError::new_err(err.to_string())),
+            },
+        }
+    }
+
+    /// Extends the path with `(kind, value)` tuples, where kind is one of
+    /// "key", "index", "key_variable" or "index_variable". Nothing is added
+    /// if any segment is invalid.
+    fn append_segments(&mut self, segments: &PyAny) -> PyResult<()> {
+        let mut path = self.inner.clone();
+        for item in segments.iter()? {
+            let (kind, value): (&str, &PyAny) = item?.extract()?;
+            let segment = match kind {
+                "key" => match value.extract::<i64>() {
+                    Ok(int_key) => Segment::Key(SegmentKey::Int(int_key)),
+                    Err(_) => Segment::Key(SegmentKey::String(
+                        value.extract::<String>().map_err(|_| {
+                            PyTypeError::new_err(
+                                "Key must be a string or integer",
+                            )
+                        })?,
+                    )),
+                },
+                "index" => Segment::Index(value.extract()?),
+                "key_variable" => Segment::KeyVariable(value.extract()?),
+                "index_variable" => Segment::IndexVariable(value.extract()?),
+                other => {
+                    return Err(PyValueError::new_err(format!(
+                        "Unknown segment kind: {}",
+                        other
+                    )))
+                }
+            };
+            path.push_segment(segment).map_err(|err| match err {
+                StructpathError::DuplicateVariable(name) => {
+                    PyValueError::new_err(format!(
+                        "Duplicate variable name: {}",
+                        name
+                    ))
+                }
+                _ => PyValueError::new_err(err.to_string()),
+            })?;
+        }
+
+        self.inner = path;
+        Ok(())
+    }
+
+    /// Extends the path with a parsed relative path string, such as
+    /// "b.c[0]". Nothing is added if the string is invalid.
+    fn append(&mut self, path_str: &str) -> PyResult<()> {
+        self.inner
+            .append_path_str(path_str)
+            .map_err(|err| match err {
+                StructpathError::DuplicateVariable(name) => {
+                    PyValueError::new_err(format!(
+                        "Duplicate variable name: {}",
+                        name
+                    ))
+                }
+                _ => PyValueError::new_err(err.to_string()),
+            })
+    }
+
+    fn contains_variable(&self, name: &str) -> bool {
+        self.inner.contains_variable(name)
+    }
+
+    #[pyo3(signature = (data, vars = None, **kwargs))]
+    fn get(
+        &self,
+        data: &PyAny,
+        vars: Option<&PyDict>,
+        kwargs: Option<&PyDict>,
+    ) -> PyResult<PyObject> {
+        // Only needed to serialize the data, so not passed on to the lookup
+        let mut options = SerializeOptions::default();
+        if let Some(kwargs) = kwargs {
+            if let Some(name) = kwargs.get_item("duplicate_keys") {
+                options.duplicate_keys =
+                    extract_duplicate_keys(name.extract()?)?;
+                kwargs.del_item("duplicate_keys")?;
+            }
+        }
+        let value = serialization::serialize_with(data, &options)?;
+        get_in_value(&self.inner, &value, vars, kwargs, data.py())
+    }
+
+    /// Resolves the sibling `key` of the node at this path, i.e. the path
+    /// with its last segment replaced by `key`.
+    #[pyo3(signature = (data, key, vars = None))]
+    fn get_sibling(
+        &self,
+        data: &PyAny,
+        key: &PyAny,
+        vars: Option<&PyDict>,
+    ) -> PyResult<PyObject> {
+        let len = self.inner.segments().len();
+        if len == 0 {
+            return Err(PyValueError::new_err("The root path has no siblings"));
+        }
+
+        let mut sibling = PyStructpath {
+            inner: self.inner.head(len - 1),
+        };
+        sibling.push_key(key)?;
+
+        let value = serialization::serialize(data)?;
+        let rust_vars = extract_vars(vars)?;
+        match sibling.inner.get_value(&value, rust_vars.as_ref()) {
+            Ok(result) => serialization::deserialize(&result, data.py()),
+            Err(err) => Err(get_error_to_py(err, &sibling.inner)),
+        }
+    }
+
+    /// Counts the keys or elements of the node at this path without building
+    /// it as a Python object.
+    #[pyo3(signature = (data, vars = None))]
+    fn child_count(
+        &self,
+        data: &PyAny,
+        vars: Option<&PyDict>,
+    ) -> PyResult<usize> {
+        let value = serialization::serialize(data)?;
+        let rust_vars = extract_vars(vars)?;
+        self.inner
+            .child_count(&value, rust_vars.as_ref())
+            .map_err(|err| get_error_to_py(err, &self.inner))
+    }
+
+    /// Removes the node at this path from `data` in place, returning whether
+    /// anything was removed.
+    #[pyo3(signature = (data, vars = None))]
+    fn unset(&self, data: &PyAny, vars: Option<&PyDict>) -> PyResult<bool> {
+        let mut json_data = serialization::serialize(data)?;
+        let rust_vars = extract_vars(vars)?;
+
+        let removed = self
+            .inner
+            .unset(&mut json_data, rust_vars.as_ref())
+            .map_err(|err| get_error_to_py(err, &self.inner))?;
+        if removed {
+            replace_contents(data, &json_data)?;
+        }
+        Ok(removed)
+    }
+
+    fn iter_matches(&self, data: &PyAny) -> PyResult<PyMatchIterator> {
+        Ok(PyMatchIterator {
+            data: serialization::serialize(data)?,
+            cursor: MatchCursor::new(self.inner.clone()),
+        })
+    }
+
+    #[pyo3(signature = (data, fixed = None, *, include_path = false))]
+    fn iter(
+        &self,
+        data: &PyAny,
+        fixed: Option<&PyDict>,
+        include_path: bool,
+    ) -> PyResult<PyVariableIterator> {
+        let json_data = serialization::serialize(data)?;
+
+        let fixed = extract_vars(fixed)?.unwrap_or_default();
+        let rust_iter = self.inner.iter_variables_with(&json_data, &fixed);
+        let mut results = Vec::new();
+        let mut paths = Vec::new();
+
+        for (value, vars) in rust_iter {
+            if include_path {
+                let bindings = VarContext::from(vars.clone());
+                let path = self
+                    .inner
+                    .resolved_path(&json_data, Some(&bindings))
+                    .map_err(|err| get_error_to_py(err, &self.inner))?;
+                paths.push(path);
+            }
+            results.push((value.clone(), vars));
+        }
+
+        let variable_names = self
+            .inner
+            .segments()
+            .iter()
+            .filter_map(|segment| segment.variable_name().map(str::to_string))
+            .collect();
+
+        Ok(PyVariableIterator {
+            results,
+            paths,
+            variable_names,
+            current_pos: 0,
+        })
+    }
+
+    #[pyo3(signature = (
+        data = None, value = None, vars = None, *, strict_root = false,
+        skip_none = false, duplicate_keys = "error"
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn write(
+        &self,
+        py: Python<'_>,
+        data: Option<&PyAny>,
+        value: Option<&PyAny>,
+        vars: Option<&PyDict>,
+        strict_root: bool,
+        skip_none: bool,
+        duplicate_keys: &str,
+    ) -> PyResult<PyObject> {
+        let serialize_options = SerializeOptions {
+            skip_none,
+            duplicate_keys: extract_duplicate_keys(duplicate_keys)?,
+        };
+
+        // Writing a single key into a dict converts only the value and
+        // returns a shallow copy of the dict. Skipping None values has to
+        // filter the siblings in place, so it cannot take this path.
+        if let (false, Some(dict), [Segment::Key(key)]) = (
+            skip_none,
+            data.and_then(|d| d.downcast::<PyDict>().ok()),
+            self.inner.segments(),
+        ) {
+            if let Some(result) =
+                write_dict_key(dict, key, value, &serialize_options)?
+            {
+                return Ok(result);
+            }
+        }
+
+        let mut json_data = match &data {
+            Some(py_data) => {
+                serialization::serialize_with(py_data, &serialize_options)?
+            }
+            None => Value::Null,
+        };
+
+        let json_value = match value {
+            Some(val) => {
+                serialization::serialize_with(val, &serialize_options)?
+            }
+            None => Value::Null,
+        };
+
+        let rust_vars = extract_vars(vars)?;
+
+        let options = WriteOptions { strict_root };
+        match self.inner.write_with(
+            Some(&mut json_data),
+            json_value,
+            rust_vars.as_ref(),
+            &options,
+        ) {
+            Ok(_) => {
+                // The document was written in place, so it is the result
+                let result = json_data;
+                // Update the original Python object if provided (for side effects)
+                if let Some(py_data) = data {
+                    if skip_none {
+                        // The siblings were filtered too
+                        replace_contents(py_data, &result)?;
+                    } else {
+                        match self
+                            .inner
+                            .resolved_path(&result, rust_vars.as_ref())
+                        {
+                            Ok(resolved) => {
+                                write_in_place(py_data, &resolved, &result)?
+                            }
+                            Err(_) => replace_contents(py_data, &result)?,
+                        }
+                    }
+                }
+
+                serialization::deserialize(&result, py)
+            }
+            Err(err) => {
+                let py_err = match &err {
+                    StructpathError::InvalidPath { expected, found } => {
+                        PyTypeError::new_err(format!(
+                            "Invalid path: expected {}, found {}",
+                            expected, found
+                        ))
+                    }
+                    StructpathError::MissingVariable(var_name) => {
+                        PyValueError::new_err(format!(
+                            "Missing variable in context: {}",
+                            var_name
+                        ))
+                    }
+                    _ => PyValueError::new_err(err.to_string()),
+                };
+                Err(with_error_fields(py_err, &err, None))
+            }
+        }
+    }
+
+    #[staticmethod]
+    #[pyo3(signature = (data, paths, vars = None))]
+    fn get_first(
+        data: &PyAny,
+        paths: &PyAny,
+        vars: Option<&PyDict>,
+    ) -> PyResult<PyObject> {
+        let value = serialization::serialize(data)?;
+        let candidates = paths
+            .iter()?
+            .map(|path| extract_path(path?))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let rust_vars = extract_vars(vars)?;
+        match Structpath::get_first(&candidates, &value, rust_vars.as_ref()) {
+            Ok(result) => serialization::deserialize(result, data.py()),
+            Err(StructpathError::NotFound) => {
+                let tried: Vec<String> =
+                    candidates.iter().map(|path| path.to_string()).collect();
+                Err(PyKeyError::new_err(format!(
+                    "None of the paths found: {}",
+                    tried.join(", ")
+                )))
+            }
+            Err(err) => Err(get_error_to_py(err, &Structpath::new())),
+        }
+    }
+
+    #[staticmethod]
+    #[pyo3(signature = (data, paths, vars = None))]
+    fn exists_many(
+        data: &PyAny,
+        paths: &PyAny,
+        vars: Option<&PyDict>,
+    ) -> PyResult<Vec<bool>> {
+        let value = serialization::serialize(data)?;
+        let paths = paths
+            .iter()?
+            .map(|path| extract_path(path?))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let rust_vars = extract_vars(vars)?;
+        Structpath::exists_many(&paths, &value, rust_vars.as_ref())
+            .map_err(|err| get_error_to_py(err, &Structpath::new()))
+    }
+
+    /// Resolves `path` in a JSON file without building the Python object
+    /// for the whole document; only the result is converted.
+    #[staticmethod]
+    #[pyo3(signature = (path, file_path, vars = None))]
+    fn get_from_json_file(
+        py: Python<'_>,
+        path: &PyAny,
+        file_path: PathBuf,
+        vars: Option<&PyDict>,
+    ) -> PyResult<PyObject> {
+        let path = extract_path(path)?;
+        let file = File::open(&file_path)?;
+        let value: Value = serde_json::from_reader(BufReader::new(file))
+            .map_err(|err| {
+                PyValueError::new_err(format!(
+                    "Invalid JSON in {}: {}",
+                    file_path.display(),
+                    err
+                ))
+            })?;
+
+        let rust_vars = extract_vars(vars)?;
+        match path.get_value(&value, rust_vars.as_ref()) {
+            Ok(result) => serialization::deserialize(&result, py),
+            Err(err) => Err(get_error_to_py(err, &path)),
+        }
+    }
+
+    #[staticmethod]
+    #[pyo3(
+        name = "walk",
+        signature = (
+            data, root = None, max_nodes = None, *, include_kind = false,
+            duplicate_keys = "error"
+        )
+    )]
+    fn py_walk(
+        data: &PyAny,
+        root: Option<&PyAny>,
+        max_nodes: Option<usize>,
+        include_kind: bool,
+        duplicate_keys: &str,
+    ) -> PyResult<PyWalker> {
+        PyWalker::new(data, root, max_nodes, include_kind, duplicate_keys)
+    }
+
+    /// The strings of every path `walk` yields, in the same order.
+    #[staticmethod]
+    fn paths(data: &PyAny) -> PyResult<Vec<String>> {
+        let json_data = serialization::serialize(data)?;
+        Ok(Structpath::walk(&json_data)
+            .map(|(path, _)| path.to_string())
+            .collect())
+    }
+
+    /// Like `paths`, but only for nodes without children: scalars and
+    /// empty containers.
+    #[staticmethod]
+    fn leaf_paths(data: &PyAny) -> PyResult<Vec<String>> {
+        let json_data = serialization::serialize(data)?;
+        Ok(Structpath::walk(&json_data)
+            .filter(|(_, value)| match value {
+                Value::Object(map) => map.is_empty(),
+                Value::Array(arr) => arr.is_empty(),
+                _ => true,
+            })
+            .map(|(path, _)| path.to_string())
+            .collect())
+    }
+
+    /// Renders the path with another root or key separator, escaping keys
+    /// as `str` does.
+    #[pyo3(signature = (*, root = "$", separator = '.', ascii_only = false))]
+    fn format(&self, root: &str, separator: char, ascii_only: bool) -> String {
+        self.inner.to_string_with(&FormatOptions {
+            root: root.to_string(),
+            key_separator: separator,
+            ascii_only,
+        })
+    }
+
+    fn __str__(&self) -> String {
+        format!("{}", self.inner)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Structpath('{}')", self.inner)
+    }
+}
@@ -0,0 +1,224 @@
+use crate::access::pattern_matches;
+use crate::types::{Segment, SegmentKey, Structpath, StructpathError};
+use serde_json::Value;
+
+/// Checks that `path` can be followed through the skeleton document
+/// `schema`; see `Structpath::validate_against_schema`.
+pub fn validate_against_schema(
+    path: &Structpath,
+    schema: &Value,
+) -> Result<(), StructpathError> {
+    check(path, 0, schema)
+}
+
+fn check(
+    path: &Structpath,
+    pos: usize,
+    schema: &Value,
+) -> Result<(), StructpathError> {
+    let Some(segment) = path.segments().get(pos) else {
+        return Ok(());
+    };
+    if schema == "any" {
+        return Ok(());
+    }
+
+    let children: Vec<&Value> = match segment {
+        Segment::Key(key) => {
+            let key = match key {
+                SegmentKey::String(s) => s.clone(),
+                SegmentKey::Int(i) => i.to_string(),
+            };
+            match schema {
+                Value::Object(map) if map.contains_key(&key) => {
+                    vec![&map[&key]]
+                }
+                _ if schema == "object" => return Ok(()),
+                _ => {
+                    let found = format!("the key {}", key);
+                    return Err(not_permitted(path, pos, schema, found));
+                }
+            }
+        }
+        Segment::KeyVariable(_)
+        | Segment::NthKeyVariable(_, _)
+        | Segment::Wildcard
+        | Segment::KeyPattern(_) => match schema {
+            Value::Object(map) => map
+                .iter()
+                .filter(|(key, _)| match segment {
+                    Segment::KeyPattern(pattern) => {
+                        pattern_matches(pattern, key)
+                    }
+                    _ => true,
+                })
+                .map(|(_, child)| child)
+                .collect(),
+            _ if schema == "object" => return Ok(()),
+            _ => {
+                return Err(not_permitted(
+                    path,
+                    pos,
+                    schema,
+                    segment.to_string(),
+                ))
+            }
+        },
+        Segment::Index(_)
+        | Segment::FindValue(_)
+        | Segment::IndexVariable(_)
+        | Segment::IndexWildcard => match schema {
+            Value::Array(items) => match items.first() {
+                Some(item) => vec![item],
+                // An empty array says nothing about its elements
+                None => return Ok(()),
+            },
+            _ if schema == "array" => return Ok(()),
+            _ => {
+                return Err(not_permitted(
+                    path,
+                    pos,
+                    schema,
+                    segment.to_string(),
+                ))
+            }
+        },
+        Segment::Length => {
+            if schema.is_object()
+                || schema.is_array()
+                || schema == "object"
+                || schema == "array"
+            {
+                return Ok(());
+            }
+            return Err(not_permitted(path, pos, schema, "length".into()));
+        }
+        Segment::Parent => return Err(StructpathError::UnnormalizedPath),
+    };
+
+    // Report the failure of the first candidate if none of them fits
+    let mut first_err = None;
+    for child in children {
+        match check(path, pos + 1, child) {
+            Ok(()) => return Ok(()),
+            Err(err) => {
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+    Err(first_err.unwrap_or_else(|| {
+        not_permitted(path, pos, schema, segment.to_string())
+    }))
+}
+
+fn not_permitted(
+    path: &Structpath,
+    pos: usize,
+    schema: &Value,
+    found: String,
+) -> StructpathError {
+    let expected = match schema {
+        Value::Object(map) => {
+            let keys: Vec<&str> = map.keys().map(String::as_str).collect();
+            format!("one of the keys [{}]", keys.join(", "))
+        }
+        Value::Array(_) => "an index".to_string(),
+        Value::String(name) => format!("nothing below a {}", name),
+        other => format!("nothing below the value {}", other),
+    };
+    StructpathError::InvalidPath {
+        expected: format!("{} at {}", expected, path.head(pos)),
+        found,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "users": [{
+                "name": "string",
+                "age": "number",
+                "tags": ["string"],
+                "address": {"city": "string", "zip": "string"},
+                "extra": "object",
+            }],
+            "settings": "any",
+            "counts": {"a": 0, "b": 0},
+        })
+    }
+
+    #[test]
+    fn test_valid_paths() {
+        let valid = [
+            "$",
+            "$users",
+            "$users[0].name",
+            "$users[#i].tags[*]",
+            "$users[=null].address.zip",
+            "$users[0].extra.anything",
+            "$settings.deep[3].x",
+            "$counts.#k",
+            "$counts.*",
+            "$users[0].address.#field",
+            "$users.length",
+            "$users[0].tags[#]",
+            "$users[0].*.city",
+            "$users[0].addr*.city",
+        ];
+        for path_str in valid {
+            let path = parse(path_str).unwrap();
+            assert!(
+                validate_against_schema(&path, &schema()).is_ok(),
+                "{}",
+                path_str
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_paths() {
+        let path = parse("$users[0].adress.city").unwrap();
+        match validate_against_schema(&path, &schema()) {
+            Err(StructpathError::InvalidPath { expected, found }) => {
+                assert_eq!(found, "the key adress");
+                assert!(expected.ends_with("at $users[0]"), "{}", expected);
+                assert!(expected.contains("address"), "{}", expected);
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let invalid = [
+            "$users.name",
+            "$users[0].name.first",
+            "$users[0].tags.first",
+            "$counts[0]",
+            "$counts.a.b",
+            "$users[0].name.length",
+            "$users[0].a*.country",
+            "$users[0].x*.city",
+        ];
+        for path_str in invalid {
+            let path = parse(path_str).unwrap();
+            assert!(
+                matches!(
+                    validate_against_schema(&path, &schema()),
+                    Err(StructpathError::InvalidPath { .. })
+                ),
+                "{}",
+                path_str
+            );
+        }
+
+        let mut path = parse("$users[0]").unwrap();
+        path.push_parent();
+        assert!(matches!(
+            validate_against_schema(&path, &schema()),
+            Err(StructpathError::UnnormalizedPath)
+        ));
+    }
+}
@@ -1,9 +1,99 @@
-use pyo3::exceptions::{PyTypeError, PyValueError};
+use crate::types::{Segment, SegmentKey, Structpath};
+use pyo3::exceptions::{PyRuntimeError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyList, PyTuple};
+use pyo3::types::{PyByteArray, PyBytes, PyDict, PyList, PyLong, PyTuple};
 use serde_json::Value;
 
+/// A lossy conversion performed while serializing a Python object.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// Location of the affected value in the serialized document.
+    pub path: Structpath,
+    pub message: String,
+}
+
+/// Collects diagnostics along with the location currently being serialized.
+#[derive(Default)]
+struct Diagnostics {
+    location: Vec<Segment>,
+    entries: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    fn record(&mut self, message: String) {
+        let mut path = Structpath::new();
+        for segment in &self.location {
+            // Locations never contain variables, so this cannot fail
+            let _ = path.push_segment(segment.clone());
+        }
+        self.entries.push(Diagnostic { path, message });
+    }
+}
+
+/// What to do when two keys of a mapping become the same JSON key, as `1`
+/// and `"1"` do once integer keys are converted to strings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateKeyPolicy {
+    /// Raise a `ValueError`, so that no value is lost silently.
+    #[default]
+    Error,
+    /// Keep the value of the key iterated last.
+    LastWins,
+    /// Keep the value of the key iterated first.
+    FirstWins,
+}
+
+impl DuplicateKeyPolicy {
+    /// Looks up a policy by its Python name: "error", "last_wins" or
+    /// "first_wins".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "error" => Some(DuplicateKeyPolicy::Error),
+            "last_wins" => Some(DuplicateKeyPolicy::LastWins),
+            "first_wins" => Some(DuplicateKeyPolicy::FirstWins),
+            _ => None,
+        }
+    }
+}
+
+/// Options for converting Python objects to JSON values.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SerializeOptions {
+    /// Omit dict entries whose value is `None` instead of storing them as
+    /// null, at any depth. A `None` that is not a dict value, such as a list
+    /// item or the object itself, is still serialized as null.
+    pub skip_none: bool,
+    /// How to resolve keys that collide once converted to strings.
+    pub duplicate_keys: DuplicateKeyPolicy,
+}
+
 pub fn serialize(obj: &PyAny) -> PyResult<Value> {
+    serialize_with(obj, &SerializeOptions::default())
+}
+
+pub fn serialize_with(
+    obj: &PyAny,
+    options: &SerializeOptions,
+) -> PyResult<Value> {
+    serialize_inner(obj, options, &mut None)
+}
+
+/// Serializes like `serialize`, additionally reporting every lossy
+/// conversion that was made.
+pub fn serialize_with_diagnostics(
+    obj: &PyAny,
+) -> PyResult<(Value, Vec<Diagnostic>)> {
+    let mut diagnostics = Some(Diagnostics::default());
+    let value =
+        serialize_inner(obj, &SerializeOptions::default(), &mut diagnostics)?;
+    Ok((value, diagnostics.map(|d| d.entries).unwrap_or_default()))
+}
+
+fn serialize_inner(
+    obj: &PyAny,
+    options: &SerializeOptions,
+    diagnostics: &mut Option<Diagnostics>,
+) -> PyResult<Value> {
     if obj.is_none() {
         return Ok(Value::Null);
     }
@@ -16,10 +106,25 @@ pub fn serialize(obj: &PyAny) -> PyResult<Value> {
         return Ok(Value::Number(val.into()));
     }
 
+    // Unsigned ids above i64::MAX still fit a JSON number exactly
+    if let Ok(val) = obj.extract::<u64>() {
+        return Ok(Value::Number(val.into()));
+    }
+
     if let Ok(val) = obj.extract::<f64>() {
-        return Ok(serde_json::Number::from_f64(val)
-            .map(Value::Number)
-            .unwrap_or(Value::Null));
+        let number = serde_json::Number::from_f64(val);
+        if let Some(diagnostics) = diagnostics {
+            if obj.is_instance_of::<PyLong>() {
+                diagnostics.record(format!(
+                    "Integer {} exceeds the 64-bit range",
+                    obj
+                ));
+            } else if number.is_none() {
+                diagnostics
+                    .record(format!("Float {} was replaced by null", val));
+            }
+        }
+        return Ok(number.map(Value::Number).unwrap_or(Value::Null));
     }
 
     if let Ok(val) = obj.extract::<String>() {
@@ -27,26 +132,30 @@ pub fn serialize(obj: &PyAny) -> PyResult<Value> {
     }
 
     if let Ok(list) = obj.downcast::<PyList>() {
-        let mut values = Vec::new();
-        for item in list.iter() {
-            values.push(serialize(item)?);
+        let len = list.len();
+        let mut values = Vec::with_capacity(len);
+        for idx in 0..len {
+            let item = list.get_item(idx)?;
+            if let Some(diagnostics) = diagnostics {
+                diagnostics.location.push(Segment::Index(idx));
+            }
+            values.push(serialize_inner(item, options, diagnostics)?);
+            if let Some(diagnostics) = diagnostics {
+                diagnostics.location.pop();
+            }
+            check_size_unchanged("list", len, list.len())?;
         }
         return Ok(Value::Array(values));
     }
 
     if let Ok(dict) = obj.downcast::<PyDict>() {
+        let len = dict.len();
         let mut map = serde_json::Map::new();
         for (key, value) in dict.iter() {
-            let key_str = if let Ok(key_str) = key.extract::<String>() {
-                key_str
-            } else if let Ok(key_int) = key.extract::<i64>() {
-                key_int.to_string()
-            } else {
-                return Err(PyTypeError::new_err(
-                    "Dictionary keys must be strings or integers",
-                ));
-            };
-            map.insert(key_str, serialize(value)?);
+            insert_entry(&mut map, key, value, options, diagnostics)?;
+            // Checked before the iterator advances, which would panic on a
+            // resized dict
+            check_size_unchanged("dict", len, dict.len())?;
         }
         return Ok(Value::Object(map));
     }
@@ -90,19 +199,137 @@ pub fn serialize(obj: &PyAny) -> PyResult<Value> {
         return Ok(Value::Object(map));
     }
 
+    // Other objects following the mapping or sequence protocol, such as
+    // `types.MappingProxyType` or tuples. Bytes are not taken as a sequence
+    // of numbers.
+    if obj.hasattr("keys")? && obj.hasattr("__getitem__")? {
+        let mut map = serde_json::Map::new();
+        for key in obj.call_method0("keys")?.iter()? {
+            let key = key?;
+            let value = obj.get_item(key)?;
+            insert_entry(&mut map, key, value, options, diagnostics)?;
+        }
+        return Ok(Value::Object(map));
+    }
+
+    if obj.hasattr("__len__")?
+        && obj.hasattr("__getitem__")?
+        && !obj.is_instance_of::<PyBytes>()
+        && !obj.is_instance_of::<PyByteArray>()
+    {
+        let len = obj.len()?;
+        let mut values = Vec::with_capacity(len);
+        for idx in 0..len {
+            let item = obj.get_item(idx)?;
+            if let Some(diagnostics) = diagnostics {
+                diagnostics.location.push(Segment::Index(idx));
+            }
+            values.push(serialize_inner(item, options, diagnostics)?);
+            if let Some(diagnostics) = diagnostics {
+                diagnostics.location.pop();
+            }
+        }
+        return Ok(Value::Array(values));
+    }
+
     Err(PyTypeError::new_err(format!(
         "Invalid type {}",
         obj.get_type().name()?
     )))
 }
 
+/// Serializes one entry of a dict or other mapping into `map`. Keys must be
+/// strings or integers; integers are converted to strings.
+fn insert_entry(
+    map: &mut serde_json::Map<String, Value>,
+    key: &PyAny,
+    value: &PyAny,
+    options: &SerializeOptions,
+    diagnostics: &mut Option<Diagnostics>,
+) -> PyResult<()> {
+    let (key_str, segment_key) = if let Ok(key_str) = key.extract::<String>() {
+        (key_str.clone(), SegmentKey::String(key_str))
+    } else if let Ok(key_int) = key.extract::<i64>() {
+        (key_int.to_string(), SegmentKey::Int(key_int))
+    } else {
+        return Err(PyTypeError::new_err(
+            "Dictionary keys must be strings or integers",
+        ));
+    };
+    if options.skip_none && value.is_none() {
+        return Ok(());
+    }
+    if map.contains_key(&key_str) {
+        match options.duplicate_keys {
+            DuplicateKeyPolicy::Error => {
+                return Err(PyValueError::new_err(format!(
+                    "Duplicate key {} after converting keys to strings",
+                    key_str
+                )))
+            }
+            DuplicateKeyPolicy::FirstWins => return Ok(()),
+            DuplicateKeyPolicy::LastWins => {}
+        }
+    }
+    if let Some(diagnostics) = diagnostics {
+        let stringified = matches!(segment_key, SegmentKey::Int(_));
+        diagnostics.location.push(Segment::Key(segment_key));
+        if stringified {
+            diagnostics.record(format!(
+                "Integer key {} was converted to a string",
+                key
+            ));
+        }
+    }
+    map.insert(key_str, serialize_inner(value, options, diagnostics)?);
+    if let Some(diagnostics) = diagnostics {
+        diagnostics.location.pop();
+    }
+    Ok(())
+}
+
+/// Fails like CPython does when a container was resized while it was being
+/// iterated, e.g. by a custom `__index__` or `isoformat` of one of its items.
+fn check_size_unchanged(
+    kind: &str,
+    expected: usize,
+    actual: usize,
+) -> PyResult<()> {
+    if expected != actual {
+        return Err(PyRuntimeError::new_err(format!(
+            "{} changed size during serialization",
+            kind
+        )));
+    }
+    Ok(())
+}
+
+/// Options for building Python objects from JSON values.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeserializeOptions {
+    /// Insert dict keys in sorted order, at any depth, instead of the order
+    /// of the JSON map. That order depends on whether serde_json's
+    /// `preserve_order` feature is enabled anywhere in the build.
+    pub sort_keys: bool,
+}
+
 pub fn deserialize(value: &Value, py: Python) -> PyResult<PyObject> {
+    deserialize_with(value, py, &DeserializeOptions::default())
+}
+
+pub fn deserialize_with(
+    value: &Value,
+    py: Python,
+    options: &DeserializeOptions,
+) -> PyResult<PyObject> {
     match value {
         Value::Null => Ok(py.None()),
         Value::Bool(b) => Ok(b.to_object(py)),
         Value::Number(n) => {
             if let Some(i) = n.as_i64() {
                 Ok(i.to_object(py))
+            } else if let Some(u) = n.as_u64() {
+                Ok(u.to_object(py))
             } else if let Some(f) = n.as_f64() {
                 Ok(f.to_object(py))
             } else {
@@ -113,7 +340,7 @@ pub fn deserialize(value: &Value, py: Python) -> PyResult<PyObject> {
         Value::Array(a) => {
             let list = PyList::empty(py);
             for item in a {
-                list.append(deserialize(item, py)?)?;
+                list.append(deserialize_with(item, py, options)?)?;
             }
             Ok(list.to_object(py))
         }
@@ -154,9 +381,13 @@ pub fn deserialize(value: &Value, py: Python) -> PyResult<PyObject> {
                 }
             }
 
+            let mut entries: Vec<(&String, &Value)> = o.iter().collect();
+            if options.sort_keys {
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+            }
             let dict = PyDict::new(py);
-            for (key, value) in o {
-                dict.set_item(key, deserialize(value, py)?)?;
+            for (key, value) in entries {
+                dict.set_item(key, deserialize_with(value, py, options)?)?;
             }
             Ok(dict.to_object(py))
         }
@@ -0,0 +1,284 @@
+use std::borrow::Cow;
+use std::fmt;
+
+/// A tree-shaped value that paths can be resolved against.
+///
+/// `access::get` and the walker only need read access to objects and arrays,
+/// so any document model exposing these operations can be navigated.
+pub trait PathTarget: fmt::Debug + Sized {
+    /// The iterator over the entries of an object node.
+    type Entries<'a>: Iterator<Item = (Cow<'a, str>, &'a Self)>
+    where
+        Self: 'a;
+
+    /// Returns the entries of an object node with their keys as strings,
+    /// borrowed where the node stores them as strings, or `None` if this
+    /// node is not an object.
+    fn entries(&self) -> Option<Self::Entries<'_>>;
+
+    /// Returns the number of entries of an object node, or `None` if this
+    /// node is not an object.
+    fn object_len(&self) -> Option<usize> {
+        self.entries().map(Iterator::count)
+    }
+
+    /// Returns the elements of an array node, or `None` if this node is not
+    /// an array.
+    fn as_array(&self) -> Option<&[Self]>;
+
+    /// Looks up `key` in an object node.
+    fn get_key(&self, key: &str) -> Option<&Self>;
+
+    /// Looks up `idx` in an array node.
+    fn get_index(&self, idx: usize) -> Option<&Self> {
+        self.as_array().and_then(|arr| arr.get(idx))
+    }
+
+    /// Returns this node as a string usable as an object key, if it is a
+    /// scalar that can be one. Used to match `[key, value]` pairs when
+    /// `GetOptions::assoc_arrays` is set; the default matches nothing.
+    fn as_key(&self) -> Option<String> {
+        None
+    }
+
+    /// Returns true if this node equals the JSON scalar `value`. Used by
+    /// `[=value]` segments; the default matches nothing.
+    fn equals_json(&self, _value: &serde_json::Value) -> bool {
+        false
+    }
+
+    fn is_object(&self) -> bool {
+        self.entries().is_some()
+    }
+
+    fn is_array(&self) -> bool {
+        self.as_array().is_some()
+    }
+}
+
+type JsonEntry<'a> = (Cow<'a, str>, &'a serde_json::Value);
+
+fn json_entry<'a>(
+    (key, value): (&'a String, &'a serde_json::Value),
+) -> JsonEntry<'a> {
+    (Cow::Borrowed(key), value)
+}
+
+impl PathTarget for serde_json::Value {
+    type Entries<'a> = std::iter::Map<
+        serde_json::map::Iter<'a>,
+        fn((&'a String, &'a Self)) -> JsonEntry<'a>,
+    >;
+
+    fn entries(&self) -> Option<Self::Entries<'_>> {
+        self.as_object()
+            .map(|map| map.iter().map(json_entry as fn(_) -> _))
+    }
+
+    fn object_len(&self) -> Option<usize> {
+        self.as_object().map(serde_json::Map::len)
+    }
+
+    fn as_array(&self) -> Option<&[Self]> {
+        self.as_array().map(Vec::as_slice)
+    }
+
+    fn get_key(&self, key: &str) -> Option<&Self> {
+        self.as_object().and_then(|map| map.get(key))
+    }
+
+    fn as_key(&self) -> Option<String> {
+        match self {
+            serde_json::Value::String(s) => Some(s.clone()),
+            serde_json::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        }
+    }
+
+    fn equals_json(&self, value: &serde_json::Value) -> bool {
+        self == value
+    }
+
+    fn is_object(&self) -> bool {
+        self.is_object()
+    }
+
+    fn is_array(&self) -> bool {
+        self.is_array()
+    }
+}
+
+#[cfg(feature = "yaml")]
+type YamlEntry<'a> = (Cow<'a, str>, &'a serde_yaml::Value);
+
+/// Keys that have no natural string form (sequences, mappings) are not
+/// addressable by a path segment and are skipped.
+#[cfg(feature = "yaml")]
+fn yaml_entry<'a>(
+    (key, value): (&'a serde_yaml::Value, &'a serde_yaml::Value),
+) -> Option<YamlEntry<'a>> {
+    let key = match key {
+        serde_yaml::Value::String(s) => Cow::Borrowed(s.as_str()),
+        serde_yaml::Value::Number(n) => Cow::Owned(n.to_string()),
+        serde_yaml::Value::Bool(b) => Cow::Owned(b.to_string()),
+        _ => return None,
+    };
+    Some((key, value))
+}
+
+#[cfg(feature = "yaml")]
+impl PathTarget for serde_yaml::Value {
+    type Entries<'a> = std::iter::FilterMap<
+        serde_yaml::mapping::Iter<'a>,
+        fn((&'a Self, &'a Self)) -> Option<YamlEntry<'a>>,
+    >;
+
+    fn entries(&self) -> Option<Self::Entries<'_>> {
+        self.as_mapping()
+            .map(|map| map.iter().filter_map(yaml_entry as fn(_) -> _))
+    }
+
+    fn as_array(&self) -> Option<&[Self]> {
+        self.as_sequence().map(Vec::as_slice)
+    }
+
+    fn get_key(&self, key: &str) -> Option<&Self> {
+        let map = self.as_mapping()?;
+        if let Some(value) = map.get(key) {
+            return Some(value);
+        }
+
+        // YAML keeps numeric keys as numbers, so `$123` must also match an
+        // unquoted `123:` key.
+        key.parse::<i64>()
+            .ok()
+            .and_then(|int_key| map.get(serde_yaml::Value::from(int_key)))
+    }
+
+    fn as_key(&self) -> Option<String> {
+        match self {
+            serde_yaml::Value::String(s) => Some(s.clone()),
+            serde_yaml::Value::Number(n) => Some(n.to_string()),
+            serde_yaml::Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+
+    fn equals_json(&self, value: &serde_json::Value) -> bool {
+        serde_json::to_value(self).is_ok_and(|converted| converted == *value)
+    }
+
+    fn is_object(&self) -> bool {
+        self.is_mapping()
+    }
+
+    fn is_array(&self) -> bool {
+        self.is_sequence()
+    }
+}
+
+#[cfg(all(test, feature = "yaml"))]
+mod tests {
+    use super::PathTarget;
+    use crate::parse::parse;
+    use crate::types::{Structpath, StructpathError};
+    use serde_json::json;
+    use std::borrow::Cow;
+
+    fn yaml_fixture() -> serde_yaml::Value {
+        serde_yaml::from_str(
+            r#"
+a:
+  - b:
+      c: 42
+  - d: hello
+"e.f": escaped key
+123: integer key
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_get_yaml_matches_json() {
+        let yaml = yaml_fixture();
+        let json = json!({
+            "a": [{"b": {"c": 42}}, {"d": "hello"}],
+            "e.f": "escaped key",
+            "123": "integer key"
+        });
+
+        let path = parse("$a[0].b.c").unwrap();
+        assert_eq!(path.get(&json, None).unwrap(), &json!(42));
+        assert_eq!(
+            path.get(&yaml, None).unwrap(),
+            &serde_yaml::Value::from(42)
+        );
+
+        let path = parse(r"$e\.f").unwrap();
+        assert_eq!(
+            path.get(&yaml, None).unwrap(),
+            &serde_yaml::Value::from("escaped key")
+        );
+
+        let path = parse("$123").unwrap();
+        assert_eq!(
+            path.get(&yaml, None).unwrap(),
+            &serde_yaml::Value::from("integer key")
+        );
+    }
+
+    #[test]
+    fn test_get_yaml_errors() {
+        let yaml = yaml_fixture();
+
+        let path = parse("$a[1].x").unwrap();
+        assert!(matches!(
+            path.get(&yaml, None),
+            Err(StructpathError::NotFound)
+        ));
+
+        let path = parse("$a.b").unwrap();
+        assert!(matches!(
+            path.get(&yaml, None),
+            Err(StructpathError::InvalidPath { .. })
+        ));
+
+        let path = parse("$a[5]").unwrap();
+        assert!(matches!(
+            path.get(&yaml, None),
+            Err(StructpathError::IndexOutOfBounds(_))
+        ));
+    }
+
+    #[test]
+    fn test_yaml_entries() {
+        let yaml = yaml_fixture();
+
+        let keys: Vec<_> = yaml.entries().unwrap().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["a", "e.f", "123"]);
+        // Only keys that are not strings in the document are converted
+        assert!(matches!(keys[0], Cow::Borrowed(_)));
+        assert!(matches!(keys[2], Cow::Owned(_)));
+        assert_eq!(yaml.object_len(), Some(3));
+        assert_eq!(yaml["a"].object_len(), None);
+
+        let path = parse("$a[0].b").unwrap();
+        assert_eq!(path.child_count(&yaml, None).unwrap(), 1);
+        assert_eq!(parse("$a").unwrap().child_count(&yaml, None).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_walk_yaml() {
+        let yaml = yaml_fixture();
+        let paths: Vec<String> = Structpath::walk(&yaml)
+            .map(|(path, _)| format!("{}", path))
+            .collect();
+
+        assert!(paths.contains(&"$".to_string()));
+        assert!(paths.contains(&"$a[0].b.c".to_string()));
+        assert!(paths.contains(&"$a[1].d".to_string()));
+        assert!(paths.contains(&r"$e\.f".to_string()));
+        assert!(paths.contains(&"$123".to_string()));
+    }
+}
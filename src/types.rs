@@ -1,18 +1,154 @@
-use serde_json::Value;
-use std::collections::HashMap;
+use crate::access::{GetOptions, TraceStep};
+use crate::format::FormatOptions;
+use crate::parse::ParseOptions;
+use crate::target::PathTarget;
+use crate::vars::VarContext;
+use crate::walk::{NodeKind, Walker};
+use crate::write::{WriteOptions, WriteOutcome};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashSet;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 use thiserror::Error;
 
-#[derive(Debug, Clone, PartialEq)]
+/// Segments are ordered by kind first, in declaration order, so keys sort
+/// before indices. Indices and int keys compare numerically.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Segment {
     Key(SegmentKey),
     Index(usize),
+    /// The first element of an array equal to a JSON scalar, written
+    /// `[=5]`, `[="name"]`, `[=true]` or `[=null]`.
+    FindValue(Value),
     KeyVariable(String),
     IndexVariable(String),
+    /// A key variable bound to the `n`th key, counted from 0 in the map's
+    /// iteration order (sorted by key for `serde_json::Value`), among the
+    /// keys the rest of the path resolves under. Writes go to the key a
+    /// read would follow.
+    NthKeyVariable(String, usize),
+    Wildcard,
+    IndexWildcard,
+    /// A key matched as a glob: `?` is any single character and `*` any run
+    /// of characters. A backslash makes the next character literal.
+    KeyPattern(String),
+    Parent,
+    /// The length of an array or the number of keys of an object, written
+    /// `length` or `[#]`. Only `get_value` can resolve it, as the last
+    /// segment of the path.
+    Length,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// The kind of a segment, without its key, index or variable name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SegmentKind {
+    Key,
+    Index,
+    FindValue,
+    /// A key variable, including one bound to the `n`th key.
+    KeyVariable,
+    IndexVariable,
+    Wildcard,
+    IndexWildcard,
+    KeyPattern,
+    Parent,
+    Length,
+}
+
+impl Ord for Segment {
+    fn cmp(&self, other: &Self) -> Ordering {
+        use Segment::*;
+        match (self, other) {
+            (Key(a), Key(b)) => a.cmp(b),
+            (Index(a), Index(b)) => a.cmp(b),
+            // JSON values have no order of their own, so compare their text
+            (FindValue(a), FindValue(b)) => a.to_string().cmp(&b.to_string()),
+            (KeyVariable(a), KeyVariable(b))
+            | (IndexVariable(a), IndexVariable(b))
+            | (KeyPattern(a), KeyPattern(b)) => a.cmp(b),
+            (NthKeyVariable(a, n), NthKeyVariable(b, m)) => (a, n).cmp(&(b, m)),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for Segment {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Segment {
+    /// The position of the variant in the declaration, used to order
+    /// segments of different kinds.
+    fn rank(&self) -> u8 {
+        match self {
+            Segment::Key(_) => 0,
+            Segment::Index(_) => 1,
+            Segment::FindValue(_) => 2,
+            Segment::KeyVariable(_) => 3,
+            Segment::IndexVariable(_) => 4,
+            Segment::NthKeyVariable(_, _) => 5,
+            Segment::Wildcard => 6,
+            Segment::IndexWildcard => 7,
+            Segment::KeyPattern(_) => 8,
+            Segment::Parent => 9,
+            Segment::Length => 10,
+        }
+    }
+
+    pub fn kind(&self) -> SegmentKind {
+        match self {
+            Segment::Key(_) => SegmentKind::Key,
+            Segment::Index(_) => SegmentKind::Index,
+            Segment::FindValue(_) => SegmentKind::FindValue,
+            Segment::KeyVariable(_) | Segment::NthKeyVariable(_, _) => {
+                SegmentKind::KeyVariable
+            }
+            Segment::IndexVariable(_) => SegmentKind::IndexVariable,
+            Segment::Wildcard => SegmentKind::Wildcard,
+            Segment::IndexWildcard => SegmentKind::IndexWildcard,
+            Segment::KeyPattern(_) => SegmentKind::KeyPattern,
+            Segment::Parent => SegmentKind::Parent,
+            Segment::Length => SegmentKind::Length,
+        }
+    }
+
+    /// Returns the kind of position the segment stands for: `Key` for any
+    /// segment stepping into an object, `Index` for any stepping into an
+    /// array, and `Parent` or `Length` for the others.
+    fn shape(&self) -> SegmentKind {
+        match self {
+            Segment::Key(_)
+            | Segment::KeyVariable(_)
+            | Segment::NthKeyVariable(_, _)
+            | Segment::Wildcard
+            | Segment::KeyPattern(_) => SegmentKind::Key,
+            Segment::Index(_)
+            | Segment::FindValue(_)
+            | Segment::IndexVariable(_)
+            | Segment::IndexWildcard => SegmentKind::Index,
+            Segment::Parent => SegmentKind::Parent,
+            Segment::Length => SegmentKind::Length,
+        }
+    }
+
+    /// Returns the variable name if this segment is a variable.
+    pub(crate) fn variable_name(&self) -> Option<&str> {
+        match self {
+            Segment::KeyVariable(name)
+            | Segment::IndexVariable(name)
+            | Segment::NthKeyVariable(name, _) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum SegmentKey {
     String(String),
     Int(i64),
@@ -21,6 +157,8 @@ pub enum SegmentKey {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Structpath {
     segments: Vec<Segment>,
+    /// Names of the variables in `segments`, used only for membership
+    /// checks. Variable order is always taken from `segments`.
     variable_names: HashSet<String>,
 }
 
@@ -40,6 +178,18 @@ pub enum StructpathError {
     MissingVariable(String),
     #[error("Invalid variable value: expected number for index, got {0}")]
     InvalidVariableValue(String),
+    #[error("Path contains wildcards, which can only be resolved by get_all")]
+    UnexpectedWildcard,
+    #[error("Path contains parent segments and must be normalized first")]
+    UnnormalizedPath,
+    #[error("Parent segment at position {0} steps above the root")]
+    ParentAboveRoot(usize),
+    #[error("Path contains a length segment, which can only be resolved by get_value as the last segment")]
+    UnexpectedLength,
+    #[error("Path has index segments at positions {0:?}, but only object keys are allowed")]
+    IndexSegments(Vec<usize>),
+    #[error("Path contains the variable {0}, which must be resolved first")]
+    UnexpectedVariable(String),
 }
 
 impl Structpath {
@@ -50,6 +200,22 @@ impl Structpath {
         }
     }
 
+    /// The path of the document root, which has no segments and formats as
+    /// `$`. The same as `new`, for code that means the root itself.
+    pub fn root() -> Self {
+        Structpath::new()
+    }
+
+    /// The number of segments; 0 for the root.
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Returns true for the root path, which has no segments.
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
     pub fn push_string_key(&mut self, key: &str) {
         self.segments
             .push(Segment::Key(SegmentKey::String(key.to_string())));
@@ -85,33 +251,1099 @@ impl Structpath {
         Ok(())
     }
 
+    /// Pushes a key variable even if the path already has a variable of
+    /// that name. Every occurrence of a name stands for the same value: the
+    /// one given for it in the variable context of `get`, `write` and the
+    /// like, while `iter_variables` only yields results where all the
+    /// occurrences bind the same key or index, as in a join.
+    ///
+    /// Such a path does not parse back from its string form, since `parse`
+    /// refuses duplicate names.
+    pub fn push_key_variable_allow_dup(&mut self, name: &str) {
+        self.variable_names.insert(name.to_string());
+        self.segments.push(Segment::KeyVariable(name.to_string()));
+    }
+
+    /// Pushes an index variable even if the path already has a variable of
+    /// that name, like `push_key_variable_allow_dup`.
+    pub fn push_index_variable_allow_dup(&mut self, name: &str) {
+        self.variable_names.insert(name.to_string());
+        self.segments.push(Segment::IndexVariable(name.to_string()));
+    }
+
+    pub fn push_nth_key_variable(
+        &mut self,
+        name: &str,
+        n: usize,
+    ) -> Result<(), StructpathError> {
+        if !self.variable_names.insert(name.to_string()) {
+            return Err(StructpathError::DuplicateVariable(name.to_string()));
+        }
+        self.segments
+            .push(Segment::NthKeyVariable(name.to_string(), n));
+        Ok(())
+    }
+
+    pub fn push_wildcard(&mut self) {
+        self.segments.push(Segment::Wildcard);
+    }
+
+    pub fn push_index_wildcard(&mut self) {
+        self.segments.push(Segment::IndexWildcard);
+    }
+
+    pub fn push_key_pattern(&mut self, pattern: &str) {
+        self.segments.push(Segment::KeyPattern(pattern.to_string()));
+    }
+
+    pub fn push_parent(&mut self) {
+        self.segments.push(Segment::Parent);
+    }
+
+    pub fn push_length(&mut self) {
+        self.segments.push(Segment::Length);
+    }
+
+    pub fn push_find_value(&mut self, value: Value) {
+        self.segments.push(Segment::FindValue(value));
+    }
+
+    /// Appends an already constructed segment, registering its variable name.
+    pub(crate) fn push_segment(
+        &mut self,
+        segment: Segment,
+    ) -> Result<(), StructpathError> {
+        if let Some(name) = segment.variable_name() {
+            if !self.variable_names.insert(name.to_string()) {
+                return Err(StructpathError::DuplicateVariable(
+                    name.to_string(),
+                ));
+            }
+        }
+        self.segments.push(segment);
+        Ok(())
+    }
+
+    /// Appends an already constructed segment like `push_segment`, even if
+    /// it is a variable whose name the path already uses.
+    pub(crate) fn push_segment_allow_dup(&mut self, segment: Segment) {
+        if let Some(name) = segment.variable_name() {
+            self.variable_names.insert(name.to_string());
+        }
+        self.segments.push(segment);
+    }
+
+    /// Inserts `segment` before all others, for building a path from the
+    /// leaf up. Fails without changing the path if it is a variable whose
+    /// name is already used.
+    pub fn prepend(&mut self, segment: Segment) -> Result<(), StructpathError> {
+        if let Some(name) = segment.variable_name() {
+            if !self.variable_names.insert(name.to_string()) {
+                return Err(StructpathError::DuplicateVariable(
+                    name.to_string(),
+                ));
+            }
+        }
+        self.segments.insert(0, segment);
+        Ok(())
+    }
+
+    /// Replaces the segment at `index`, keeping variable names unique. On
+    /// error the path is left unchanged.
+    pub fn replace_segment(
+        &mut self,
+        index: usize,
+        segment: Segment,
+    ) -> Result<(), StructpathError> {
+        let len = self.segments.len();
+        let Some(old) = self.segments.get(index) else {
+            return Err(StructpathError::IndexOutOfBounds(format!(
+                "Segment {} out of bounds for path with {} segments",
+                index, len
+            )));
+        };
+
+        let old_name = old.variable_name().map(str::to_string);
+        if let Some(name) = segment.variable_name() {
+            if old_name.as_deref() != Some(name)
+                && self.variable_names.contains(name)
+            {
+                return Err(StructpathError::DuplicateVariable(
+                    name.to_string(),
+                ));
+            }
+        }
+
+        if let Some(name) = segment.variable_name() {
+            self.variable_names.insert(name.to_string());
+        }
+        self.segments[index] = segment;
+        if let Some(name) = old_name {
+            self.release_variable(&name);
+        }
+        Ok(())
+    }
+
+    /// Forgets the variable `name` unless a segment still uses it, which
+    /// happens when names were pushed with `push_key_variable_allow_dup`.
+    fn release_variable(&mut self, name: &str) {
+        let still_used = self
+            .segments
+            .iter()
+            .any(|segment| segment.variable_name() == Some(name));
+        if !still_used {
+            self.variable_names.remove(name);
+        }
+    }
+
+    /// Returns a template of this path with the segment at each position
+    /// of `variables` replaced by a variable of the name paired with it: a
+    /// key variable where the path steps into an object and an index
+    /// variable where it steps into an array. Fails on a position out of
+    /// bounds, a parent or length segment, or a duplicate variable name.
+    pub fn generalize(
+        &self,
+        variables: &[(usize, &str)],
+    ) -> Result<Structpath, StructpathError> {
+        let mut template = self.clone();
+        for &(position, name) in variables {
+            let shape = self.segments.get(position).map(Segment::shape);
+            let variable = match shape {
+                Some(SegmentKind::Index) => {
+                    Segment::IndexVariable(name.to_string())
+                }
+                Some(SegmentKind::Parent | SegmentKind::Length) => {
+                    return Err(StructpathError::InvalidPath {
+                        expected: "a key or index segment".to_string(),
+                        found: self.segments[position].to_string(),
+                    })
+                }
+                // Positions out of bounds are reported by replace_segment
+                _ => Segment::KeyVariable(name.to_string()),
+            };
+            template.replace_segment(position, variable)?;
+        }
+        Ok(template)
+    }
+
+    /// Builds a path from an environment-style key such as
+    /// `APP__SERVER__PORT`, with one string key per token between
+    /// separators. Empty tokens are skipped, and an empty `sep` keeps the
+    /// whole key as a single segment.
+    pub fn from_env_key(key: &str, sep: &str) -> Structpath {
+        let mut path = Structpath::new();
+        for token in env_key_tokens(key, sep) {
+            path.push_string_key(token);
+        }
+        path
+    }
+
+    /// Like `from_env_key`, but all-digit tokens become array indices, so
+    /// `SERVERS__0__HOST` addresses the first element of `SERVERS`.
+    pub fn from_env_key_indexed(key: &str, sep: &str) -> Structpath {
+        let mut path = Structpath::new();
+        for token in env_key_tokens(key, sep) {
+            let is_index = token.bytes().all(|b| b.is_ascii_digit());
+            match token.parse::<usize>() {
+                Ok(index) if is_index => path.push_index(index),
+                _ => path.push_string_key(token),
+            }
+        }
+        path
+    }
+
+    /// Shortens the path to its first `len` segments, releasing the names of
+    /// any variables that are dropped. Does nothing if the path is already
+    /// `len` segments or shorter.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.segments.len() {
+            return;
+        }
+        let dropped: Vec<Segment> = self.segments.drain(len..).collect();
+        for segment in dropped {
+            if let Some(name) = segment.variable_name() {
+                self.release_variable(name);
+            }
+        }
+    }
+
+    /// Parses `relative` like `parse`, where the `$` is optional, and
+    /// appends its segments, so `$a` extended with `"b.c[0]"` is
+    /// `$a.b.c[0]`. On a parse error or a variable name the path already
+    /// has, the path is left unchanged.
+    pub fn append_path_str(
+        &mut self,
+        relative: &str,
+    ) -> Result<(), StructpathError> {
+        let suffix = Structpath::parse(relative)?;
+        let mut joined = self.clone();
+        for segment in suffix.segments {
+            joined.push_segment(segment)?;
+        }
+        *self = joined;
+        Ok(())
+    }
+
+    /// Returns a copy of the path holding only its first `n` segments.
+    #[must_use]
+    pub fn head(&self, n: usize) -> Structpath {
+        let mut head = self.clone();
+        head.truncate(n);
+        head
+    }
+
+    /// Builds a path from segments, failing on duplicate variable names.
+    pub fn try_from_iter<I: IntoIterator<Item = Segment>>(
+        iter: I,
+    ) -> Result<Self, StructpathError> {
+        let mut path = Structpath::new();
+        for segment in iter {
+            path.push_segment(segment)?;
+        }
+        Ok(path)
+    }
+
+    /// Parses a path string. The `$` root marker is optional and a leading
+    /// `.` may stand in for it, so `$a.b`, `a.b` and `.a.b` are the same
+    /// path.
     pub fn parse(path_str: &str) -> Result<Self, StructpathError> {
         crate::parse::parse(path_str)
     }
 
-    pub fn get<'a>(
+    /// Parses a list of paths, one per line, as found in config files.
+    /// Blank lines and lines starting with `//` are skipped; `#` cannot mark
+    /// comments as it starts variables. An error comes with the 1-based
+    /// number of the line that failed.
+    pub fn parse_many(
+        input: &str,
+    ) -> Result<Vec<Self>, (usize, StructpathError)> {
+        crate::parse::parse_many(input)
+    }
+
+    /// Parses a path, reporting every problem in the input instead of only
+    /// the first one.
+    pub fn parse_collect(
+        path_str: &str,
+    ) -> Result<Self, Vec<crate::parse::ParseIssue>> {
+        crate::parse::parse_collect(path_str, &ParseOptions::default())
+    }
+
+    pub fn parse_with(
+        path_str: &str,
+        options: &ParseOptions,
+    ) -> Result<Self, StructpathError> {
+        crate::parse::parse_with(path_str, options)
+    }
+
+    /// Renders the path like `to_string`, but with the given options. Only
+    /// the default options are guaranteed to produce a string that parses
+    /// back to this path.
+    #[must_use]
+    pub fn to_string_with(&self, options: &FormatOptions) -> String {
+        crate::format::to_string_with(self, options)
+    }
+
+    /// Renders the path over several lines for logs: the root, then each
+    /// segment on its own line, indented one step per level. This is for
+    /// reading only and does not parse back.
+    #[must_use]
+    pub fn to_tree_string(&self) -> String {
+        crate::format::to_tree_string(self)
+    }
+
+    /// Renders the path in the dotted form analytics tools use, like
+    /// `users.0.name`: no `$`, indices as plain numbers and dots inside keys
+    /// replaced by `_`. This is lossy and does not parse back.
+    ///
+    /// Only keys and indices can be exported; other segments are errors.
+    pub fn to_dot_notation(&self) -> Result<String, StructpathError> {
+        crate::format::to_dot_notation(self)
+    }
+
+    /// Renders the path as a jq filter, like `.users[0].name`. Keys that are
+    /// not plain identifiers are quoted, as in `."weird.key"`, and wildcards
+    /// become `[]`.
+    ///
+    /// Variables, key patterns, value searches, parents and lengths have no
+    /// jq form here and are errors.
+    pub fn to_jq(&self) -> Result<String, StructpathError> {
+        crate::format::to_jq(self)
+    }
+
+    /// Encodes the path in a compact binary form that does not depend on
+    /// the string grammar. The encoding is stable: bytes stored by one
+    /// version decode to the same path in later ones.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        crate::binary::to_bytes(self)
+    }
+
+    /// Decodes a path written by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Structpath, StructpathError> {
+        crate::binary::from_bytes(bytes)
+    }
+
+    /// Collapses parent segments into the segments they step out of, so
+    /// `a.b..c` becomes `a.c`.
+    ///
+    /// A parent segment with nothing left to step out of is an error
+    /// (`ParentAboveRoot`) rather than being clamped at the root.
+    pub fn normalize(&self) -> Result<Structpath, StructpathError> {
+        let mut segments: Vec<Segment> = Vec::new();
+
+        for (position, segment) in self.segments.iter().enumerate() {
+            if let Segment::Parent = segment {
+                if segments.pop().is_none() {
+                    return Err(StructpathError::ParentAboveRoot(position));
+                }
+            } else {
+                segments.push(segment.clone());
+            }
+        }
+
+        let mut normalized = Structpath::new();
+        for segment in segments {
+            normalized.push_segment(segment)?;
+        }
+        Ok(normalized)
+    }
+
+    pub fn get<'a, T: PathTarget>(
+        &self,
+        data: &'a T,
+        vars: Option<&VarContext>,
+    ) -> Result<&'a T, StructpathError> {
+        crate::access::get(self, data, vars.map(VarContext::as_map))
+    }
+
+    pub fn get_with<'a, T: PathTarget>(
+        &self,
+        data: &'a T,
+        vars: Option<&VarContext>,
+        options: &GetOptions,
+    ) -> Result<&'a T, StructpathError> {
+        crate::access::get_with(
+            self,
+            data,
+            vars.map(VarContext::as_map),
+            options,
+        )
+    }
+
+    /// Resolves the path like `get`, also returning the JSON type name of
+    /// the value: "object", "array", "string", "number", "boolean" or
+    /// "null".
+    pub fn get_typed<'a>(
         &self,
         data: &'a Value,
-        vars: Option<&HashMap<String, String>>,
+        vars: Option<&VarContext>,
+    ) -> Result<(&'a Value, &'static str), StructpathError> {
+        let value = self.get(data, vars)?;
+        Ok((value, crate::access::json_type_name(value)))
+    }
+
+    /// Returns the value at the first of `candidates` that resolves. A path
+    /// that is missing from `data` or does not fit its shape is skipped;
+    /// any other error, such as a missing variable, is returned at once.
+    /// Fails with `NotFound` if no candidate resolves.
+    pub fn get_first<'a, T: PathTarget>(
+        candidates: &[Structpath],
+        data: &'a T,
+        vars: Option<&VarContext>,
+    ) -> Result<&'a T, StructpathError> {
+        for candidate in candidates {
+            match candidate.get(data, vars) {
+                Ok(value) => return Ok(value),
+                Err(
+                    StructpathError::NotFound
+                    | StructpathError::IndexOutOfBounds(_)
+                    | StructpathError::InvalidPath { .. },
+                ) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Err(StructpathError::NotFound)
+    }
+
+    /// Tells for each of `paths` whether it resolves in `data`, in the same
+    /// order. As in `get_first`, a path that is missing or does not fit the
+    /// shape of `data` gives false, and any other error is returned at once.
+    pub fn exists_many<T: PathTarget>(
+        paths: &[Structpath],
+        data: &T,
+        vars: Option<&VarContext>,
+    ) -> Result<Vec<bool>, StructpathError> {
+        paths
+            .iter()
+            .map(|path| match path.get(data, vars) {
+                Ok(_) => Ok(true),
+                Err(
+                    StructpathError::NotFound
+                    | StructpathError::IndexOutOfBounds(_)
+                    | StructpathError::InvalidPath { .. },
+                ) => Ok(false),
+                Err(err) => Err(err),
+            })
+            .collect()
+    }
+
+    /// Tells whether the shape of the path is compatible with `data`, as a
+    /// quick check before resolving it. Unlike `exists_many`, a path that is
+    /// only absent from `data` still fits; false means some segment meets
+    /// the wrong kind of node, such as an index applied to an object.
+    pub fn is_valid_for<T: PathTarget>(
+        &self,
+        data: &T,
+        vars: Option<&VarContext>,
+    ) -> bool {
+        crate::access::is_valid_for(self, data, vars.map(VarContext::as_map))
+    }
+
+    /// Resolves the path like `get`, but also accepts a final length
+    /// segment, whose value is computed rather than borrowed from `data`.
+    pub fn get_value<'a>(
+        &self,
+        data: &'a Value,
+        vars: Option<&VarContext>,
+    ) -> Result<Cow<'a, Value>, StructpathError> {
+        crate::access::get_value(self, data, vars.map(VarContext::as_map))
+    }
+
+    /// Resolves the path like `get_value`, with the given options. This is
+    /// the only lookup that honors `GetOptions::parse_embedded_json`.
+    pub fn get_value_with<'a>(
+        &self,
+        data: &'a Value,
+        vars: Option<&VarContext>,
+        options: &GetOptions,
+    ) -> Result<Cow<'a, Value>, StructpathError> {
+        crate::access::get_value_with(
+            self,
+            data,
+            vars.map(VarContext::as_map),
+            options,
+        )
+    }
+
+    /// Returns the number of keys of the object or elements of the array at
+    /// this path, like a final length segment, without copying the node.
+    /// A scalar is an `InvalidPath` error.
+    pub fn child_count<T: PathTarget>(
+        &self,
+        data: &T,
+        vars: Option<&VarContext>,
+    ) -> Result<usize, StructpathError> {
+        crate::access::child_count(self, data, vars.map(VarContext::as_map))
+    }
+
+    /// Resolves the path with a bare object as the root.
+    pub fn get_in_object<'a>(
+        &self,
+        map: &'a Map<String, Value>,
+        vars: Option<&VarContext>,
     ) -> Result<&'a Value, StructpathError> {
-        crate::access::get(self, data, vars)
+        crate::access::get_in_object(self, map, vars.map(VarContext::as_map))
+    }
+
+    /// Resolves the path with a bare array as the root.
+    pub fn get_in_array<'a>(
+        &self,
+        arr: &'a [Value],
+        vars: Option<&VarContext>,
+    ) -> Result<&'a Value, StructpathError> {
+        crate::access::get_in_array(self, arr, vars.map(VarContext::as_map))
+    }
+
+    /// Returns every value matched by the path, expanding wildcards. Parts
+    /// that cannot be resolved, unbound variables included, match nothing;
+    /// use `try_get_all` to have those reported as errors.
+    pub fn get_all<'a, T: PathTarget>(
+        &self,
+        data: &'a T,
+        vars: Option<&VarContext>,
+    ) -> Vec<&'a T> {
+        crate::access::get_all_entries(self, data, vars.map(VarContext::as_map))
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    /// Returns every value matched by the path like `get_all`, telling
+    /// "no matches" apart from "cannot be evaluated". A path that matches
+    /// nothing in `data` gives `Ok` with an empty vec, while a variable
+    /// missing from `vars` is a `MissingVariable` error, an index variable
+    /// that is not a number an `InvalidVariableValue` error, and a parent
+    /// or length segment an `UnnormalizedPath` or `UnexpectedLength` error.
+    /// `get_all` returns an empty vec in all of these cases.
+    pub fn try_get_all<'a, T: PathTarget>(
+        &self,
+        data: &'a T,
+        vars: Option<&VarContext>,
+    ) -> Result<Vec<&'a T>, StructpathError> {
+        let entries = crate::access::try_get_all_entries(
+            self,
+            data,
+            vars.map(VarContext::as_map),
+        )?;
+        Ok(entries.into_iter().map(|(_, value)| value).collect())
+    }
+
+    /// Like `get_all`, but pairs each value with the key (or index, as a
+    /// string) it was found under.
+    pub fn get_all_entries<'a, T: PathTarget>(
+        &self,
+        data: &'a T,
+        vars: Option<&VarContext>,
+    ) -> Vec<(String, &'a T)> {
+        crate::access::get_all_entries(self, data, vars.map(VarContext::as_map))
     }
 
+    /// Writes `value` at this path and returns the whole document. Given
+    /// data is updated in place and copied for the result.
     pub fn write(
         &self,
         data: Option<&mut Value>,
         value: Value,
-        vars: Option<&HashMap<String, String>>,
+        vars: Option<&VarContext>,
     ) -> Result<Value, StructpathError> {
-        crate::write::write(self, data, value, vars)
+        crate::write::write(self, data, value, vars.map(VarContext::as_map))
+    }
+
+    /// Writes like `write`, also returning the concrete paths of the nodes
+    /// that did not exist before and were created on the way, outermost
+    /// first. A node that existed as null or as a scalar replaced by a
+    /// container is not listed.
+    pub fn write_audited(
+        &self,
+        data: Option<&mut Value>,
+        value: Value,
+        vars: Option<&VarContext>,
+    ) -> Result<(Value, Vec<Structpath>), StructpathError> {
+        crate::write::write_audited(
+            self,
+            data,
+            value,
+            vars.map(VarContext::as_map),
+        )
+    }
+
+    /// Writes `value` at this path inside `data`, like `write`, but without
+    /// copying the document: only the part that changes is staged, so the
+    /// cost depends on the path rather than the size of `data`. An error
+    /// leaves `data` untouched.
+    pub fn write_in_place(
+        &self,
+        data: &mut Value,
+        value: Value,
+        vars: Option<&VarContext>,
+    ) -> Result<(), StructpathError> {
+        crate::write::write_in_place(
+            self,
+            data,
+            value,
+            vars.map(VarContext::as_map),
+        )
+    }
+
+    /// Writes like `write`, reporting a scalar root that had to be replaced
+    /// by a container, or refusing to replace it with
+    /// `WriteOptions::strict_root`. Given data is only updated in place,
+    /// without the copy `write` returns.
+    pub fn write_with(
+        &self,
+        data: Option<&mut Value>,
+        value: Value,
+        vars: Option<&VarContext>,
+        options: &WriteOptions,
+    ) -> Result<WriteOutcome, StructpathError> {
+        crate::write::write_with(
+            self,
+            data,
+            value,
+            vars.map(VarContext::as_map),
+            options,
+        )
+    }
+
+    /// Removes the node at this path, returning whether anything was
+    /// removed. This is what makes a key absent: `write` with `Value::Null`
+    /// keeps the key, present with a null value. Array elements after a
+    /// removed index move down by one.
+    pub fn unset(
+        &self,
+        data: &mut Value,
+        vars: Option<&VarContext>,
+    ) -> Result<bool, StructpathError> {
+        crate::write::unset(self, data, vars.map(VarContext::as_map))
+    }
+
+    /// Returns the node at this path mutably, for changing it in place.
+    /// Errors are the same as for `get`.
+    pub fn get_mut<'a>(
+        &self,
+        data: &'a mut Value,
+        vars: Option<&VarContext>,
+    ) -> Result<&'a mut Value, StructpathError> {
+        crate::write::get_mut(self, data, vars.map(VarContext::as_map))
+    }
+
+    /// Returns the node at this path mutably, first writing `default()`
+    /// there (creating containers on the way) if it is missing, like
+    /// `HashMap::entry(..).or_insert_with`. `default` is only called when
+    /// the node is missing.
+    pub fn get_mut_or_insert_with<'a>(
+        &self,
+        data: &'a mut Value,
+        default: impl FnOnce() -> Value,
+        vars: Option<&VarContext>,
+    ) -> Result<&'a mut Value, StructpathError> {
+        crate::write::get_mut_or_insert_with(
+            self,
+            data,
+            default,
+            vars.map(VarContext::as_map),
+        )
+    }
+
+    /// Resolves the path like `get`, also returning a step per segment tried
+    /// that records whether it resolved and the type of value it met, so a
+    /// miss shows exactly which segment failed.
+    pub fn trace_get<'a>(
+        &self,
+        data: &'a Value,
+        vars: Option<&VarContext>,
+    ) -> (Result<&'a Value, StructpathError>, Vec<TraceStep>) {
+        crate::access::trace_get(self, data, vars.map(VarContext::as_map))
+    }
+
+    /// Resolves the path like `get` and returns the concrete path of the
+    /// node found, with variables replaced by the keys and indices they
+    /// stood for.
+    pub fn resolved_path<T: PathTarget>(
+        &self,
+        data: &T,
+        vars: Option<&VarContext>,
+    ) -> Result<Structpath, StructpathError> {
+        crate::access::resolved_path(self, data, vars.map(VarContext::as_map))
+    }
+
+    /// Resolves the path like `get` and returns the index of the array
+    /// element its last segment selects, as found by a `[=value]` search.
+    pub fn index_of<T: PathTarget>(
+        &self,
+        data: &T,
+        vars: Option<&VarContext>,
+    ) -> Result<usize, StructpathError> {
+        match self.resolved_path(data, vars)?.segments.last() {
+            Some(Segment::Index(idx)) => Ok(*idx),
+            _ => Err(StructpathError::InvalidPath {
+                expected: "a path ending in an array element".to_string(),
+                found: self.to_string(),
+            }),
+        }
+    }
+
+    /// Returns an id for the location the path resolves to in `data`,
+    /// independent of the value stored there: paths reaching the same node,
+    /// with or without variables, get the same id. Ids are a hash of the
+    /// resolved path and are stable for a given build of this library.
+    pub fn resolved_location_id<T: PathTarget>(
+        &self,
+        data: &T,
+        vars: Option<&VarContext>,
+    ) -> Result<u64, StructpathError> {
+        let mut hasher = DefaultHasher::new();
+        self.resolved_path(data, vars)?.hash(&mut hasher);
+        Ok(hasher.finish())
+    }
+
+    /// Returns a numeric key for the path, a hash of its segments with
+    /// variable names included. Equal paths get the same fingerprint, and
+    /// it is cheaper than hashing the formatted string. Like
+    /// `resolved_location_id`, it is stable for a given build of this
+    /// library but should not be stored.
+    pub fn segment_fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.segments.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Applies an RFC 7386 JSON Merge Patch to a whole document. Unlike
+    /// `write`, this is not tied to a path: a null member of `patch` deletes
+    /// the key, nested objects merge and anything else replaces.
+    pub fn apply_merge_patch(data: &mut Value, patch: &Value) {
+        crate::write::apply_merge_patch(data, patch)
+    }
+
+    /// Iterates over every value the path resolves to, together with the
+    /// variable bindings that reach it.
+    pub fn iter_variables<'a>(
+        &'a self,
+        data: &'a Value,
+    ) -> crate::iter::VariableIterator<'a> {
+        crate::iter::iter_variables(self, data)
+    }
+
+    /// Lazily finds every node this path matches in `data`, expanding
+    /// variables, wildcards and key patterns, and yields each with its
+    /// concrete path.
+    pub fn iter_matches<'a>(
+        &self,
+        data: &'a Value,
+    ) -> crate::iter::MatchIterator<'a> {
+        crate::iter::iter_matches(self, data)
+    }
+
+    /// Like `iter_variables`, but the variables bound in `fixed` keep their
+    /// value and only the others are iterated.
+    pub fn iter_variables_with<'a>(
+        &'a self,
+        data: &'a Value,
+        fixed: &VarContext,
+    ) -> crate::iter::VariableIterator<'a> {
+        crate::iter::iter_variables_with(self, data, fixed.as_map())
+    }
+
+    /// Checks that the path only steps through objects, failing with the
+    /// positions of any index, value search, index variable or index
+    /// wildcard segments.
+    pub fn require_object_path(&self) -> Result<(), StructpathError> {
+        let positions: Vec<usize> = self
+            .segments
+            .iter()
+            .enumerate()
+            .filter(|(_, segment)| {
+                matches!(
+                    segment,
+                    Segment::Index(_)
+                        | Segment::FindValue(_)
+                        | Segment::IndexVariable(_)
+                        | Segment::IndexWildcard
+                )
+            })
+            .map(|(position, _)| position)
+            .collect();
+
+        if positions.is_empty() {
+            Ok(())
+        } else {
+            Err(StructpathError::IndexSegments(positions))
+        }
+    }
+
+    /// Returns true if the path uses a variable called `name`.
+    pub fn contains_variable(&self, name: &str) -> bool {
+        self.variable_names.contains(name)
     }
 
     pub fn segments(&self) -> &[Segment] {
         &self.segments
     }
 
-    pub fn walk(data: &Value) -> impl Iterator<Item = (Structpath, &Value)> {
+    /// Returns a copy of the segments, leaving the path in place.
+    pub fn to_segments(&self) -> Vec<Segment> {
+        self.segments.clone()
+    }
+
+    /// Takes the segments out of the path without copying them.
+    pub fn into_segments(self) -> Vec<Segment> {
+        self.segments
+    }
+
+    /// Returns the kind of the segment at position `i`, or `None` if the
+    /// path is shorter.
+    pub fn kind_at(&self, i: usize) -> Option<SegmentKind> {
+        self.segments.get(i).map(Segment::kind)
+    }
+
+    /// Returns true if the segment at position `i` is a variable.
+    pub fn is_variable_at(&self, i: usize) -> bool {
+        self.segments
+            .get(i)
+            .is_some_and(|segment| segment.variable_name().is_some())
+    }
+
+    /// Returns the segments from last to first, e.g. for printing a path
+    /// backwards. The result is not meant to be resolved as a path.
+    #[must_use]
+    pub fn rev_segments(&self) -> Vec<&Segment> {
+        self.segments.iter().rev().collect()
+    }
+
+    /// Walks the subtree at this path. Yielded paths start with this path,
+    /// and the subtree root itself is yielded with this path, last as in
+    /// `walk`.
+    pub fn walk_from<'a, T: PathTarget>(
+        &self,
+        data: &'a T,
+    ) -> Result<Walker<'a, T>, StructpathError> {
+        let subtree = self.get(data, None)?;
+        Ok(crate::walk::new_walker_from(self, subtree))
+    }
+
+    /// Returns true if the trailing segments of this path equal the segments
+    /// of `suffix`. Variables match by kind and name.
+    ///
+    /// The `$` of a parsed suffix does not anchor it at the root: `$name` is
+    /// simply the one-segment path `name`, and matches any path whose last
+    /// segment is the key `name`. An empty suffix matches every path.
+    pub fn ends_with(&self, suffix: &Structpath) -> bool {
+        self.segments.ends_with(&suffix.segments)
+    }
+
+    /// Checks that the path can be followed through the skeleton document
+    /// `schema`, without any real data, e.g. to catch misspelled keys.
+    ///
+    /// Objects in the skeleton list the keys that are allowed, and an array
+    /// holds the skeleton of its elements as its first item. The strings
+    /// `"object"`, `"array"` and `"any"` stand for an object with any keys,
+    /// an array of anything and any value at all. Every other value is a
+    /// leaf, e.g. a type name like `"string"`, which no segment can step
+    /// into. A sample document therefore works as a skeleton too.
+    ///
+    /// Variables, wildcards and key patterns stand for every key of the
+    /// skeleton object they match, and the path is accepted if the rest of
+    /// it fits below any of them. Fails with `InvalidPath` at the first
+    /// segment the skeleton does not permit.
+    pub fn validate_against_schema(
+        &self,
+        schema: &Value,
+    ) -> Result<(), StructpathError> {
+        crate::schema::validate_against_schema(self, schema)
+    }
+
+    /// Returns true if both paths step through the same sequence of objects
+    /// and arrays, whatever the keys and indices. Variables, wildcards and
+    /// value searches count as the key or index they stand for, so `$a[0].b`,
+    /// `$x[3].y` and `$a[#i].#k` all have the same shape.
+    pub fn same_shape(&self, other: &Structpath) -> bool {
+        self.segments.len() == other.segments.len()
+            && self
+                .segments
+                .iter()
+                .zip(&other.segments)
+                .all(|(a, b)| a.shape() == b.shape())
+    }
+
+    /// Returns true if the leading segments of this path equal the segments
+    /// of `prefix`. Variables match by kind and name, and every path starts
+    /// with the root path and with itself.
+    pub fn starts_with(&self, prefix: &Structpath) -> bool {
+        self.segments.starts_with(&prefix.segments)
+    }
+
+    /// Returns the rest of this path after `base`, for rebasing paths found
+    /// below `base` such as those yielded by `walk_from`: `$users[0].name`
+    /// relative to `$users` is `$[0].name`. Returns None if this path does
+    /// not start with `base`.
+    pub fn relative_to(&self, base: &Structpath) -> Option<Structpath> {
+        if !self.starts_with(base) {
+            return None;
+        }
+        let segments = self.segments[base.segments.len()..].to_vec();
+        let variable_names = segments
+            .iter()
+            .filter_map(Segment::variable_name)
+            .map(str::to_string)
+            .collect();
+        Some(Structpath {
+            segments,
+            variable_names,
+        })
+    }
+
+    /// Checks that no path in `paths` is a prefix of another, as needed to
+    /// dispatch on them unambiguously. A path given twice counts as its own
+    /// prefix. On a conflict, returns the (prefix, longer) pair from `paths`
+    /// that comes first in path order.
+    // The owned pair is large, but only built once, on a conflict
+    #[allow(clippy::result_large_err)]
+    pub fn check_prefix_free(
+        paths: &[Structpath],
+    ) -> Result<(), (Structpath, Structpath)> {
+        // In path order a prefix comes right before the paths extending it,
+        // or before another such path, so only neighbours need comparing.
+        let mut sorted: Vec<&Structpath> = paths.iter().collect();
+        sorted.sort();
+        match sorted.windows(2).find(|pair| pair[1].starts_with(pair[0])) {
+            Some(pair) => Err((pair[0].clone(), pair[1].clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Matches this path, used as a template, against the concrete path
+    /// `concrete` of the same length. Key and index variables match any key
+    /// or index and are bound to it; wildcards and key patterns match like
+    /// they do in `get_all`. Any other segment only matches an equal one.
+    /// A variable used more than once must be bound to the same value at
+    /// every occurrence. Returns the bindings, or `None` if the paths do
+    /// not match.
+    pub fn match_against(&self, concrete: &Structpath) -> Option<VarContext> {
+        if self.segments.len() != concrete.segments.len() {
+            return None;
+        }
+
+        let mut bindings = VarContext::new();
+        let mut bind = |name: &str, value: String| match bindings.get(name) {
+            Some(bound) => bound == value,
+            None => {
+                bindings.insert(name, value);
+                true
+            }
+        };
+        for (template, segment) in self.segments.iter().zip(&concrete.segments)
+        {
+            match (template, segment) {
+                (Segment::KeyVariable(name), Segment::Key(key)) => {
+                    if !bind(name, key_text(key)) {
+                        return None;
+                    }
+                }
+                (Segment::IndexVariable(name), Segment::Index(idx)) => {
+                    if !bind(name, idx.to_string()) {
+                        return None;
+                    }
+                }
+                (Segment::Key(a), Segment::Key(b)) => {
+                    if key_text(a) != key_text(b) {
+                        return None;
+                    }
+                }
+                (Segment::Wildcard, Segment::Key(_))
+                | (Segment::IndexWildcard, Segment::Index(_)) => {}
+                (Segment::KeyPattern(pattern), Segment::Key(key)) => {
+                    if !crate::access::pattern_matches(pattern, &key_text(key))
+                    {
+                        return None;
+                    }
+                }
+                _ if template == segment => {}
+                _ => return None,
+            }
+        }
+        Some(bindings)
+    }
+
+    /// Returns the first of `templates` that `concrete` matches, as decided
+    /// by `match_against`, together with its bindings.
+    pub fn first_match<'a>(
+        concrete: &Structpath,
+        templates: &'a [Structpath],
+    ) -> Option<(&'a Structpath, VarContext)> {
+        templates.iter().find_map(|template| {
+            template
+                .match_against(concrete)
+                .map(|bindings| (template, bindings))
+        })
+    }
+
+    /// Walks every node of `data` depth-first, yielding each node after its
+    /// descendants. The root comes last, with `Structpath::root()` as its
+    /// path, whose `len()` is 0.
+    pub fn walk<T: PathTarget>(data: &T) -> Walker<'_, T> {
+        crate::walk::new_walker(data)
+    }
+
+    /// Walks `data` like `walk`, also yielding the kind of each node, so that
+    /// consumers need not inspect the value again.
+    pub fn walk_with_kinds<T: PathTarget>(
+        data: &T,
+    ) -> impl Iterator<Item = (Structpath, &T, NodeKind)> {
         crate::walk::new_walker(data)
+            .map(|(path, value)| (path, value, NodeKind::of(value)))
+    }
+
+    /// Walks `data` like `walk`, but yields at most `max_nodes` nodes. Once
+    /// the walk has ended, `Walker::is_truncated` tells whether nodes were
+    /// left out because of the limit.
+    pub fn walk_limited<T: PathTarget>(
+        data: &T,
+        max_nodes: usize,
+    ) -> Walker<'_, T> {
+        crate::walk::new_walker_limited(data, max_nodes)
+    }
+
+    /// Walks `data` like `walk`, but returns the nodes sorted by path, so
+    /// the walks of two documents can be merge-joined. Each node comes
+    /// before its descendants.
+    pub fn walk_sorted<T: PathTarget>(data: &T) -> Vec<(Structpath, &T)> {
+        let mut nodes: Vec<_> = crate::walk::new_walker(data).collect();
+        nodes.sort_by(|(a, _), (b, _)| a.cmp(b));
+        nodes
+    }
+}
+
+fn env_key_tokens<'a>(
+    key: &'a str,
+    sep: &'a str,
+) -> impl Iterator<Item = &'a str> {
+    let tokens: Vec<&str> = if sep.is_empty() {
+        vec![key]
+    } else {
+        key.split(sep).collect()
+    };
+    tokens.into_iter().filter(|token| !token.is_empty())
+}
+
+/// Returns the key as it appears in an object, without escapes.
+fn key_text(key: &SegmentKey) -> String {
+    match key {
+        SegmentKey::String(s) => s.clone(),
+        SegmentKey::Int(i) => i.to_string(),
+    }
+}
+
+impl Eq for Structpath {}
+
+// Variable names are derived from the segments, so hashing the segments
+// alone agrees with `PartialEq`.
+impl Hash for Structpath {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.segments.hash(state);
+    }
+}
+
+/// Paths are ordered segment by segment, using the order of `Segment`; a
+/// path sorts before every longer path it is a prefix of.
+impl Ord for Structpath {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.segments.cmp(&other.segments)
+    }
+}
+
+impl PartialOrd for Structpath {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Renders the segment as it appears inside a path, e.g. `.a`, `[0]`,
+/// `.#id` or `[#i]`.
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut result = String::new();
+        crate::format::format_segment(
+            &mut result,
+            self,
+            None,
+            &mut false,
+            &FormatOptions::default(),
+        );
+        f.write_str(&result)
+    }
+}
+
+/// Renders the key as it appears inside a path, escaped but without a
+/// leading separator.
+impl fmt::Display for SegmentKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut result = String::new();
+        crate::format::format_key_segment(
+            &mut result,
+            self,
+            &mut true,
+            &FormatOptions::default(),
+        );
+        f.write_str(&result)
     }
 }
 
@@ -121,8 +1353,896 @@ impl fmt::Display for Structpath {
     }
 }
 
+impl FromStr for Structpath {
+    type Err = StructpathError;
+
+    fn from_str(path_str: &str) -> Result<Self, Self::Err> {
+        Structpath::parse(path_str)
+    }
+}
+
+impl TryFrom<&str> for Structpath {
+    type Error = StructpathError;
+
+    fn try_from(path_str: &str) -> Result<Self, Self::Error> {
+        Structpath::parse(path_str)
+    }
+}
+
+impl TryFrom<String> for Structpath {
+    type Error = StructpathError;
+
+    fn try_from(path_str: String) -> Result<Self, Self::Error> {
+        Structpath::parse(&path_str)
+    }
+}
+
+/// Collects segments into a path.
+///
+/// # Panics
+///
+/// Panics if a variable name occurs more than once, since `FromIterator`
+/// cannot report errors. Use `Structpath::try_from_iter` to handle this case.
+impl FromIterator<Segment> for Structpath {
+    fn from_iter<I: IntoIterator<Item = Segment>>(iter: I) -> Self {
+        match Structpath::try_from_iter(iter) {
+            Ok(path) => path,
+            Err(err) => panic!("{}", err),
+        }
+    }
+}
+
 impl Default for Structpath {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn navigation() -> ParseOptions {
+        ParseOptions { navigation: true }
+    }
+
+    #[test]
+    fn test_normalize_collapses_parent() {
+        let path = Structpath::parse_with("a.b..c", &navigation()).unwrap();
+        let normalized = path.normalize().unwrap();
+
+        assert_eq!(normalized, Structpath::parse("a.c").unwrap());
+        assert_eq!(format!("{}", normalized), "$a.c");
+    }
+
+    #[test]
+    fn test_normalize_frees_removed_variables() {
+        let path = Structpath::parse_with("a.#v..c", &navigation()).unwrap();
+        let mut normalized = path.normalize().unwrap();
+
+        assert!(normalized.push_key_variable("v").is_ok());
+    }
+
+    #[test]
+    fn test_normalize_parent_above_root() {
+        let path = Structpath::parse_with("a...b", &navigation()).unwrap();
+        let result = path.normalize();
+
+        assert!(matches!(result, Err(StructpathError::ParentAboveRoot(2))));
+    }
+
+    #[test]
+    fn test_ends_with() {
+        let path = Structpath::parse("$users[0].name").unwrap();
+
+        assert!(path.ends_with(&Structpath::parse("$name").unwrap()));
+        assert!(path.ends_with(&Structpath::parse("$[0].name").unwrap()));
+        assert!(path.ends_with(&path));
+        assert!(path.ends_with(&Structpath::new()));
+        assert!(!path.ends_with(&Structpath::parse("$email").unwrap()));
+        assert!(!path.ends_with(&Structpath::parse("$users").unwrap()));
+        assert!(
+            !path.ends_with(&Structpath::parse("$x.users[0].name").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_ends_with_variables() {
+        let path = Structpath::parse("$users.#id.name").unwrap();
+
+        assert!(path.ends_with(&Structpath::parse("$#id.name").unwrap()));
+        assert!(!path.ends_with(&Structpath::parse("$#other.name").unwrap()));
+        assert!(!path.ends_with(&Structpath::parse("$[#id].name").unwrap()));
+        assert!(!path.ends_with(&Structpath::parse(r"$\#id.name").unwrap()));
+    }
+
+    #[test]
+    fn test_starts_with() {
+        let path = Structpath::parse("$users[0].name").unwrap();
+
+        assert!(path.starts_with(&Structpath::parse("$users").unwrap()));
+        assert!(path.starts_with(&Structpath::parse("$users[0]").unwrap()));
+        assert!(path.starts_with(&path));
+        assert!(path.starts_with(&Structpath::new()));
+        assert!(!path.starts_with(&Structpath::parse("$name").unwrap()));
+        assert!(!path.starts_with(&Structpath::parse("$users[1]").unwrap()));
+    }
+
+    #[test]
+    fn test_check_prefix_free() {
+        let parse_all = |path_strs: &[&str]| -> Vec<Structpath> {
+            path_strs
+                .iter()
+                .map(|path_str| Structpath::parse(path_str).unwrap())
+                .collect()
+        };
+
+        let paths = parse_all(&["$users.#id", "$orders", "$users.list", "$a"]);
+        assert!(Structpath::check_prefix_free(&paths).is_ok());
+        assert!(Structpath::check_prefix_free(&[]).is_ok());
+
+        let paths = parse_all(&["$users.#id.name", "$orders", "$users.#id"]);
+        let (prefix, longer) =
+            Structpath::check_prefix_free(&paths).unwrap_err();
+        assert_eq!(prefix.to_string(), "$users.#id");
+        assert_eq!(longer.to_string(), "$users.#id.name");
+
+        // A prefix separated from its extension by another extension
+        let paths = parse_all(&["$a.c", "$a", "$b", "$a.b"]);
+        let (prefix, longer) =
+            Structpath::check_prefix_free(&paths).unwrap_err();
+        assert_eq!(
+            (prefix.to_string(), longer.to_string()),
+            ("$a".into(), "$a.b".into())
+        );
+
+        let paths = parse_all(&["$a.b", "$a.b"]);
+        assert!(Structpath::check_prefix_free(&paths).is_err());
+    }
+
+    #[test]
+    fn test_generalize() {
+        let path = Structpath::parse("$users[0].name").unwrap();
+
+        let template = path.generalize(&[(1, "i")]).unwrap();
+        assert_eq!(template.to_string(), "$users[#i].name");
+        assert!(template.contains_variable("i"));
+
+        let template = path.generalize(&[(0, "coll"), (1, "i")]).unwrap();
+        assert_eq!(template.to_string(), "$#coll[#i].name");
+        assert_eq!(path.generalize(&[]).unwrap(), path);
+
+        assert!(matches!(
+            path.generalize(&[(0, "x"), (2, "x")]),
+            Err(StructpathError::DuplicateVariable(name)) if name == "x"
+        ));
+        assert!(matches!(
+            path.generalize(&[(3, "x")]),
+            Err(StructpathError::IndexOutOfBounds(_))
+        ));
+        let length = Structpath::parse("$users.length").unwrap();
+        assert!(matches!(
+            length.generalize(&[(1, "x")]),
+            Err(StructpathError::InvalidPath { .. })
+        ));
+    }
+
+    #[test]
+    fn test_same_shape() {
+        let shape_of = |path_str: &str| Structpath::parse(path_str).unwrap();
+
+        let first = shape_of("$a[0].b");
+        for path_str in ["$x[3].y", "$a[#i].b", "$#k[*].*", "$a[=1].b?"] {
+            assert!(first.same_shape(&shape_of(path_str)), "{}", path_str);
+        }
+
+        for path_str in ["$a.0.b", "$a[0][1]", "$a[0]", "$a[0].b.c"] {
+            assert!(!first.same_shape(&shape_of(path_str)), "{}", path_str);
+        }
+        assert!(shape_of("$").same_shape(&Structpath::new()));
+    }
+
+    #[test]
+    fn test_match_against() {
+        let concrete = Structpath::parse("$users.alice.tags[2]").unwrap();
+
+        let template = Structpath::parse("$users.#id.tags[#i]").unwrap();
+        let bindings = template.match_against(&concrete).unwrap();
+        assert_eq!(
+            bindings,
+            VarContext::new().with("id", "alice").with("i", 2)
+        );
+
+        let matching =
+            ["$users.*.tags[*]", "$users.a?ice.tags[2]", "$*.#id.*[2]"];
+        for template_str in matching {
+            let template = Structpath::parse(template_str).unwrap();
+            assert!(
+                template.match_against(&concrete).is_some(),
+                "{}",
+                template_str
+            );
+        }
+
+        let not_matching = [
+            "$users.#id.tags",
+            "$users.#id.tags[#i].x",
+            "$users.bob.tags[#i]",
+            "$users.#id[#i].tags[2]",
+            "$users.#id.tags.#i",
+            "$users.b*.tags[2]",
+        ];
+        for template_str in not_matching {
+            let template = Structpath::parse(template_str).unwrap();
+            assert!(
+                template.match_against(&concrete).is_none(),
+                "{}",
+                template_str
+            );
+        }
+
+        // Int keys match their string form
+        let concrete = Structpath::parse("$codes.404").unwrap();
+        let mut template = Structpath::parse("$codes").unwrap();
+        template.push_string_key("404");
+        assert!(template.match_against(&concrete).is_some());
+
+        // A repeated variable binds the same value at every occurrence
+        let mut template = Structpath::parse("$a.#k").unwrap();
+        template.push_key_variable_allow_dup("k");
+        let concrete = Structpath::parse("$a.x.y").unwrap();
+        assert!(template.match_against(&concrete).is_none());
+        let concrete = Structpath::parse("$a.x.x").unwrap();
+        assert_eq!(
+            template.match_against(&concrete),
+            Some(VarContext::new().with("k", "x"))
+        );
+    }
+
+    #[test]
+    fn test_first_match() {
+        let templates: Vec<Structpath> = [
+            "$users.#id.name",
+            "$users.#id.orders[#n]",
+            "$users.#id.orders[*]",
+        ]
+        .iter()
+        .map(|template_str| Structpath::parse(template_str).unwrap())
+        .collect();
+
+        let concrete = Structpath::parse("$users.u7.orders[3]").unwrap();
+        let (template, bindings) =
+            Structpath::first_match(&concrete, &templates).unwrap();
+        assert!(std::ptr::eq(template, &templates[1]));
+        assert_eq!(bindings, VarContext::new().with("id", "u7").with("n", 3));
+
+        let concrete = Structpath::parse("$users.u7.email").unwrap();
+        assert!(Structpath::first_match(&concrete, &templates).is_none());
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let segments = vec![
+            Segment::Key(SegmentKey::String("users".to_string())),
+            Segment::IndexVariable("idx".to_string()),
+            Segment::Key(SegmentKey::Int(42)),
+            Segment::KeyVariable("name".to_string()),
+        ];
+
+        let path: Structpath = segments.into_iter().collect();
+
+        assert_eq!(format!("{}", path), "$users[#idx].42.#name");
+        assert_eq!(path, Structpath::parse("$users[#idx].42.#name").unwrap());
+    }
+
+    #[test]
+    fn test_into_segments_roundtrip() {
+        let path = Structpath::parse("$users[#idx].42.#name[=1].*").unwrap();
+
+        let segments = path.to_segments();
+        assert_eq!(segments, path.segments());
+
+        let rebuilt: Structpath =
+            path.clone().into_segments().into_iter().collect();
+        assert_eq!(rebuilt, path);
+        assert!(rebuilt.contains_variable("idx"));
+
+        let keys_only: Structpath = segments
+            .into_iter()
+            .filter(|segment| matches!(segment, Segment::Key(_)))
+            .collect();
+        assert_eq!(keys_only.to_string(), "$users.42");
+    }
+
+    #[test]
+    fn test_try_from_iter_duplicate_variable() {
+        let segments = vec![
+            Segment::KeyVariable("id".to_string()),
+            Segment::IndexVariable("id".to_string()),
+        ];
+
+        let result = Structpath::try_from_iter(segments);
+        assert!(matches!(result, Err(StructpathError::DuplicateVariable(_))));
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate variable name: id")]
+    fn test_from_iter_duplicate_variable_panics() {
+        let segments = vec![
+            Segment::KeyVariable("id".to_string()),
+            Segment::KeyVariable("id".to_string()),
+        ];
+
+        let _: Structpath = segments.into_iter().collect();
+    }
+
+    #[test]
+    fn test_contains_variable() {
+        let path = Structpath::parse("$a.#x.b").unwrap();
+
+        assert!(path.contains_variable("x"));
+        assert!(!path.contains_variable("y"));
+        assert!(!path.contains_variable("a"));
+    }
+
+    #[test]
+    fn test_replace_segment() {
+        let mut path = Structpath::parse("$a.b.#v.#w").unwrap();
+
+        path.replace_segment(1, Segment::Index(3)).unwrap();
+        assert_eq!(format!("{}", path), "$a[3].#v.#w");
+
+        path.replace_segment(2, Segment::IndexVariable("x".into()))
+            .unwrap();
+        assert_eq!(format!("{}", path), "$a[3][#x].#w");
+        assert!(!path.contains_variable("v"));
+        assert!(path.contains_variable("x"));
+
+        // Reusing the replaced segment's own name is fine
+        path.replace_segment(2, Segment::KeyVariable("x".into()))
+            .unwrap();
+        assert_eq!(format!("{}", path), "$a[3].#x.#w");
+
+        let before = path.clone();
+        assert!(matches!(
+            path.replace_segment(2, Segment::KeyVariable("w".into())),
+            Err(StructpathError::DuplicateVariable(name)) if name == "w"
+        ));
+        assert!(matches!(
+            path.replace_segment(4, Segment::Wildcard),
+            Err(StructpathError::IndexOutOfBounds(_))
+        ));
+        assert_eq!(path, before);
+
+        // The freed name can be pushed again
+        path.replace_segment(3, Segment::Wildcard).unwrap();
+        path.push_key_variable("w").unwrap();
+        assert_eq!(format!("{}", path), "$a[3].#x.*.#w");
+    }
+
+    #[test]
+    fn test_require_object_path() {
+        let path = Structpath::parse("$config.#env.*.database.host").unwrap();
+        assert!(path.require_object_path().is_ok());
+
+        let path = Structpath::parse("$servers[0].ports[#i].tags[*]").unwrap();
+        let err = path.require_object_path().unwrap_err();
+        assert!(matches!(
+            &err,
+            StructpathError::IndexSegments(positions) if positions == &[1, 3, 5]
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Path has index segments at positions [1, 3, 5], but only object keys are allowed"
+        );
+    }
+
+    #[test]
+    fn test_get_first() {
+        let data = serde_json::json!({"old": {"field": 1}, "flat": "x"});
+        let candidates = [
+            Structpath::parse("$new.field").unwrap(),
+            Structpath::parse("$flat.field").unwrap(),
+            Structpath::parse("$old.field").unwrap(),
+        ];
+
+        let value = Structpath::get_first(&candidates, &data, None).unwrap();
+        assert_eq!(value, &serde_json::json!(1));
+
+        assert!(matches!(
+            Structpath::get_first(&candidates[..2], &data, None),
+            Err(StructpathError::NotFound)
+        ));
+        assert!(matches!(
+            Structpath::get_first(&[], &data, None),
+            Err(StructpathError::NotFound)
+        ));
+
+        // Variable errors are not skipped
+        let candidates = [
+            Structpath::parse("$#version.field").unwrap(),
+            Structpath::parse("$old.field").unwrap(),
+        ];
+        let vars = VarContext::new();
+        assert!(matches!(
+            Structpath::get_first(&candidates, &data, Some(&vars)),
+            Err(StructpathError::MissingVariable(name)) if name == "version"
+        ));
+    }
+
+    #[test]
+    fn test_try_get_all() {
+        let data =
+            serde_json::json!({"users": [{"name": "Ann"}, {"name": "Bo"}]});
+
+        let path = Structpath::parse("$users[*].name").unwrap();
+        assert_eq!(
+            path.try_get_all(&data, None).unwrap(),
+            [&serde_json::json!("Ann"), &serde_json::json!("Bo")]
+        );
+
+        // Zero matches is not an error
+        let path = Structpath::parse("$users[*].email").unwrap();
+        assert!(path.try_get_all(&data, None).unwrap().is_empty());
+        let path = Structpath::parse("$groups.*").unwrap();
+        assert!(path.try_get_all(&data, None).unwrap().is_empty());
+
+        // An unbound variable is, where get_all only finds nothing
+        let path = Structpath::parse("$users[#i].*").unwrap();
+        assert!(path.get_all(&data, None).is_empty());
+        assert!(matches!(
+            path.try_get_all(&data, None),
+            Err(StructpathError::MissingVariable(name)) if name == "i"
+        ));
+        assert!(matches!(
+            path.try_get_all(&data, Some(&VarContext::new().with("i", "x"))),
+            Err(StructpathError::InvalidVariableValue(_))
+        ));
+        assert_eq!(
+            path.try_get_all(&data, Some(&VarContext::new().with("i", 1)))
+                .unwrap(),
+            [&serde_json::json!("Bo")]
+        );
+
+        let path = Structpath::parse("$users.length").unwrap();
+        assert!(matches!(
+            path.try_get_all(&data, None),
+            Err(StructpathError::UnexpectedLength)
+        ));
+    }
+
+    #[test]
+    fn test_segment_fingerprint() {
+        let path = Structpath::parse("$users[#i].#k{1}[=\"x\"].*").unwrap();
+        let same = Structpath::parse("users[#i].#k{1}[=\"x\"].*").unwrap();
+        assert_eq!(path.segment_fingerprint(), same.segment_fingerprint());
+
+        let mut int_key = Structpath::new();
+        int_key.push_int_key(1);
+        let mut string_key = Structpath::new();
+        string_key.push_string_key("1");
+        assert_ne!(int_key.to_string(), string_key.to_string());
+        assert_ne!(
+            int_key.segment_fingerprint(),
+            string_key.segment_fingerprint()
+        );
+
+        let renamed = Structpath::parse("$users[#j].#k{1}[=\"x\"].*").unwrap();
+        assert_ne!(path.segment_fingerprint(), renamed.segment_fingerprint());
+    }
+
+    #[test]
+    fn test_relative_to() {
+        let data = serde_json::json!({"users": [{"name": "Ann"}]});
+        let base = Structpath::parse("$users").unwrap();
+
+        let relative: Vec<Structpath> = base
+            .walk_from(&data)
+            .unwrap()
+            .map(|(path, _)| path.relative_to(&base).unwrap())
+            .collect();
+        let name = Structpath::parse("$[0].name").unwrap();
+        assert!(relative.contains(&name));
+        assert!(relative.contains(&Structpath::new()));
+        assert_eq!(name.get(&data["users"], None).unwrap(), "Ann");
+
+        let path = Structpath::parse("$users[#i].#k").unwrap();
+        assert_eq!(
+            path.relative_to(&base),
+            Some(Structpath::parse("$[#i].#k").unwrap())
+        );
+        assert_eq!(path.relative_to(&path), Some(Structpath::new()));
+        assert_eq!(path.relative_to(&Structpath::new()), Some(path.clone()));
+
+        // Not a prefix
+        for other in ["$user", "$users[0]", "$users[#j]", "$users[#i].#k.x"] {
+            let other = Structpath::parse(other).unwrap();
+            assert_eq!(path.relative_to(&other), None);
+        }
+    }
+
+    #[test]
+    fn test_get_typed() {
+        let data = serde_json::json!({
+            "o": {"k": 1},
+            "a": [true],
+            "s": "text",
+            "n": 1.5,
+            "b": false,
+            "z": null,
+        });
+        let cases = [
+            ("$o", "object"),
+            ("$a", "array"),
+            ("$s", "string"),
+            ("$n", "number"),
+            ("$o.k", "number"),
+            ("$b", "boolean"),
+            ("$a[0]", "boolean"),
+            ("$z", "null"),
+        ];
+        for (path_str, type_name) in cases {
+            let path = Structpath::parse(path_str).unwrap();
+            let (value, found) = path.get_typed(&data, None).unwrap();
+            assert_eq!(value, path.get(&data, None).unwrap());
+            assert_eq!(found, type_name, "{}", path_str);
+        }
+
+        let path = Structpath::parse("$missing").unwrap();
+        assert!(matches!(
+            path.get_typed(&data, None),
+            Err(StructpathError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_exists_many() {
+        let data = serde_json::json!({"name": "x", "tags": ["a"], "n": 1});
+        let paths: Vec<Structpath> = [
+            "$name",
+            "$email",
+            "$tags[0]",
+            "$tags[1]",
+            "$n.x",
+            "$tags[#i]",
+        ]
+        .iter()
+        .map(|path_str| Structpath::parse(path_str).unwrap())
+        .collect();
+
+        let vars = VarContext::new().with("i", 0);
+        assert_eq!(
+            Structpath::exists_many(&paths, &data, Some(&vars)).unwrap(),
+            [true, false, true, false, false, true]
+        );
+        assert!(Structpath::exists_many(&[], &data, None)
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            Structpath::exists_many(&paths, &data, Some(&VarContext::new())),
+            Err(StructpathError::MissingVariable(name)) if name == "i"
+        ));
+    }
+
+    #[test]
+    fn test_rev_segments() {
+        let path = Structpath::parse("$a[0].#v").unwrap();
+
+        assert_eq!(
+            path.rev_segments(),
+            vec![
+                &Segment::KeyVariable("v".into()),
+                &Segment::Index(0),
+                &Segment::Key(SegmentKey::String("a".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_equality_ignores_variable_history() {
+        // Same segments, but the variable names were registered in a
+        // different order and with different removals along the way
+        let mut first = Structpath::new();
+        first.push_key_variable("a").unwrap();
+        first.push_index_variable("b").unwrap();
+        first.push_key_variable("c").unwrap();
+
+        let mut second = Structpath::new();
+        second.push_key_variable("c").unwrap();
+        second.push_key_variable("x").unwrap();
+        second.push_key_variable("y").unwrap();
+        second
+            .replace_segment(0, Segment::KeyVariable("a".into()))
+            .unwrap();
+        second
+            .replace_segment(1, Segment::IndexVariable("b".into()))
+            .unwrap();
+        second.truncate(2);
+        second.push_key_variable("c").unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(first.to_string(), "$#a[#b].#c");
+    }
+
+    #[test]
+    fn test_ordering() {
+        let parse = |s| Structpath::parse(s).unwrap();
+
+        assert!(parse("$a") < parse("$a.b"));
+        assert!(parse("$a.b") < parse("$a[0]"));
+        assert!(parse("$a[2]") < parse("$a[10]"));
+        assert!(parse("$a.2") < parse("$a.10"));
+        assert_eq!(parse("$a.b").cmp(&parse("$a.b")), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_sort_indices_numerically() {
+        let paths = ["$a[10]", "$a[2]", "$a[1]"];
+
+        let mut sorted: Vec<Structpath> = paths
+            .iter()
+            .map(|s| Structpath::parse(s).unwrap())
+            .collect();
+        sorted.sort();
+        let sorted: Vec<String> =
+            sorted.iter().map(Structpath::to_string).collect();
+        assert_eq!(sorted, ["$a[1]", "$a[2]", "$a[10]"]);
+
+        // Sorting the strings puts 10 before 2
+        let mut strings = paths.to_vec();
+        strings.sort();
+        assert_eq!(strings, ["$a[10]", "$a[1]", "$a[2]"]);
+    }
+
+    #[test]
+    fn test_walk_sorted() {
+        let data = serde_json::json!({
+            "b": [1, {"c": 2}, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            "a": {"10": true, "2": false, "x": null},
+            "": {}
+        });
+
+        let nodes = Structpath::walk_sorted(&data);
+
+        assert_eq!(nodes.len(), Structpath::walk(&data).count());
+        assert_eq!(nodes[0].0, Structpath::new());
+        for pair in nodes.windows(2) {
+            assert!(pair[0].0 <= pair[1].0, "{} > {}", pair[0].0, pair[1].0);
+        }
+        let position = |path_str: &str| {
+            let path = Structpath::parse(path_str).unwrap();
+            nodes.iter().position(|(p, _)| *p == path).unwrap()
+        };
+        assert!(position("$b[2]") < position("$b[10]"));
+        assert!(position("$a.2") < position("$a.10"));
+    }
+
+    #[test]
+    fn test_kind_at() {
+        let path = Structpath::parse("$a[0].#id[#i].#n{1}").unwrap();
+
+        let kinds: Vec<_> = (0..6).map(|i| path.kind_at(i)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                Some(SegmentKind::Key),
+                Some(SegmentKind::Index),
+                Some(SegmentKind::KeyVariable),
+                Some(SegmentKind::IndexVariable),
+                Some(SegmentKind::KeyVariable),
+                None,
+            ]
+        );
+
+        let variables: Vec<_> =
+            (0..6).map(|i| path.is_variable_at(i)).collect();
+        assert_eq!(variables, vec![false, false, true, true, true, false]);
+    }
+
+    #[test]
+    fn test_prepend() {
+        let mut path = Structpath::new();
+        for key in ["c", "b", "a"] {
+            path.prepend(Segment::Key(SegmentKey::String(key.into())))
+                .unwrap();
+        }
+        assert_eq!(path, Structpath::parse("$a.b.c").unwrap());
+
+        path.prepend(Segment::KeyVariable("k".into())).unwrap();
+        assert_eq!(path.to_string(), "$#k.a.b.c");
+        assert!(path.contains_variable("k"));
+
+        assert!(matches!(
+            path.prepend(Segment::IndexVariable("k".into())),
+            Err(StructpathError::DuplicateVariable(name)) if name == "k"
+        ));
+        assert_eq!(path.to_string(), "$#k.a.b.c");
+    }
+
+    #[test]
+    fn test_resolved_location_id() {
+        let path = Structpath::parse("$users.#id.tags[#i]").unwrap();
+        let vars = VarContext::new().with("id", 7).with("i", 1);
+        let first = serde_json::json!({"users": {"7": {"tags": ["a", "b"]}}});
+        let second = serde_json::json!({
+            "users": {"7": {"tags": [1, 2, 3]}, "8": {}},
+            "other": true
+        });
+
+        let id = path.resolved_location_id(&first, Some(&vars)).unwrap();
+        assert_eq!(
+            path.resolved_location_id(&second, Some(&vars)).unwrap(),
+            id
+        );
+        assert_eq!(
+            path.resolved_path(&first, Some(&vars)).unwrap(),
+            Structpath::parse("$users.7.tags[1]").unwrap()
+        );
+
+        // The same location reached without variables
+        let literal = Structpath::parse(r"$users.\7.tags[1]").unwrap();
+        assert_eq!(literal.resolved_location_id(&first, None).unwrap(), id);
+
+        let mut other_vars = vars.clone();
+        other_vars.insert("i", 0);
+        assert_ne!(
+            path.resolved_location_id(&first, Some(&other_vars))
+                .unwrap(),
+            id
+        );
+
+        other_vars.insert("id", 9);
+        assert!(matches!(
+            path.resolved_location_id(&first, Some(&other_vars)),
+            Err(StructpathError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_conversions_from_strings() {
+        let expected = Structpath::parse("$a.b[0]").unwrap();
+
+        let path: Structpath = "$a.b[0]".try_into().unwrap();
+        assert_eq!(path, expected);
+
+        let path: Structpath = String::from("$a.b[0]").try_into().unwrap();
+        assert_eq!(path, expected);
+
+        assert_eq!("$a.b[0]".parse::<Structpath>().unwrap(), expected);
+
+        assert!(matches!(
+            Structpath::try_from("$a[x]"),
+            Err(StructpathError::ParseError(_))
+        ));
+        assert!(matches!(
+            Structpath::try_from(String::from("$#v.#v")),
+            Err(StructpathError::DuplicateVariable(name)) if name == "v"
+        ));
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut path = Structpath::parse("$a.#v.b.c").unwrap();
+
+        assert_eq!(format!("{}", path.head(2)), "$a.#v");
+        assert_eq!(format!("{}", path.head(10)), "$a.#v.b.c");
+
+        path.truncate(2);
+        assert_eq!(format!("{}", path), "$a.#v");
+        assert!(path.contains_variable("v"));
+
+        path.truncate(1);
+        assert_eq!(format!("{}", path), "$a");
+        assert!(!path.contains_variable("v"));
+        path.push_index_variable("v").unwrap();
+        assert_eq!(format!("{}", path), "$a[#v]");
+    }
+
+    #[test]
+    fn test_append_path_str() {
+        let mut path = Structpath::parse("$a").unwrap();
+        path.append_path_str("b.c[0]").unwrap();
+        assert_eq!(path.to_string(), "$a.b.c[0]");
+
+        path.append_path_str("[#i].#k").unwrap();
+        assert_eq!(path, Structpath::parse("$a.b.c[0][#i].#k").unwrap());
+
+        assert!(matches!(
+            path.append_path_str("x.#i"),
+            Err(StructpathError::DuplicateVariable(name)) if name == "i"
+        ));
+        assert!(path.append_path_str("x[").is_err());
+        assert_eq!(path.to_string(), "$a.b.c[0][#i].#k");
+    }
+
+    #[test]
+    fn test_push_variable_allow_dup() {
+        let mut path = Structpath::parse("$links.#id").unwrap();
+        assert!(path.push_key_variable("id").is_err());
+        path.push_key_variable_allow_dup("id");
+        assert_eq!(path.to_string(), "$links.#id.#id");
+
+        // Every occurrence takes the single value from the context
+        let data = serde_json::json!({"links": {"a": {"a": 1, "b": 2}}});
+        let vars = VarContext::new().with("id", "a");
+        assert_eq!(path.get(&data, Some(&vars)).unwrap(), &1);
+        let written = path
+            .write(Some(&mut serde_json::json!({})), 5.into(), Some(&vars))
+            .unwrap();
+        assert_eq!(written, serde_json::json!({"links": {"a": {"a": 5}}}));
+
+        // The name stays taken until its last occurrence is gone
+        path.truncate(2);
+        assert!(path.contains_variable("id"));
+        path.replace_segment(1, Segment::Wildcard).unwrap();
+        assert!(!path.contains_variable("id"));
+        path.push_index_variable("id").unwrap();
+    }
+
+    #[test]
+    fn test_from_env_key() {
+        let path = Structpath::from_env_key("SERVER__PORT", "__");
+        assert_eq!(format!("{}", path), "$SERVER.PORT");
+
+        let mut config = serde_json::json!({"SERVER": {"HOST": "localhost"}});
+        let config = path
+            .write(Some(&mut config), serde_json::json!(8080), None)
+            .unwrap();
+        assert_eq!(
+            config,
+            serde_json::json!({"SERVER": {"HOST": "localhost", "PORT": 8080}})
+        );
+
+        assert_eq!(
+            format!("{}", Structpath::from_env_key("__A____B__", "__")),
+            "$A.B"
+        );
+        assert_eq!(format!("{}", Structpath::from_env_key("A_B", "")), "$A_B");
+
+        let path = Structpath::from_env_key("SERVERS__0__HOST", "__");
+        assert_eq!(
+            path.segments()[1],
+            Segment::Key(SegmentKey::String("0".into()))
+        );
+        let path = Structpath::from_env_key_indexed("SERVERS__0__HOST", "__");
+        assert_eq!(format!("{}", path), "$SERVERS[0].HOST");
+    }
+
+    #[test]
+    fn test_walk_from() {
+        let data = serde_json::json!({
+            "users": [{"name": "Alice"}],
+            "other": {"x": 1}
+        });
+        let start = Structpath::parse("$users").unwrap();
+
+        let paths: Vec<String> = start
+            .walk_from(&data)
+            .unwrap()
+            .map(|(path, _)| format!("{}", path))
+            .collect();
+
+        assert_eq!(paths, vec!["$users[0].name", "$users[0]", "$users"]);
+
+        let missing = Structpath::parse("$missing").unwrap();
+        assert!(matches!(
+            missing.walk_from(&data),
+            Err(StructpathError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_get_requires_normalized_path() {
+        let data = serde_json::json!({"a": {"c": 1}});
+        let path = Structpath::parse_with("a.b..c", &navigation()).unwrap();
+
+        assert!(matches!(
+            path.get(&data, None),
+            Err(StructpathError::UnnormalizedPath)
+        ));
+        assert_eq!(
+            path.normalize().unwrap().get(&data, None).unwrap(),
+            &serde_json::json!(1)
+        );
+    }
+}
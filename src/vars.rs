@@ -0,0 +1,140 @@
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// The values bound to the variables of a path, by variable name.
+///
+/// Contexts can be built up step by step, e.g. by resolving a shallow path
+/// with `iter_variables`, converting its bindings and merging them into the
+/// context used for a deeper path.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VarContext {
+    values: HashMap<String, String>,
+}
+
+impl VarContext {
+    pub fn new() -> Self {
+        VarContext::default()
+    }
+
+    /// Returns the context with `name` bound to `value`, replacing any
+    /// earlier value.
+    #[must_use]
+    pub fn with(mut self, name: &str, value: impl ToString) -> Self {
+        self.insert(name, value);
+        self
+    }
+
+    pub fn insert(&mut self, name: &str, value: impl ToString) {
+        self.values.insert(name.to_string(), value.to_string());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// Adds the bindings of `other`, whose values win for names bound in
+    /// both.
+    pub fn merge(&mut self, other: &VarContext) {
+        self.values.extend(
+            other
+                .values
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone())),
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn as_map(&self) -> &HashMap<String, String> {
+        &self.values
+    }
+}
+
+impl From<HashMap<String, String>> for VarContext {
+    fn from(values: HashMap<String, String>) -> Self {
+        VarContext { values }
+    }
+}
+
+/// Converts the bindings yielded by `iter_variables`: strings are used as
+/// they are and other values (index numbers) in their JSON form.
+impl From<HashMap<String, Value>> for VarContext {
+    fn from(bindings: HashMap<String, Value>) -> Self {
+        bindings
+            .into_iter()
+            .map(|(name, value)| match value {
+                Value::String(s) => (name, s),
+                other => (name, other.to_string()),
+            })
+            .collect()
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for VarContext {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        VarContext {
+            values: iter
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Structpath;
+    use serde_json::json;
+
+    #[test]
+    fn test_build_context_incrementally() {
+        let data = json!({
+            "teams": {
+                "red": {"members": [{"name": "Ann"}, {"name": "Bo"}]},
+                "blue": {"members": [{"name": "Cy"}]}
+            }
+        });
+
+        let team = Structpath::parse("$teams.#team").unwrap();
+        let (_, bindings) = team
+            .iter_variables(&data)
+            .find(|(_, bindings)| bindings["team"] == json!("red"))
+            .unwrap();
+
+        let mut vars = VarContext::from(bindings);
+        vars.merge(&VarContext::new().with("idx", 1));
+        assert_eq!(vars.get("team"), Some("red"));
+        assert_eq!(vars.len(), 2);
+
+        let name =
+            Structpath::parse("$teams.#team.members[#idx].name").unwrap();
+        assert_eq!(name.get(&data, Some(&vars)).unwrap(), &json!("Bo"));
+
+        vars.merge(&VarContext::new().with("team", "blue").with("idx", 0));
+        assert_eq!(name.get(&data, Some(&vars)).unwrap(), &json!("Cy"));
+    }
+
+    #[test]
+    fn test_context_conversions() {
+        let mut map = HashMap::new();
+        map.insert("a".to_string(), "1".to_string());
+        assert_eq!(VarContext::from(map), VarContext::new().with("a", "1"));
+
+        let mut bindings = HashMap::new();
+        bindings.insert("i".to_string(), json!(3));
+        bindings.insert("k".to_string(), json!("x"));
+        let vars = VarContext::from(bindings);
+        assert_eq!((vars.get("i"), vars.get("k")), (Some("3"), Some("x")));
+
+        let vars: VarContext = [("a", "b")].into_iter().collect();
+        assert_eq!(vars.get("a"), Some("b"));
+        assert_eq!(vars.get("b"), None);
+    }
+}
@@ -1,96 +1,206 @@
+use crate::target::PathTarget;
 use crate::types::Structpath;
 use serde_json::Value;
 use std::collections::VecDeque;
 
-pub fn new_walker(data: &Value) -> impl Iterator<Item = (Structpath, &Value)> {
-    Walker::new(&Structpath::new(), data)
+pub fn new_walker<T: PathTarget>(data: &T) -> Walker<'_, T> {
+    Walker::new(&Structpath::root(), data)
+}
+
+pub fn new_walker_limited<T: PathTarget>(
+    data: &T,
+    max_nodes: usize,
+) -> Walker<'_, T> {
+    new_walker(data).with_max_nodes(max_nodes)
+}
+
+/// Walks `data` as the subtree found at `path`, prefixing every yielded path
+/// with it.
+pub fn new_walker_from<'a, T: PathTarget>(
+    path: &Structpath,
+    data: &'a T,
+) -> Walker<'a, T> {
+    Walker::new(path, data)
+}
+
+/// The kind of a node yielded by a walk, with the number of children of a
+/// container.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Object { len: usize },
+    Array { len: usize },
+    Scalar,
+}
+
+impl NodeKind {
+    pub fn of<T: PathTarget>(value: &T) -> NodeKind {
+        if let Some(arr) = value.as_array() {
+            NodeKind::Array { len: arr.len() }
+        } else if let Some(len) = value.object_len() {
+            NodeKind::Object { len }
+        } else {
+            NodeKind::Scalar
+        }
+    }
+
+    /// The name of the kind: "object", "array" or "scalar".
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeKind::Object { .. } => "object",
+            NodeKind::Array { .. } => "array",
+            NodeKind::Scalar => "scalar",
+        }
+    }
 }
 
 /// A state item for the Walker's traversal stack
-#[derive(Clone)]
-struct WalkerItem<'a> {
+struct WalkerItem<'a, T> {
     path: Structpath,
-    value: &'a Value,
+    value: &'a T,
     processed: bool,
 }
 
+// Implemented by hand since deriving would require `T: Clone`
+impl<T> Clone for WalkerItem<'_, T> {
+    fn clone(&self) -> Self {
+        WalkerItem {
+            path: self.path.clone(),
+            value: self.value,
+            processed: self.processed,
+        }
+    }
+}
+
 /// An iterator that walks through a JSON-like data structure depth-first
-pub struct Walker<'a> {
-    stack: VecDeque<WalkerItem<'a>>,
+pub struct Walker<'a, T = Value> {
+    root: Structpath,
+    stack: VecDeque<WalkerItem<'a, T>>,
+    /// The most nodes to yield, if limited
+    max_nodes: Option<usize>,
+    yielded: usize,
+    truncated: bool,
 }
 
-impl<'a> Walker<'a> {
+// Cloning forks the traversal: the clone continues from the same position
+// independently. Implemented by hand since deriving would require `T: Clone`.
+impl<T> Clone for Walker<'_, T> {
+    fn clone(&self) -> Self {
+        Walker {
+            root: self.root.clone(),
+            stack: self.stack.clone(),
+            max_nodes: self.max_nodes,
+            yielded: self.yielded,
+            truncated: self.truncated,
+        }
+    }
+}
+
+impl<'a, T: PathTarget> Walker<'a, T> {
     /// Create a new Walker to iterate over the data starting from the given path
-    pub fn new(_path: &Structpath, data: &'a Value) -> Self {
-        let mut stack = VecDeque::new();
-        stack.push_back(WalkerItem {
-            path: Structpath::new(),
+    pub fn new(path: &Structpath, data: &'a T) -> Self {
+        let mut walker = Walker {
+            root: path.clone(),
+            stack: VecDeque::new(),
+            max_nodes: None,
+            yielded: 0,
+            truncated: false,
+        };
+        walker.reset(data);
+        walker
+    }
+
+    /// Stop the walk after `max_nodes` nodes have been yielded
+    pub fn with_max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    /// Whether the walk ended at the node limit with nodes left unvisited
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Restart the walk over `data`, keeping the path it started from
+    pub fn reset(&mut self, data: &'a T) {
+        self.yielded = 0;
+        self.truncated = false;
+        self.stack.clear();
+        self.stack.push_back(WalkerItem {
+            path: self.root.clone(),
             value: data,
             processed: false,
         });
-        Walker { stack }
     }
 }
 
-impl<'a> Iterator for Walker<'a> {
-    type Item = (Structpath, &'a Value);
+impl<'a, T: PathTarget> Iterator for Walker<'a, T> {
+    type Item = (Structpath, &'a T);
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.max_nodes == Some(self.yielded) {
+            self.truncated = !self.stack.is_empty();
+            return None;
+        }
+
+        let node = self.next_node()?;
+        self.yielded += 1;
+        Some(node)
+    }
+}
+
+impl<'a, T: PathTarget> Walker<'a, T> {
+    fn next_node(&mut self) -> Option<(Structpath, &'a T)> {
         if let Some(mut item) = self.stack.pop_front() {
             if !item.processed {
                 // Mark as processed and push back to the stack
                 item.processed = true;
 
                 // Process children before revisiting this node
-                match item.value {
-                    Value::Object(map) => {
-                        // Push the current item back to the stack to be returned later
-                        self.stack.push_front(item.clone());
-
-                        // Then push all children to be processed first (in reverse order)
-                        let mut entries: Vec<_> = map.iter().collect();
-                        // Reverse to maintain expected traversal order
-                        entries.reverse();
-
-                        for (key, value) in entries {
-                            let mut new_path = item.path.clone();
-                            if let Ok(int_key) = key.parse::<i64>() {
-                                new_path.push_int_key(int_key);
-                            } else {
-                                new_path.push_string_key(key);
-                            }
-
-                            self.stack.push_front(WalkerItem {
-                                path: new_path,
-                                value,
-                                processed: false,
-                            });
-                        }
-                    }
-                    Value::Array(arr) => {
-                        // Push the current item back to the stack to be returned later
-                        self.stack.push_front(item.clone());
-
-                        // Then push all array items to be processed first (in reverse order)
-                        for (idx, value) in arr.iter().enumerate().rev() {
-                            let mut new_path = item.path.clone();
-                            new_path.push_index(idx);
-
-                            self.stack.push_front(WalkerItem {
-                                path: new_path,
-                                value,
-                                processed: false,
-                            });
+                if let Some(entries) = item.value.entries() {
+                    // Push the current item back to the stack to be returned later
+                    self.stack.push_front(item.clone());
+
+                    // Then push all children to be processed first (in reverse order)
+                    // Reverse to maintain expected traversal order
+                    let mut entries: Vec<_> = entries.collect();
+                    entries.reverse();
+
+                    for (key, value) in entries {
+                        let mut new_path = item.path.clone();
+                        if let Ok(int_key) = key.parse::<i64>() {
+                            new_path.push_int_key(int_key);
+                        } else {
+                            new_path.push_string_key(&key);
                         }
+
+                        self.stack.push_front(WalkerItem {
+                            path: new_path,
+                            value,
+                            processed: false,
+                        });
                     }
-                    _ => {
-                        // For scalar values, just return the item directly
-                        return Some((item.path, item.value));
+                } else if let Some(arr) = item.value.as_array() {
+                    // Push the current item back to the stack to be returned later
+                    self.stack.push_front(item.clone());
+
+                    // Then push all array items to be processed first (in reverse order)
+                    for (idx, value) in arr.iter().enumerate().rev() {
+                        let mut new_path = item.path.clone();
+                        new_path.push_index(idx);
+
+                        self.stack.push_front(WalkerItem {
+                            path: new_path,
+                            value,
+                            processed: false,
+                        });
                     }
+                } else {
+                    // For scalar values, just return the item directly
+                    return Some((item.path, item.value));
                 }
 
                 // Get the next item
-                return self.next();
+                return self.next_node();
             } else {
                 // Item has been processed, return it
                 return Some((item.path, item.value));
@@ -268,6 +378,64 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_walker_from_path() {
+        let data = json!({"a": 1, "b": 2});
+        let start = crate::parse::parse("$users[3]").unwrap();
+
+        let paths: Vec<String> = new_walker_from(&start, &data)
+            .map(|(path, _)| format!("{}", path))
+            .collect();
+
+        assert_eq!(paths, vec!["$users[3].a", "$users[3].b", "$users[3]"]);
+    }
+
+    #[test]
+    fn test_walker_clone_and_reset() {
+        let data = json!({"a": [1, 2], "b": 3});
+        let other = json!([true]);
+        let all: Vec<_> = new_walker(&data).collect();
+
+        let mut walker = new_walker(&data);
+        walker.next();
+        walker.next();
+
+        let fork = walker.clone();
+        assert_eq!(fork.collect::<Vec<_>>(), all[2..]);
+
+        // The original is unaffected by consuming the clone
+        assert_eq!(walker.next(), Some(all[2].clone()));
+
+        walker.reset(&data);
+        assert_eq!(walker.collect::<Vec<_>>(), all);
+
+        let mut walker = new_walker(&data);
+        walker.reset(&other);
+        let paths: Vec<String> =
+            walker.map(|(path, _)| format!("{}", path)).collect();
+        assert_eq!(paths, vec!["$[0]", "$"]);
+    }
+
+    #[test]
+    fn test_walker_limited() {
+        let data = json!({"items": (0..1000).collect::<Vec<_>>()});
+        let total = new_walker(&data).count();
+
+        let mut walker = new_walker_limited(&data, 10);
+        let results: Vec<_> = walker.by_ref().collect();
+        assert_eq!(results.len(), 10);
+        assert_eq!(results, new_walker(&data).take(10).collect::<Vec<_>>());
+        assert!(walker.is_truncated());
+
+        let mut walker = new_walker_limited(&data, total);
+        assert_eq!(walker.by_ref().count(), total);
+        assert!(!walker.is_truncated());
+
+        walker.reset(&data);
+        assert!(!walker.is_truncated());
+        assert_eq!(walker.count(), total);
+    }
+
     #[test]
     fn test_walker_with_empty_structures() {
         // Empty object
@@ -385,4 +553,53 @@ mod tests {
             &&json!(1)
         );
     }
+
+    #[test]
+    fn test_node_kinds() {
+        let data = json!({"a": {"b": [1, {"c": null}], "d": "x"}, "e": []});
+
+        let kinds: Vec<(String, NodeKind)> = Structpath::walk_with_kinds(&data)
+            .map(|(path, _, kind)| (path.to_string(), kind))
+            .collect();
+
+        let kind_of = |path: &str| {
+            kinds
+                .iter()
+                .find(|(p, _)| p == path)
+                .map(|(_, kind)| *kind)
+                .unwrap()
+        };
+        assert_eq!(kinds.len(), 8);
+        assert_eq!(kind_of("$"), NodeKind::Object { len: 2 });
+        assert_eq!(kind_of("$a"), NodeKind::Object { len: 2 });
+        assert_eq!(kind_of("$a.b"), NodeKind::Array { len: 2 });
+        assert_eq!(kind_of("$a.b[0]"), NodeKind::Scalar);
+        assert_eq!(kind_of("$a.b[1]"), NodeKind::Object { len: 1 });
+        assert_eq!(kind_of("$a.b[1].c"), NodeKind::Scalar);
+        assert_eq!(kind_of("$a.d"), NodeKind::Scalar);
+        assert_eq!(kind_of("$e"), NodeKind::Array { len: 0 });
+        assert_eq!(kind_of("$e").name(), "array");
+    }
+
+    #[test]
+    fn test_walk_root_paths() {
+        let data = json!({"users": [{"name": "Ann"}], "count": 1});
+
+        let nodes: Vec<_> = new_walker(&data).collect();
+        let (root, value) = nodes.last().unwrap();
+        assert_eq!(*root, Structpath::root());
+        assert_eq!(root.len(), 0);
+        assert!(root.is_empty());
+        assert_eq!(root.to_string(), "$");
+        assert_eq!(*value, &data);
+        assert!(nodes[..nodes.len() - 1].iter().all(|(p, _)| !p.is_empty()));
+
+        let users = Structpath::parse("$users").unwrap();
+        let nodes: Vec<_> = users.walk_from(&data).unwrap().collect();
+        let paths: Vec<String> =
+            nodes.iter().map(|(path, _)| path.to_string()).collect();
+        assert_eq!(paths, ["$users[0].name", "$users[0]", "$users"]);
+        assert_eq!(nodes.last().unwrap().0.len(), 1);
+        assert_eq!(*nodes.last().unwrap().1, data["users"]);
+    }
 }
@@ -1,19 +1,398 @@
-use crate::types::{Segment, SegmentKey, Structpath, StructpathError};
+use crate::access::{
+    concrete_key, find_value, nth_entry, resolve_segments, GetOptions,
+};
+use crate::types::{
+    Segment, SegmentKey, SegmentKind, Structpath, StructpathError,
+};
 use serde_json::{Map, Value};
+use std::borrow::Cow;
 use std::collections::HashMap;
 
+/// Options controlling how `write_with` treats existing data.
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// Fail instead of replacing a root that is a scalar other than null
+    /// with the container the first segment needs. Off by default, where
+    /// the scalar is replaced and reported in `WriteOutcome::replaced_root`.
+    pub strict_root: bool,
+}
+
+/// The result of `write_with`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteOutcome {
+    /// The new document, when no data was given. Given data is updated in
+    /// place and not copied, so this is `None` for it.
+    pub value: Option<Value>,
+    /// The scalar root that was replaced by a container, if any
+    pub replaced_root: Option<Value>,
+}
+
+/// Writes `value` at `path` and returns the whole document after the write.
+/// Given data is updated in place and then copied for the result, which
+/// `write_in_place` and `write_with` avoid.
 pub fn write(
     path: &Structpath,
     data: Option<&mut Value>,
     value: Value,
     vars: Option<&HashMap<String, String>>,
 ) -> Result<Value, StructpathError> {
-    let mut root_value = match &data {
-        Some(d) => (*d).clone(),
-        None => Value::Null,
+    let options = WriteOptions::default();
+    match data {
+        Some(data) => {
+            write_with(path, Some(&mut *data), value, vars, &options)?;
+            Ok(data.clone())
+        }
+        None => write_with(path, None, value, vars, &options)
+            .map(|outcome| outcome.value.unwrap_or_default()),
+    }
+}
+
+pub fn write_with(
+    path: &Structpath,
+    data: Option<&mut Value>,
+    value: Value,
+    vars: Option<&HashMap<String, String>>,
+    options: &WriteOptions,
+) -> Result<WriteOutcome, StructpathError> {
+    let mut owned_root = Value::Null;
+    let is_borrowed = data.is_some();
+    let root_value = match data {
+        Some(d) => d,
+        None => &mut owned_root,
+    };
+
+    // An empty path replaces the document by design, so only a write below
+    // the root can discard a scalar root
+    let replaced_root = match (path.segments().first(), &*root_value) {
+        (None, _) | (_, Value::Null | Value::Array(_) | Value::Object(_)) => {
+            None
+        }
+        (Some(first), scalar) if options.strict_root => {
+            let expected = match first.kind() {
+                SegmentKind::Index
+                | SegmentKind::FindValue
+                | SegmentKind::IndexVariable
+                | SegmentKind::IndexWildcard => "an array root",
+                _ => "an object root",
+            };
+            return Err(StructpathError::InvalidPath {
+                expected: expected.to_string(),
+                found: format!("the scalar root {}", scalar),
+            });
+        }
+        (Some(_), scalar) => Some(scalar.clone()),
     };
-    let mut_ref = &mut root_value;
 
+    write_in_place(path, root_value, value, vars)?;
+
+    Ok(WriteOutcome {
+        value: (!is_borrowed).then(|| std::mem::take(root_value)),
+        replaced_root,
+    })
+}
+
+/// Writes like `write`, also returning the concrete paths of the nodes that
+/// did not exist before, from the outermost to the written node itself.
+/// Nodes that existed, even as null or as a scalar replaced by a container,
+/// are not listed, nor are the nulls padding an array up to an index.
+pub fn write_audited(
+    path: &Structpath,
+    data: Option<&mut Value>,
+    value: Value,
+    vars: Option<&HashMap<String, String>>,
+) -> Result<(Value, Vec<Structpath>), StructpathError> {
+    let before = data.as_deref().unwrap_or(&Value::Null);
+    let pinned = pin_occurrences(path, before, vars)?;
+    let created = missing_paths(&pinned, before, vars);
+    let result = write(path, data, value, vars)?;
+    Ok((result, created))
+}
+
+/// Follows `path` through `data` and returns the concrete paths of the
+/// nodes on it that are missing. Stops at a segment that cannot be made
+/// concrete, for which the write itself fails.
+fn missing_paths(
+    path: &Structpath,
+    data: &Value,
+    vars: Option<&HashMap<String, String>>,
+) -> Vec<Structpath> {
+    let mut concrete = Structpath::new();
+    let mut missing = Vec::new();
+    let mut current = Some(data);
+    for segment in path.segments() {
+        let slot = current.and_then(|node| slot_for(node, segment, vars));
+        let concrete_segment = match (segment, &slot) {
+            (Segment::Key(_) | Segment::Index(_), _) => segment.clone(),
+            (_, Some(Slot::Key(key))) => concrete_key(key),
+            (_, Some(Slot::Index(idx))) => Segment::Index(*idx),
+            (Segment::KeyVariable(var_name), None) => {
+                match resolve_key_variable(vars, var_name) {
+                    Ok(key) => concrete_key(&key),
+                    Err(_) => break,
+                }
+            }
+            (Segment::IndexVariable(var_name), None) => {
+                match resolve_index_variable(vars, var_name) {
+                    Ok(idx) => Segment::Index(idx),
+                    Err(_) => break,
+                }
+            }
+            _ => break,
+        };
+        // Concrete segments never contain variables, so this cannot fail
+        let _ = concrete.push_segment(concrete_segment);
+
+        current = match (current, slot) {
+            (Some(node), Some(slot)) => slot.get(node),
+            _ => None,
+        };
+        if current.is_none() {
+            missing.push(concrete.clone());
+        }
+    }
+    missing
+}
+
+/// Writes `value` at `path` inside `data`, like `write`, without copying
+/// the whole document. The walk follows the objects and arrays that already
+/// exist and only stages the child where they end, so the cost depends on
+/// the path rather than the size of `data`. As with `write`, an error
+/// leaves `data` untouched.
+pub fn write_in_place(
+    path: &Structpath,
+    data: &mut Value,
+    value: Value,
+    vars: Option<&HashMap<String, String>>,
+) -> Result<(), StructpathError> {
+    check_writable(path, vars)?;
+    let path = pin_occurrences(path, data, vars)?;
+    let segments = path.segments();
+    if segments.is_empty() {
+        *data = value;
+        return Ok(());
+    }
+
+    // Existing containers on the way are never replaced, so the write can
+    // only change the node where they end
+    let mut depth = 0;
+    let mut current = &*data;
+    while depth + 1 < segments.len() {
+        let child = slot_for(current, &segments[depth], vars)
+            .and_then(|slot| slot.get(current));
+        match child {
+            Some(child) if child.is_object() || child.is_array() => {
+                current = child;
+                depth += 1;
+            }
+            _ => break,
+        }
+    }
+
+    let node = resolve_mut(&segments[..depth], data, vars)?;
+    let rest = &segments[depth + 1..];
+    match slot_for(node, &segments[depth], vars) {
+        Some(slot) => {
+            let child = match rest.first() {
+                None => value,
+                Some(next) => {
+                    // The walk stopped here, so the child is missing or a
+                    // scalar and cheap to stage
+                    let mut child =
+                        slot.get(node).cloned().unwrap_or(Value::Null);
+                    if let Slot::Key(_) = slot {
+                        prepare_child(&mut child, next)?;
+                    }
+                    write_into(rest, &mut child, value, vars)?;
+                    child
+                }
+            };
+            slot.set(node, child);
+        }
+        // The segment does not fit the node, which leads to an error or,
+        // for a scalar root, to replacing it
+        None => {
+            let mut staged = node.clone();
+            write_into(&segments[depth..], &mut staged, value, vars)?;
+            *node = staged;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the node at `path` mutably. Fails with the same errors as `get`
+/// when the path does not resolve.
+pub fn get_mut<'a>(
+    path: &Structpath,
+    data: &'a mut Value,
+    vars: Option<&HashMap<String, String>>,
+) -> Result<&'a mut Value, StructpathError> {
+    crate::access::get(path, &*data, vars)?;
+    let path = pin_occurrences(path, data, vars)?;
+    resolve_mut(path.segments(), data, vars)
+}
+
+/// Returns the node at `path` mutably, writing `default()` there first if
+/// the path is missing. Errors other than a missing key or index are passed
+/// on without calling `default`.
+pub fn get_mut_or_insert_with<'a>(
+    path: &Structpath,
+    data: &'a mut Value,
+    default: impl FnOnce() -> Value,
+    vars: Option<&HashMap<String, String>>,
+) -> Result<&'a mut Value, StructpathError> {
+    match crate::access::get(path, &*data, vars) {
+        Ok(_) => {}
+        Err(
+            StructpathError::NotFound | StructpathError::IndexOutOfBounds(_),
+        ) => {
+            write_in_place(path, data, default(), vars)?;
+        }
+        Err(err) => return Err(err),
+    }
+
+    let path = pin_occurrences(path, data, vars)?;
+    resolve_mut(path.segments(), data, vars)
+}
+
+/// Removes the node at `path` from its parent object or array, returning
+/// whether there was anything to remove. A missing node, or a missing parent,
+/// is not an error.
+pub fn unset(
+    path: &Structpath,
+    data: &mut Value,
+    vars: Option<&HashMap<String, String>>,
+) -> Result<bool, StructpathError> {
+    if path.segments().is_empty() {
+        return Err(StructpathError::InvalidPath {
+            expected: "a path below the root".to_string(),
+            found: "the root".to_string(),
+        });
+    }
+    let path = match pin_occurrences(path, data, vars) {
+        Ok(path) => path,
+        Err(StructpathError::IndexOutOfBounds(_)) => return Ok(false),
+        Err(err) => return Err(err),
+    };
+    let last = &path.segments()[path.segments().len() - 1];
+
+    let parent_path = path.head(path.segments().len() - 1);
+    match crate::access::get(&parent_path, &*data, vars) {
+        Ok(_) => {}
+        Err(
+            StructpathError::NotFound | StructpathError::IndexOutOfBounds(_),
+        ) => return Ok(false),
+        Err(err) => return Err(err),
+    }
+    let parent = resolve_mut(parent_path.segments(), data, vars)?;
+
+    let key = match last {
+        Segment::Key(SegmentKey::String(key)) => Some(key.clone()),
+        Segment::Key(SegmentKey::Int(key)) => Some(key.to_string()),
+        Segment::KeyVariable(var_name) => {
+            Some(resolve_key_variable(vars, var_name)?)
+        }
+        Segment::NthKeyVariable(var_name, n) => {
+            match nth_key(&*parent, var_name, *n) {
+                Ok(key) => Some(key),
+                Err(StructpathError::IndexOutOfBounds(_)) => return Ok(false),
+                Err(err) => return Err(err),
+            }
+        }
+        Segment::Index(_)
+        | Segment::FindValue(_)
+        | Segment::IndexVariable(_) => None,
+        Segment::Wildcard | Segment::IndexWildcard | Segment::KeyPattern(_) => {
+            return Err(StructpathError::UnexpectedWildcard);
+        }
+        Segment::Parent => return Err(StructpathError::UnnormalizedPath),
+        Segment::Length => return Err(StructpathError::UnexpectedLength),
+    };
+
+    match (key, parent) {
+        (Some(key), Value::Object(map)) => Ok(map.remove(&key).is_some()),
+        (None, Value::Array(arr)) => {
+            let idx = match last {
+                Segment::IndexVariable(var_name) => {
+                    resolve_index_variable(vars, var_name)?
+                }
+                Segment::Index(idx) => *idx,
+                Segment::FindValue(value) => {
+                    match arr.iter().position(|element| element == value) {
+                        Some(idx) => idx,
+                        None => return Ok(false),
+                    }
+                }
+                _ => unreachable!("only index segments have no key"),
+            };
+            if idx < arr.len() {
+                arr.remove(idx);
+                Ok(true)
+            } else {
+                Ok(false)
+            }
+        }
+        (key, other) => Err(StructpathError::InvalidPath {
+            expected: if key.is_some() { "object" } else { "array" }
+                .to_string(),
+            found: format!("{:?}", other),
+        }),
+    }
+}
+
+/// Follows a path that is known to resolve, returning the node mutably.
+fn resolve_mut<'a>(
+    segments: &[Segment],
+    data: &'a mut Value,
+    vars: Option<&HashMap<String, String>>,
+) -> Result<&'a mut Value, StructpathError> {
+    let mut current = data;
+    for segment in segments {
+        let next = match segment {
+            Segment::Key(SegmentKey::String(key)) => current.get_mut(key),
+            Segment::Key(SegmentKey::Int(key)) => {
+                current.get_mut(key.to_string())
+            }
+            Segment::Index(idx) => current.get_mut(*idx),
+            Segment::FindValue(value) => {
+                let (idx, _) = find_value(&*current, value)?;
+                current.get_mut(idx)
+            }
+            Segment::KeyVariable(var_name) => {
+                let key = resolve_key_variable(vars, var_name)?;
+                current.get_mut(key)
+            }
+            Segment::IndexVariable(var_name) => {
+                let idx = resolve_index_variable(vars, var_name)?;
+                current.get_mut(idx)
+            }
+            Segment::NthKeyVariable(var_name, n) => {
+                let key = nth_key(&*current, var_name, *n)?;
+                current.get_mut(key)
+            }
+            Segment::Wildcard
+            | Segment::IndexWildcard
+            | Segment::KeyPattern(_) => {
+                return Err(StructpathError::UnexpectedWildcard);
+            }
+            Segment::Parent => {
+                return Err(StructpathError::UnnormalizedPath);
+            }
+            Segment::Length => {
+                return Err(StructpathError::UnexpectedLength);
+            }
+        };
+        current = next.ok_or(StructpathError::NotFound)?;
+    }
+
+    Ok(current)
+}
+
+/// Checks the conditions every write needs: a variable context if the path
+/// has variables, and no parent segments.
+fn check_writable(
+    path: &Structpath,
+    vars: Option<&HashMap<String, String>>,
+) -> Result<(), StructpathError> {
     let has_variables = path.segments().iter().any(|segment| {
         matches!(segment, Segment::KeyVariable(_) | Segment::IndexVariable(_))
     });
@@ -25,12 +404,26 @@ pub fn write(
         ));
     }
 
-    if path.segments().is_empty() {
+    if path.segments().contains(&Segment::Parent) {
+        return Err(StructpathError::UnnormalizedPath);
+    }
+
+    Ok(())
+}
+
+/// Writes `value` at `segments` below `mut_ref`, creating containers on the
+/// way. The caller has checked the path with `check_writable`.
+fn write_into(
+    segments: &[Segment],
+    mut_ref: &mut Value,
+    value: Value,
+    vars: Option<&HashMap<String, String>>,
+) -> Result<(), StructpathError> {
+    if segments.is_empty() {
         *mut_ref = value;
-        return Ok(root_value);
+        return Ok(());
     }
 
-    let segments = path.segments().to_vec();
     let segments_len = segments.len();
 
     let mut current = mut_ref;
@@ -43,32 +436,33 @@ pub fn write(
                 Segment::Index(idx) => {
                     write_by_index(current, *idx, value)?;
                 }
+                Segment::FindValue(literal) => {
+                    let (idx, _) = find_value(&*current, literal)?;
+                    write_by_index(current, idx, value)?;
+                }
                 Segment::KeyVariable(var_name) => {
-                    let variables = vars.unwrap();
-                    let var_value =
-                        variables.get(var_name).ok_or_else(|| {
-                            StructpathError::MissingVariable(var_name.clone())
-                        })?;
-
-                    write_by_key(
-                        current,
-                        &SegmentKey::String(var_value.clone()),
-                        value,
-                    )?;
+                    let key = resolve_key_variable(vars, var_name)?;
+                    write_by_key(current, &SegmentKey::String(key), value)?;
                 }
                 Segment::IndexVariable(var_name) => {
-                    let variables = vars.unwrap();
-                    let var_value =
-                        variables.get(var_name).ok_or_else(|| {
-                            StructpathError::MissingVariable(var_name.clone())
-                        })?;
-
-                    let idx = var_value.parse::<usize>().map_err(|_| {
-                        StructpathError::InvalidVariableValue(var_value.clone())
-                    })?;
-
+                    let idx = resolve_index_variable(vars, var_name)?;
                     write_by_index(current, idx, value)?;
                 }
+                Segment::NthKeyVariable(var_name, n) => {
+                    let key = nth_key(&*current, var_name, *n)?;
+                    write_by_key(current, &SegmentKey::String(key), value)?;
+                }
+                Segment::Wildcard
+                | Segment::IndexWildcard
+                | Segment::KeyPattern(_) => {
+                    return Err(StructpathError::UnexpectedWildcard);
+                }
+                Segment::Parent => {
+                    return Err(StructpathError::UnnormalizedPath);
+                }
+                Segment::Length => {
+                    return Err(StructpathError::UnexpectedLength);
+                }
             }
             break;
         }
@@ -82,106 +476,267 @@ pub fn write(
                 current =
                     ensure_array_index_exists(current, *idx, &segments[i + 1])?;
             }
+            Segment::FindValue(literal) => {
+                let (idx, _) = find_value(&*current, literal)?;
+                current =
+                    ensure_array_index_exists(current, idx, &segments[i + 1])?;
+            }
             Segment::KeyVariable(var_name) => {
-                let variables = vars.unwrap();
-                let var_value = variables.get(var_name).ok_or_else(|| {
-                    StructpathError::MissingVariable(var_name.clone())
-                })?;
-
+                let key = resolve_key_variable(vars, var_name)?;
                 current = ensure_next_segment_exists(
                     current,
-                    &SegmentKey::String(var_value.clone()),
+                    &SegmentKey::String(key),
                     &segments[i + 1],
                 )?;
             }
             Segment::IndexVariable(var_name) => {
-                let variables = vars.unwrap();
-                let var_value = variables.get(var_name).ok_or_else(|| {
-                    StructpathError::MissingVariable(var_name.clone())
-                })?;
-
-                let idx = var_value.parse::<usize>().map_err(|_| {
-                    StructpathError::InvalidVariableValue(var_value.clone())
-                })?;
-
+                let idx = resolve_index_variable(vars, var_name)?;
                 current =
                     ensure_array_index_exists(current, idx, &segments[i + 1])?;
             }
+            Segment::NthKeyVariable(var_name, n) => {
+                let key = nth_key(&*current, var_name, *n)?;
+                current = ensure_next_segment_exists(
+                    current,
+                    &SegmentKey::String(key),
+                    &segments[i + 1],
+                )?;
+            }
+            Segment::Wildcard
+            | Segment::IndexWildcard
+            | Segment::KeyPattern(_) => {
+                return Err(StructpathError::UnexpectedWildcard);
+            }
+            Segment::Parent => {
+                return Err(StructpathError::UnnormalizedPath);
+            }
+            Segment::Length => {
+                return Err(StructpathError::UnexpectedLength);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces each occurrence-selecting key variable in `path` with the key
+/// `get` follows there in `data`, so that a write lands where a read looks:
+/// the `n`th key the rest of the path resolves under. The walk stops where
+/// `data` ends, and an occurrence below that point is left for the write to
+/// fail on, as the containers it creates are empty.
+fn pin_occurrences<'p>(
+    path: &'p Structpath,
+    data: &Value,
+    vars: Option<&HashMap<String, String>>,
+) -> Result<Cow<'p, Structpath>, StructpathError> {
+    let segments = path.segments();
+    if !segments
+        .iter()
+        .any(|segment| matches!(segment, Segment::NthKeyVariable(..)))
+    {
+        return Ok(Cow::Borrowed(path));
+    }
+    check_writable(path, vars)?;
+
+    let options = GetOptions::default();
+    let mut pinned = segments.to_vec();
+    let mut current = data;
+    for (i, segment) in segments.iter().enumerate() {
+        current = match segment {
+            Segment::NthKeyVariable(var_name, n) => {
+                let rest = &segments[i + 1..];
+                let (key, child) =
+                    nth_entry(current, var_name, *n, |_, child| {
+                        resolve_segments(rest, child, vars, &options).is_ok()
+                    })?;
+                pinned[i] = concrete_key(&key);
+                child
+            }
+            _ => {
+                let step = std::slice::from_ref(segment);
+                match resolve_segments(step, current, vars, &options) {
+                    Ok(child) => child,
+                    Err(_) => break,
+                }
+            }
+        };
+    }
+    Structpath::try_from_iter(pinned).map(Cow::Owned)
+}
+
+/// The `n`th key of `node`, for an occurrence `pin_occurrences` left in
+/// place because the walk through the data did not reach it.
+fn nth_key(
+    node: &Value,
+    var_name: &str,
+    n: usize,
+) -> Result<String, StructpathError> {
+    nth_entry(node, var_name, n, |_, _| true).map(|(key, _)| key)
+}
+
+/// Looks up the value bound to a key variable.
+fn resolve_key_variable(
+    vars: Option<&HashMap<String, String>>,
+    var_name: &str,
+) -> Result<String, StructpathError> {
+    vars.and_then(|variables| variables.get(var_name))
+        .cloned()
+        .ok_or_else(|| StructpathError::MissingVariable(var_name.to_string()))
+}
+
+/// Looks up the value bound to an index variable and parses it as an index.
+fn resolve_index_variable(
+    vars: Option<&HashMap<String, String>>,
+    var_name: &str,
+) -> Result<usize, StructpathError> {
+    let var_value = resolve_key_variable(vars, var_name)?;
+    var_value
+        .parse::<usize>()
+        .map_err(|_| StructpathError::InvalidVariableValue(var_value))
+}
+
+/// Where a segment lands in an existing object or array.
+enum Slot {
+    Key(String),
+    Index(usize),
+}
+
+impl Slot {
+    fn get<'a>(&self, node: &'a Value) -> Option<&'a Value> {
+        match self {
+            Slot::Key(key) => node.get(key),
+            Slot::Index(idx) => node.get(*idx),
+        }
+    }
+
+    /// Stores `child` in the slot, padding an array with nulls up to it.
+    fn set(self, node: &mut Value, child: Value) {
+        match (self, node) {
+            (Slot::Key(key), Value::Object(map)) => {
+                map.insert(key, child);
+            }
+            (Slot::Index(idx), Value::Array(arr)) => {
+                if idx >= arr.len() {
+                    arr.resize(idx + 1, Value::Null);
+                }
+                arr[idx] = child;
+            }
+            _ => {}
         }
     }
+}
 
-    if let Some(original_data) = data {
-        *original_data = root_value.clone();
+/// Resolves `segment` to the key or index it addresses in `node`, if `node`
+/// is the kind of container the segment applies to and any variable it uses
+/// is bound. The slot itself may be empty.
+fn slot_for(
+    node: &Value,
+    segment: &Segment,
+    vars: Option<&HashMap<String, String>>,
+) -> Option<Slot> {
+    match (node, segment) {
+        (Value::Object(_), Segment::Key(SegmentKey::String(key))) => {
+            Some(Slot::Key(key.clone()))
+        }
+        (Value::Object(_), Segment::Key(SegmentKey::Int(key))) => {
+            Some(Slot::Key(key.to_string()))
+        }
+        (Value::Object(_), Segment::KeyVariable(var_name)) => {
+            resolve_key_variable(vars, var_name).ok().map(Slot::Key)
+        }
+        (Value::Object(_), Segment::NthKeyVariable(var_name, n)) => {
+            nth_key(node, var_name, *n).ok().map(Slot::Key)
+        }
+        (Value::Array(_), Segment::Index(idx)) => Some(Slot::Index(*idx)),
+        (Value::Array(_), Segment::IndexVariable(var_name)) => {
+            resolve_index_variable(vars, var_name).ok().map(Slot::Index)
+        }
+        (Value::Array(_), Segment::FindValue(literal)) => {
+            find_value(node, literal)
+                .ok()
+                .map(|(idx, _)| Slot::Index(idx))
+        }
+        _ => None,
     }
+}
+
+/// Scalars and nulls on the way are replaced by the container a segment
+/// needs, but an existing container of the other kind is an error rather
+/// than being clobbered: a key never turns an array into an object.
+fn check_container(
+    data: &Value,
+    want_object: bool,
+) -> Result<(), StructpathError> {
+    let (expected, found) = match data {
+        Value::Array(_) if want_object => ("object", "array"),
+        Value::Object(_) if !want_object => ("array", "object"),
+        _ => return Ok(()),
+    };
 
-    Ok(root_value)
+    Err(StructpathError::InvalidPath {
+        expected: expected.to_string(),
+        found: found.to_string(),
+    })
 }
 
 fn ensure_next_segment_exists<'a>(
     data: &'a mut Value,
     key: &SegmentKey,
-    _next_segment: &Segment,
+    next_segment: &Segment,
 ) -> Result<&'a mut Value, StructpathError> {
     let key_str = match key {
         SegmentKey::String(s) => s.clone(),
         SegmentKey::Int(i) => i.to_string(),
     };
 
-    match data {
-        Value::Object(map) => {
-            // Create key if it doesn't exist
-            if !map.contains_key(&key_str) {
-                // Insert null instead of empty object/array
-                map.insert(key_str.clone(), Value::Null);
-            }
-
-            // Now get the value to return
-            let value = map.get_mut(&key_str).unwrap();
-
-            // Based on the next segment, ensure the correct container type
-            match _next_segment {
-                Segment::Key(_) | Segment::KeyVariable(_) => {
-                    // Need an object for the next segment
-                    if !value.is_object() {
-                        *value = Value::Object(Map::new());
-                    }
-                }
-                Segment::Index(_) | Segment::IndexVariable(_) => {
-                    // Need an array for the next segment
-                    if !value.is_array() {
-                        *value = Value::Array(Vec::new());
-                    }
-                }
-            }
+    // Convert to an object if it's not one already
+    if !data.is_object() {
+        check_container(data, true)?;
+        *data = Value::Object(Map::new());
+    }
+    let Value::Object(map) = data else {
+        return Err(StructpathError::InvalidPath {
+            expected: "object".to_string(),
+            found: format!("{:?}", data),
+        });
+    };
 
-            Ok(map.get_mut(&key_str).unwrap())
-        }
-        _ => {
-            // Convert to an object if it's not one already
-            let mut map = Map::new();
+    // Insert null instead of empty object/array, then give it the container
+    // the next segment needs
+    let value = map.entry(key_str).or_insert(Value::Null);
+    prepare_child(value, next_segment)?;
 
-            // Create the appropriate container based on the next segment
-            match _next_segment {
-                Segment::Key(_) | Segment::KeyVariable(_) => {
-                    map.insert(key_str.clone(), Value::Object(Map::new()));
-                }
-                Segment::Index(_) | Segment::IndexVariable(_) => {
-                    map.insert(key_str.clone(), Value::Array(Vec::new()));
-                }
-            }
+    Ok(value)
+}
 
-            *data = Value::Object(map);
+/// Gives a child reached through a key the container `next_segment` needs,
+/// replacing a scalar or null.
+fn prepare_child(
+    value: &mut Value,
+    next_segment: &Segment,
+) -> Result<(), StructpathError> {
+    let needs_array = match next_segment {
+        Segment::Key(_)
+        | Segment::KeyVariable(_)
+        | Segment::NthKeyVariable(_, _)
+        | Segment::Wildcard
+        | Segment::KeyPattern(_)
+        | Segment::Parent
+        | Segment::Length => false,
+        Segment::Index(_)
+        | Segment::FindValue(_)
+        | Segment::IndexVariable(_)
+        | Segment::IndexWildcard => true,
+    };
 
-            if let Value::Object(map) = data {
-                Ok(map.get_mut(&key_str).unwrap())
-            } else {
-                Err(StructpathError::InvalidPath {
-                    expected: "object".to_string(),
-                    found: format!("{:?}", data),
-                })
-            }
-        }
+    if needs_array && !value.is_array() {
+        check_container(value, false)?;
+        *value = Value::Array(Vec::new());
+    } else if !needs_array && !value.is_object() {
+        check_container(value, true)?;
+        *value = Value::Object(Map::new());
     }
+    Ok(())
 }
 
 fn ensure_array_index_exists<'a>(
@@ -198,6 +753,8 @@ fn ensure_array_index_exists<'a>(
             Ok(&mut arr[idx])
         }
         _ => {
+            check_container(data, false)?;
+
             let mut new_arr = Vec::new();
 
             for _ in 0..=idx {
@@ -234,6 +791,8 @@ fn write_by_key(
             Ok(())
         }
         _ => {
+            check_container(data, true)?;
+
             let mut map = Map::new();
             map.insert(key_str, value);
             *data = Value::Object(map);
@@ -256,6 +815,8 @@ fn write_by_index(
             Ok(())
         }
         _ => {
+            check_container(data, false)?;
+
             let mut new_arr = Vec::new();
 
             for _ in 0..idx {
@@ -268,3 +829,641 @@ fn write_by_index(
         }
     }
 }
+
+/// Applies an RFC 7386 JSON Merge Patch to `target`: objects in the patch
+/// are merged key by key, a null member deletes the key, and any other patch
+/// value replaces the target outright.
+pub fn apply_merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch_map) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(target_map) = target else {
+        unreachable!("target was just made an object");
+    };
+
+    for (key, patch_value) in patch_map {
+        if patch_value.is_null() {
+            target_map.remove(key);
+        } else {
+            let entry = target_map.entry(key.clone()).or_insert(Value::Null);
+            apply_merge_patch(entry, patch_value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{self, parse};
+    use crate::vars::VarContext;
+    use serde_json::json;
+
+    #[test]
+    fn test_failed_write_leaves_data_untouched() {
+        let vars: HashMap<String, String> =
+            [("i".to_string(), "foo".to_string())].into();
+        let original = json!({"a": {"b": [1, {"c": null}]}, "d": "e"});
+
+        for path_str in [
+            "$x.y.z[#i].w",
+            "$a.b[#i]",
+            "$new.deep.#missing.leaf",
+            "$new.deep.#k{3}.leaf",
+            "$a.b[0].*.c",
+        ] {
+            let path = parse(path_str).unwrap();
+            let mut data = original.clone();
+            let before = serde_json::to_string(&data).unwrap();
+
+            let result = write(&path, Some(&mut data), json!(42), Some(&vars));
+
+            assert!(result.is_err(), "{} should fail", path_str);
+            assert_eq!(serde_json::to_string(&data).unwrap(), before);
+        }
+    }
+
+    #[test]
+    fn test_write_root_updates_data() {
+        let mut data = json!({"a": 1});
+        let result = write(&Structpath::new(), Some(&mut data), json!(2), None);
+
+        assert_eq!(result.unwrap(), json!(2));
+        assert_eq!(data, json!(2));
+    }
+
+    #[test]
+    fn test_write_invalid_index_variable_value() {
+        let vars: HashMap<String, String> =
+            [("i".to_string(), "foo".to_string())].into();
+
+        for path_str in ["$a.b[#i]", "$a.b[#i].c"] {
+            let path = parse(path_str).unwrap();
+            let mut data = json!({"x": 1});
+
+            let result = write(&path, Some(&mut data), json!(42), Some(&vars));
+
+            match result {
+                Err(StructpathError::InvalidVariableValue(value)) => {
+                    assert_eq!(value, "foo")
+                }
+                other => panic!("{}: unexpected {:?}", path_str, other),
+            }
+            assert_eq!(data, json!({"x": 1}));
+        }
+    }
+
+    #[test]
+    fn test_apply_merge_patch_rfc_examples() {
+        // The test cases from RFC 7386, Appendix A
+        let cases = [
+            (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+            (
+                json!({"a": "b"}),
+                json!({"b": "c"}),
+                json!({"a": "b", "b": "c"}),
+            ),
+            (json!({"a": "b"}), json!({"a": null}), json!({})),
+            (
+                json!({"a": "b", "b": "c"}),
+                json!({"a": null}),
+                json!({"b": "c"}),
+            ),
+            (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+            (json!({"a": "c"}), json!({"a": ["b"]}), json!({"a": ["b"]})),
+            (
+                json!({"a": {"b": "c"}}),
+                json!({"a": {"b": "d", "c": null}}),
+                json!({"a": {"b": "d"}}),
+            ),
+            (
+                json!({"a": [{"b": "c"}]}),
+                json!({"a": [1]}),
+                json!({"a": [1]}),
+            ),
+            (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+            (json!({"a": "b"}), json!(["c"]), json!(["c"])),
+            (json!({"a": "foo"}), json!(null), json!(null)),
+            (json!({"a": "foo"}), json!("bar"), json!("bar")),
+            (
+                json!({"e": null}),
+                json!({"a": 1}),
+                json!({"e": null, "a": 1}),
+            ),
+            (
+                json!([1, 2]),
+                json!({"a": "b", "c": null}),
+                json!({"a": "b"}),
+            ),
+            (
+                json!({}),
+                json!({"a": {"bb": {"ccc": null}}}),
+                json!({"a": {"bb": {}}}),
+            ),
+        ];
+
+        for (original, patch, expected) in cases {
+            let mut data = original.clone();
+            apply_merge_patch(&mut data, &patch);
+            assert_eq!(data, expected, "{} patched with {}", original, patch);
+        }
+    }
+
+    #[test]
+    fn test_write_does_not_clobber_other_container() {
+        let original = json!({"a": [1, 2], "b": {"c": 3}, "d": "x"});
+
+        for (path_str, expected, found) in [
+            ("$a.k", "object", "array"),
+            ("$a.k.z", "object", "array"),
+            ("$b[0]", "array", "object"),
+            ("$b[0].z", "array", "object"),
+        ] {
+            let path = parse(path_str).unwrap();
+            let mut data = original.clone();
+
+            match write(&path, Some(&mut data), json!(42), None) {
+                Err(StructpathError::InvalidPath {
+                    expected: e,
+                    found: f,
+                }) => {
+                    assert_eq!((e.as_str(), f.as_str()), (expected, found))
+                }
+                other => panic!("{}: unexpected {:?}", path_str, other),
+            }
+            assert_eq!(data, original);
+        }
+
+        // Scalars are still replaced
+        let path = parse("$d.k").unwrap();
+        let mut data = original.clone();
+        write(&path, Some(&mut data), json!(42), None).unwrap();
+        assert_eq!(data["d"], json!({"k": 42}));
+    }
+
+    #[test]
+    fn test_write_nth_key_variable_where_get_reads() {
+        let data = json!({
+            "users": {"amy": {"name": "Amy"}, "bob": {"age": 25}}
+        });
+
+        // "amy" has no age, so the first occurrence with one is "bob"
+        let path = parse("$users.#id{0}.age").unwrap();
+        let mut written = data.clone();
+        write(&path, Some(&mut written), json!(26), None).unwrap();
+        assert_eq!(written["users"]["bob"]["age"], json!(26));
+        assert_eq!(crate::access::get(&path, &written, None).unwrap(), 26);
+
+        assert!(unset(&path, &mut written, None).unwrap());
+        assert_eq!(written["users"]["bob"], json!({}));
+
+        // No occurrence has the rest of the path, so nothing to write to
+        let path = parse("$users.#id{0}.email").unwrap();
+        let mut unchanged = data.clone();
+        let result = write(&path, Some(&mut unchanged), json!("x"), None);
+        assert!(matches!(result, Err(StructpathError::IndexOutOfBounds(_))));
+        assert_eq!(unchanged, data);
+        assert!(!unset(&path, &mut unchanged, None).unwrap());
+    }
+
+    #[test]
+    fn test_write_audited() {
+        let path = parse("$a.b[1].#k.d").unwrap();
+        let mut data = json!({"a": {"x": 1}});
+        let vars: HashMap<String, String> =
+            [("k".to_string(), "c".to_string())].into();
+
+        let (result, created) =
+            write_audited(&path, Some(&mut data), json!(5), Some(&vars))
+                .unwrap();
+
+        assert_eq!(
+            result,
+            json!({"a": {"x": 1, "b": [null, {"c": {"d": 5}}]}})
+        );
+        let created: Vec<String> =
+            created.iter().map(Structpath::to_string).collect();
+        assert_eq!(created, ["$a.b", "$a.b[1]", "$a.b[1].c", "$a.b[1].c.d"]);
+
+        // Int-like keys from variables come out as the paths `resolved_path`
+        // gives for the written node
+        let path = parse("$a.#k.b").unwrap();
+        let vars: HashMap<String, String> =
+            [("k".to_string(), "7".to_string())].into();
+        let mut data = json!({"a": {}});
+        let (_, created) =
+            write_audited(&path, Some(&mut data), json!(1), Some(&vars))
+                .unwrap();
+        let resolved =
+            crate::access::resolved_path(&path, &data, Some(&vars)).unwrap();
+        assert_eq!(created.last(), Some(&resolved));
+        assert_eq!(created, [parse("$a.7").unwrap(), parse("$a.7.b").unwrap()]);
+
+        // Existing nodes are not listed, even null or replaced scalars
+        let mut data = json!({"a": {"b": null, "s": 1}});
+        let path = parse("$a.b").unwrap();
+        let (_, created) =
+            write_audited(&path, Some(&mut data), json!(1), None).unwrap();
+        assert!(created.is_empty());
+        let path = parse("$a.s.t").unwrap();
+        let (_, created) =
+            write_audited(&path, Some(&mut data), json!(1), None).unwrap();
+        assert_eq!(created, [parse("$a.s.t").unwrap()]);
+
+        let (result, created) =
+            write_audited(&parse("$[0].x").unwrap(), None, json!(1), None)
+                .unwrap();
+        assert_eq!(result, json!([{"x": 1}]));
+        assert_eq!(created.len(), 2);
+
+        let path = parse("$a.#missing").unwrap();
+        assert!(matches!(
+            write_audited(&path, Some(&mut data), json!(1), Some(&vars)),
+            Err(StructpathError::MissingVariable(_))
+        ));
+    }
+
+    #[test]
+    fn test_write_in_place() {
+        let path = parse("$a.b[2].c").unwrap();
+        let mut data = json!({"a": {"b": [{"c": 1}, 5, 7], "x": [1, 2]}});
+
+        write_in_place(&path, &mut data, json!(3), None).unwrap();
+        assert_eq!(
+            data,
+            json!({"a": {"b": [{"c": 1}, 5, {"c": 3}], "x": [1, 2]}})
+        );
+
+        // The same document as a copying write, for every kind of step
+        let cases = [
+            ("$a.b[0].c", json!(9)),
+            ("$a.b[5]", json!(9)),
+            ("$a.b[4].d.e", json!(9)),
+            ("$a.new.deep[1]", json!(9)),
+            ("$a.x[=2]", json!(9)),
+            ("$a.#k.y", json!(9)),
+            ("$a.#k{0}[1]", json!(9)),
+            ("$a.b[#i].z", json!(9)),
+        ];
+        let vars: HashMap<String, String> = [
+            ("k".to_string(), "x".to_string()),
+            ("i".to_string(), "1".to_string()),
+        ]
+        .into();
+        for (path_str, value) in cases {
+            let path = parse(path_str).unwrap();
+            let mut expected = data.clone();
+            let copied =
+                write(&path, Some(&mut expected), value.clone(), Some(&vars));
+            let mut in_place = data.clone();
+            let written =
+                write_in_place(&path, &mut in_place, value, Some(&vars));
+            match copied {
+                Ok(_) => {
+                    written.unwrap();
+                    assert_eq!(in_place, expected, "{}", path_str);
+                }
+                Err(err) => assert_eq!(
+                    written.unwrap_err().to_string(),
+                    err.to_string(),
+                    "{}",
+                    path_str
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_in_place_error_leaves_data_untouched() {
+        let original = json!({"a": {"b": 1, "list": [1]}});
+        let failing = [
+            "$a.b.c[0].x[=1]",
+            "$a.list.x",
+            "$a.list[=5].y",
+            "$a.b.#missing",
+            "$a.*.x",
+            "$a.new.length",
+        ];
+        for path_str in failing {
+            let path = parse(path_str).unwrap();
+            let mut data = original.clone();
+            let vars = HashMap::new();
+            assert!(
+                write_in_place(&path, &mut data, json!(0), Some(&vars))
+                    .is_err(),
+                "{}",
+                path_str
+            );
+            assert_eq!(data, original, "{}", path_str);
+        }
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let path = parse("$users[=\"b\"]").unwrap();
+        let mut data = json!({"users": ["a", "b"], "other": {"x": 1}});
+
+        *get_mut(&path, &mut data, None).unwrap() = json!("c");
+        assert_eq!(data, json!({"users": ["a", "c"], "other": {"x": 1}}));
+
+        let path = parse("$users[5]").unwrap();
+        assert!(matches!(
+            get_mut(&path, &mut data, None),
+            Err(StructpathError::IndexOutOfBounds(_))
+        ));
+        let path = parse("$other.x.y").unwrap();
+        assert!(matches!(
+            get_mut(&path, &mut data, None),
+            Err(StructpathError::InvalidPath { .. })
+        ));
+    }
+
+    #[test]
+    fn test_get_mut_or_insert_with_present() {
+        let path = parse("$cache.#k").unwrap();
+        let vars: HashMap<String, String> =
+            [("k".to_string(), "a".to_string())].into();
+        let mut data = json!({"cache": {"a": [1]}});
+
+        let node = get_mut_or_insert_with(
+            &path,
+            &mut data,
+            || panic!("default must not be called"),
+            Some(&vars),
+        )
+        .unwrap();
+        node.as_array_mut().unwrap().push(json!(2));
+
+        assert_eq!(data, json!({"cache": {"a": [1, 2]}}));
+    }
+
+    #[test]
+    fn test_get_mut_or_insert_with_absent() {
+        let path = parse("$cache.b[1]").unwrap();
+        let mut data = json!({"cache": {"a": [1]}});
+        let mut calls = 0;
+
+        let node = get_mut_or_insert_with(
+            &path,
+            &mut data,
+            || {
+                calls += 1;
+                json!({"hits": 0})
+            },
+            None,
+        )
+        .unwrap();
+        node["hits"] = json!(1);
+
+        assert_eq!(calls, 1);
+        assert_eq!(
+            data,
+            json!({"cache": {"a": [1], "b": [null, {"hits": 1}]}})
+        );
+
+        // Other errors are passed on without inserting
+        let path = parse("$cache.a.x").unwrap();
+        let result =
+            get_mut_or_insert_with(&path, &mut data, || json!(0), None);
+        assert!(matches!(result, Err(StructpathError::InvalidPath { .. })));
+    }
+
+    #[test]
+    fn test_write_root_type_follows_first_segment() {
+        let cases = [
+            ("$0", json!({"0": "v"})),
+            ("$[0]", json!(["v"])),
+            ("$#k", json!({"0": "v"})),
+            ("$[#i]", json!(["v"])),
+        ];
+        let vars: HashMap<String, String> = [
+            ("k".to_string(), "0".to_string()),
+            ("i".to_string(), "0".to_string()),
+        ]
+        .into();
+
+        for (path_str, expected) in cases {
+            let path = parse(path_str).unwrap();
+            let mut data = Value::Null;
+            write(&path, Some(&mut data), json!("v"), Some(&vars)).unwrap();
+            assert_eq!(data, expected, "{}", path_str);
+        }
+    }
+
+    #[test]
+    fn test_write_creates_array_before_index() {
+        let vars: HashMap<String, String> = [
+            ("i".to_string(), "0".to_string()),
+            ("j".to_string(), "1".to_string()),
+        ]
+        .into();
+        let cases = [
+            ("$a[#i].b", json!({"a": [{"b": "v"}]})),
+            ("$a[0].b", json!({"a": [{"b": "v"}]})),
+            ("$a[#i][#j]", json!({"a": [[null, "v"]]})),
+            ("$a.b[#j].c", json!({"a": {"b": [null, {"c": "v"}]}})),
+        ];
+
+        for (path_str, expected) in cases {
+            let path = parse(path_str).unwrap();
+            for empty in [Value::Null, json!({})] {
+                let mut data = empty;
+                write(&path, Some(&mut data), json!("v"), Some(&vars)).unwrap();
+                assert_eq!(data, expected, "{}", path_str);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unset_vs_write_null() {
+        let mut data = json!({"a": {"b": 1, "c": 2}, "l": [1, 2, 3]});
+
+        let path = parse("$a.b").unwrap();
+        write(&path, Some(&mut data), Value::Null, None).unwrap();
+        assert_eq!(path.get(&data, None).unwrap(), &Value::Null);
+
+        assert!(unset(&path, &mut data, None).unwrap());
+        assert!(matches!(
+            path.get(&data, None),
+            Err(StructpathError::NotFound)
+        ));
+        assert!(!unset(&path, &mut data, None).unwrap());
+
+        let vars: HashMap<String, String> =
+            [("i".to_string(), "1".to_string())].into();
+        let path = parse("$l[#i]").unwrap();
+        assert!(unset(&path, &mut data, Some(&vars)).unwrap());
+        assert!(!unset(&parse("$l[5]").unwrap(), &mut data, None).unwrap());
+        assert!(!unset(&parse("$x.y").unwrap(), &mut data, None).unwrap());
+        assert_eq!(data, json!({"a": {"c": 2}, "l": [1, 3]}));
+
+        assert!(matches!(
+            unset(&parse("$l.k").unwrap(), &mut data, None),
+            Err(StructpathError::InvalidPath { .. })
+        ));
+        assert!(unset(&Structpath::new(), &mut data, None).is_err());
+    }
+
+    #[test]
+    fn test_write_into_scalar_root() {
+        let path = parse("$a").unwrap();
+
+        let mut data = json!(5);
+        let outcome = write_with(
+            &path,
+            Some(&mut data),
+            json!(1),
+            None,
+            &WriteOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(outcome.value, None);
+        assert_eq!(outcome.replaced_root, Some(json!(5)));
+        assert_eq!(data, json!({"a": 1}));
+
+        let outcome =
+            write_with(&path, None, json!(1), None, &WriteOptions::default())
+                .unwrap();
+        assert_eq!(outcome.value, Some(json!({"a": 1})));
+
+        let strict = WriteOptions { strict_root: true };
+        let mut data = json!("text");
+        let result = write_with(
+            &parse("$[0]").unwrap(),
+            Some(&mut data),
+            json!(1),
+            None,
+            &strict,
+        );
+        assert!(matches!(
+            result,
+            Err(StructpathError::InvalidPath { expected, .. })
+                if expected == "an array root"
+        ));
+        assert_eq!(data, json!("text"));
+
+        // Null, containers and whole-document writes replace nothing
+        for mut data in [Value::Null, json!({"b": 2})] {
+            let outcome =
+                write_with(&path, Some(&mut data), json!(1), None, &strict)
+                    .unwrap();
+            assert_eq!(outcome.replaced_root, None);
+        }
+        let mut data = json!(5);
+        let outcome = write_with(
+            &Structpath::new(),
+            Some(&mut data),
+            json!(1),
+            None,
+            &strict,
+        )
+        .unwrap();
+        assert_eq!(outcome.replaced_root, None);
+        assert_eq!(data, json!(1));
+    }
+
+    /// A small xorshift generator, so the random inputs below are the same
+    /// on every run.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+
+        fn value(&mut self, depth: usize) -> Value {
+            const KEYS: [&str; 6] = ["a", "b", "0", "1", "", "x.y"];
+            let kinds = if depth == 0 { 5 } else { 7 };
+            match self.below(kinds) {
+                0 => Value::Null,
+                1 => json!(self.below(2) == 0),
+                2 => json!(self.below(3)),
+                3 => json!(-1.5),
+                4 => json!(KEYS[self.below(KEYS.len())]),
+                5 => {
+                    (0..self.below(4)).map(|_| self.value(depth - 1)).collect()
+                }
+                _ => (0..self.below(4))
+                    .map(|_| {
+                        let key = KEYS[self.below(KEYS.len())].to_string();
+                        (key, self.value(depth - 1))
+                    })
+                    .collect::<Map<_, _>>()
+                    .into(),
+            }
+        }
+
+        fn path_str(&mut self) -> String {
+            const TOKENS: [&str; 20] = [
+                ".a",
+                ".b",
+                ".0",
+                "[0]",
+                "[1]",
+                "[5]",
+                ".*",
+                "[*]",
+                ".#k",
+                "[#i]",
+                "..",
+                ".length",
+                "[#]",
+                "[=1]",
+                r#"[="a"]"#,
+                ".#n{1}",
+                "\\",
+                "[",
+                "]",
+                ".a?",
+            ];
+            let mut path_str = "$".to_string();
+            for _ in 0..self.below(5) {
+                path_str.push_str(TOKENS[self.below(TOKENS.len())]);
+            }
+            path_str
+        }
+    }
+
+    #[test]
+    fn test_random_inputs_do_not_panic() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let navigation = parse::ParseOptions { navigation: true };
+        let mut vars = VarContext::new();
+
+        for _ in 0..5000 {
+            let data = rng.value(3);
+            let path_str = rng.path_str();
+            vars.insert("k", "a");
+            vars.insert("i", if rng.below(4) == 0 { "x" } else { "1" });
+
+            let _ = Structpath::walk(&data).count();
+            let paths =
+                [parse(&path_str), parse::parse_with(&path_str, &navigation)];
+            for path in paths.into_iter().flatten() {
+                for vars in [None, Some(&vars)] {
+                    let _ = path.get(&data, vars);
+                    let _ = path.get_value(&data, vars);
+                    let _ = path.get_all(&data, vars);
+                    let _ = path.trace_get(&data, vars);
+                    let _ = path.resolved_path(&data, vars);
+                    let _ = path.write(Some(&mut data.clone()), json!(1), vars);
+                    let _ = path.unset(&mut data.clone(), vars);
+                }
+                let _ = path.iter_variables(&data).count();
+                let _ = path.normalize();
+                if let Ok(Ok(walker)) =
+                    path.normalize().map(|start| start.walk_from(&data))
+                {
+                    let _ = walker.count();
+                }
+            }
+        }
+    }
+}